@@ -0,0 +1,173 @@
+use crate::{
+    bitboard::BitBoard,
+    board::Board,
+    eval::{pawn_attacks, piece_attacks},
+    movegen::{attackers_to, precalc::PRECALC},
+    piece::{Color, Piece, PieceKind, PIECE_KINDS},
+    see::see_value,
+};
+
+// Static facts about a position meant for training overlays: who attacks
+// what, which pieces can be taken for free and which can't move freely.
+// Nothing is searched, a hanging piece may still be tactically safe.
+pub struct PositionAnalysis {
+    // Number of pieces of each color attacking every square, by color
+    pub attackers: [[u8; 64]; 2],
+    // Cheapest attacker of every square, by color
+    cheapest: [[Option<PieceKind>; 64]; 2],
+    // Squares a color attacks more often than the other, by color
+    pub controlled: [BitBoard; 2],
+    // Pieces attacked and either undefended or attacked by something
+    // cheaper
+    pub hanging: BitBoard,
+    // Pieces of both colors standing between their king and an enemy slider
+    pub pinned: BitBoard,
+    // Pieces giving check to the side to move
+    pub checkers: BitBoard,
+}
+
+impl PositionAnalysis {
+    pub fn new(board: &Board) -> Self {
+        let mut attackers = [[0; 64]; 2];
+        let mut cheapest = [[None; 64]; 2];
+
+        for color in [Color::White, Color::Black] {
+            // Cheapest kinds first so the first one to reach a square is
+            // its cheapest attacker
+            for kind in PIECE_KINDS {
+                let mut pieces = board.board(Piece::new(kind, color)).0;
+                while pieces != 0 {
+                    let sq = pieces.trailing_zeros() as usize;
+                    pieces &= pieces - 1;
+
+                    let mut attacks = match kind {
+                        PieceKind::Pawn => pawn_attacks(1 << sq, color),
+                        _ => piece_attacks(kind, sq, board.occ).0,
+                    };
+                    while attacks != 0 {
+                        let target = attacks.trailing_zeros() as usize;
+                        attacks &= attacks - 1;
+                        attackers[color as usize][target] += 1;
+                        cheapest[color as usize][target].get_or_insert(kind);
+                    }
+                }
+            }
+        }
+
+        let mut controlled = [BitBoard(0); 2];
+        for (sq, (white, black)) in attackers[0].iter().zip(attackers[1]).enumerate() {
+            if *white > black {
+                controlled[0].set(sq as u64);
+            } else if black > *white {
+                controlled[1].set(sq as u64);
+            }
+        }
+
+        let mut hanging = BitBoard(0);
+        for sq in 0..64u64 {
+            let Some(piece) = board.piece(sq) else {
+                continue;
+            };
+            if piece.kind == PieceKind::King {
+                continue;
+            }
+            let (own, enemy) = (piece.color as usize, piece.color.opposite() as usize);
+            let Some(attacker) = cheapest[enemy][sq as usize] else {
+                continue;
+            };
+            if attackers[own][sq as usize] == 0 || see_value(attacker) < see_value(piece.kind) {
+                hanging.set(sq);
+            }
+        }
+
+        let king = board
+            .board(Piece::new(PieceKind::King, board.side_to_move))
+            .0;
+        let checkers = if king == 0 {
+            BitBoard(0)
+        } else {
+            attackers_to(board, king.trailing_zeros() as u64, board.occ)
+                & color_occ(board, board.side_to_move.opposite())
+        };
+
+        Self {
+            attackers,
+            cheapest,
+            controlled,
+            hanging,
+            pinned: pinned(board, Color::White) | pinned(board, Color::Black),
+            checkers,
+        }
+    }
+
+    // How many enemy pieces attack the piece on `square`
+    pub fn attacked(&self, board: &Board, square: usize) -> u8 {
+        board
+            .piece(square as u64)
+            .map(|p| self.attackers[p.color.opposite() as usize][square])
+            .unwrap_or(0)
+    }
+
+    // How many friendly pieces protect the piece on `square`
+    pub fn defended(&self, board: &Board, square: usize) -> u8 {
+        board
+            .piece(square as u64)
+            .map(|p| self.attackers[p.color as usize][square])
+            .unwrap_or(0)
+    }
+
+    pub fn cheapest_attacker(&self, color: Color, square: usize) -> Option<PieceKind> {
+        self.cheapest[color as usize][square]
+    }
+}
+
+fn color_occ(board: &Board, color: Color) -> BitBoard {
+    match color {
+        Color::White => board.w_occ,
+        Color::Black => board.b_occ,
+    }
+}
+
+// Pieces of `color` pinned to their own king
+fn pinned(board: &Board, color: Color) -> BitBoard {
+    let king = board.board(Piece::new(PieceKind::King, color)).0;
+    if king == 0 {
+        return BitBoard(0);
+    }
+    let king = king.trailing_zeros() as usize;
+    let enemy = color.opposite();
+    let enemy_occ = color_occ(board, enemy);
+    let queens = *board.board(Piece::new(PieceKind::Queen, enemy));
+
+    // Sliders that would attack the king if only enemy pieces were on the
+    // board, with exactly one friendly piece in between
+    let mut pinned = BitBoard(0);
+    for (rook_like, sliders) in [
+        (
+            true,
+            *board.board(Piece::new(PieceKind::Rook, enemy)) | queens,
+        ),
+        (
+            false,
+            *board.board(Piece::new(PieceKind::Bishop, enemy)) | queens,
+        ),
+    ] {
+        let attack = |sq: usize, occ: BitBoard| match rook_like {
+            true => PRECALC.rook_attack(sq, occ),
+            false => PRECALC.bishop_attack(sq, occ),
+        };
+
+        let mut snipers = (attack(king, enemy_occ) & sliders).0;
+        while snipers != 0 {
+            let sniper = snipers.trailing_zeros() as usize;
+            snipers &= snipers - 1;
+
+            let between = attack(king, BitBoard(1 << sniper)) & attack(sniper, BitBoard(1 << king));
+            let blockers = between & board.occ;
+            if blockers.0.count_ones() == 1 {
+                pinned |= blockers;
+            }
+        }
+    }
+    pinned
+}
@@ -0,0 +1,174 @@
+use chess_lib::{
+    board::Board,
+    book::{encode_move, keys::book_key, Book, BookEntry},
+    movegen::san::from_san,
+    piece::Color,
+};
+use std::collections::HashMap;
+
+// Builds a Polyglot opening book from a PGN collection. Every move played
+// in the first plies of a game is weighted like Polyglot does it, two points
+// for each win and one for each draw of the side that played it.
+//
+// Usage: book <games.pgn> [output=book.bin] [max plies=16] [min games=1]
+
+struct PgnGame {
+    // Points for white: 2, 1 or 0
+    result: Option<u32>,
+    moves: Vec<String>,
+}
+
+fn parse_result(s: &str) -> Option<u32> {
+    match s {
+        "1-0" => Some(2),
+        "1/2-1/2" => Some(1),
+        "0-1" => Some(0),
+        _ => None,
+    }
+}
+
+// "12.e4" and "12...e5" without the move number. Only digits followed by
+// dots are one, "0-0" and results stay whole.
+fn strip_move_number(token: &str) -> &str {
+    let rest = token.trim_start_matches(|c: char| c.is_ascii_digit());
+    if rest.len() < token.len() && rest.starts_with('.') {
+        rest.trim_start_matches('.')
+    } else {
+        token
+    }
+}
+
+// Drops comments, variations, annotations and move numbers
+fn movetext_tokens(text: &str) -> Vec<String> {
+    let mut clean = String::new();
+    let mut comment = false;
+    let mut line_comment = false;
+    let mut variation = 0;
+    for c in text.chars() {
+        match c {
+            '{' if !line_comment => comment = true,
+            '}' if comment => comment = false,
+            ';' if !comment => line_comment = true,
+            '\n' if line_comment => line_comment = false,
+            '(' if !comment && !line_comment => variation += 1,
+            ')' if !comment && !line_comment => variation -= 1,
+            _ if comment || line_comment || variation > 0 => {}
+            _ => clean.push(c),
+        }
+        if matches!(c, '}' | ')' | '\n') {
+            clean.push(' ');
+        }
+    }
+
+    clean
+        .split_whitespace()
+        .map(strip_move_number)
+        .filter(|t| !t.is_empty() && !t.starts_with('$') && parse_result(t).is_none() && *t != "*")
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_pgn(pgn: &str) -> Vec<PgnGame> {
+    let mut games = vec![];
+    let mut result = None;
+    let mut movetext = String::new();
+
+    let mut finish = |result: &mut Option<u32>, movetext: &mut String| {
+        if !movetext.trim().is_empty() {
+            games.push(PgnGame {
+                result: result.take(),
+                moves: movetext_tokens(movetext),
+            });
+        }
+        movetext.clear();
+    };
+
+    for line in pgn.lines() {
+        let line = line.trim();
+        if let Some(tag) = line.strip_prefix('[') {
+            finish(&mut result, &mut movetext);
+            if let Some(value) = tag.strip_prefix("Result \"") {
+                result = parse_result(value.trim_end_matches(['"', ']']));
+            }
+        } else {
+            movetext += line;
+            movetext.push('\n');
+        }
+    }
+    finish(&mut result, &mut movetext);
+    games
+}
+
+fn main() {
+    let args: Vec<_> = std::env::args().collect();
+    if args.len() < 2 {
+        panic!("Usage: book <games.pgn> [output] [max plies] [min games]");
+    }
+    let output = args.get(2).map(String::as_str).unwrap_or("book.bin");
+    let max_plies: usize = args.get(3).map(|p| p.parse().unwrap()).unwrap_or(16);
+    let min_games: u32 = args.get(4).map(|g| g.parse().unwrap()).unwrap_or(1);
+
+    let pgn = std::fs::read_to_string(&args[1]).expect("Failed to read PGN");
+    let games = parse_pgn(&pgn);
+    println!("Loaded {} games", games.len());
+
+    // (key, move) -> (games, points)
+    let mut stats: HashMap<(u64, u16), (u32, u32)> = HashMap::new();
+    for game in games.iter() {
+        let mut board = Board::start_pos();
+        for san in game.moves.iter().take(max_plies) {
+            let Some(m) = from_san(&board, san) else {
+                eprintln!("Skipping the rest of a game at illegal move {}", san);
+                break;
+            };
+
+            let points = match (game.result, board.side_to_move) {
+                (Some(r), Color::White) => r,
+                (Some(r), Color::Black) => 2 - r,
+                (None, _) => 1,
+            };
+            let entry = stats
+                .entry((book_key(&board), encode_move(&m)))
+                .or_default();
+            entry.0 += 1;
+            entry.1 += points;
+
+            board.make_move(&m);
+        }
+    }
+
+    // Scale weights into u16, moves that never scored still get picked rarely
+    let max = stats.values().map(|(_, p)| *p).max().unwrap_or(1).max(1);
+    let scale = (u16::MAX as f64 / max as f64).min(1.);
+    let entries = stats
+        .into_iter()
+        .filter(|(_, (games, _))| *games >= min_games)
+        .map(|((key, mv), (_, points))| BookEntry {
+            key,
+            mv,
+            weight: ((points as f64 * scale) as u16).max(1),
+            learn: 0,
+        })
+        .collect::<Vec<_>>();
+
+    let book = Book::from_entries(entries);
+    std::fs::write(output, book.to_bytes()).expect("Failed to write book");
+    println!("Wrote {} entries to {}", book.len(), output);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_numbers_and_results() {
+        let tokens =
+            movetext_tokens("1.e4 e5 2. Nf3 {best} Nc6 3.Bb5 a6 4.0-0 (4.Ba4) 4...Nf6 1-0");
+        assert_eq!(
+            tokens,
+            ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "0-0", "Nf6"]
+        );
+        assert_eq!(movetext_tokens("1.d4 d5 1/2-1/2"), ["d4", "d5"]);
+        assert_eq!(movetext_tokens("1.d4 d5 0-1"), ["d4", "d5"]);
+    }
+}
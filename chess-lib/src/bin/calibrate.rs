@@ -0,0 +1,150 @@
+use chess_lib::{
+    bot::Bot,
+    game::Game,
+    movegen::legal_moves,
+    piece::Color,
+    strength::{pick_move, Strength, MAX_ELO, MIN_ELO},
+    wasm::GameState,
+};
+
+// Plays neighbouring strength levels against each other and checks that
+// every level beats the one below it.
+//
+// Usage: calibrate [games per pair] [move time in ms] [levels...]
+
+const TT_ENTRIES: usize = 1 << 18;
+const MAX_PLIES: usize = 300;
+
+// Short openings so games between the same levels don't all repeat
+const OPENINGS: [&str; 8] = [
+    "e2e4 e7e5",
+    "e2e4 c7c5",
+    "e2e4 e7e6",
+    "d2d4 d7d5",
+    "d2d4 g8f6",
+    "c2c4 e7e5",
+    "g1f3 d7d5",
+    "e2e4 c7c6",
+];
+
+#[derive(Default)]
+struct Score {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl Score {
+    fn points(&self) -> f64 {
+        let games = (self.wins + self.draws + self.losses) as f64;
+        (self.wins as f64 + self.draws as f64 / 2.) / games
+    }
+
+    fn elo_diff(&self) -> f64 {
+        let p = self.points().clamp(0.001, 0.999);
+        -400. * (1. / p - 1.).log10()
+    }
+}
+
+// Result for white: 1, 0.5 or 0
+fn play_game(white: Strength, black: Strength, opening: &str, move_time: u64) -> f64 {
+    let mut game = Game::default();
+    for mv in opening.split_whitespace() {
+        let m = *legal_moves(&game.board)
+            .iter()
+            .find(|m| m.to_str() == mv)
+            .expect("Illegal opening move");
+        game.make_move(m);
+    }
+
+    let mut rng = rand::thread_rng();
+    while game.game_state == GameState::InProgress && game.moves.len() < MAX_PLIES {
+        let strength = match game.board.side_to_move {
+            Color::White => white,
+            Color::Black => black,
+        };
+        let mut limits = strength.limits();
+        limits.movetime = Some(move_time);
+
+        let mut bot = Bot::with_limits(TT_ENTRIES, limits);
+        let m = pick_move(&mut bot, game.board.clone(), strength, &mut rng)
+            .expect("No legal moves in a running game");
+        game.make_move(m);
+    }
+
+    match game.game_state {
+        GameState::Checkmate if game.board.side_to_move == Color::Black => 1.,
+        GameState::Checkmate => 0.,
+        _ => 0.5,
+    }
+}
+
+// Score of `strong` against `weak`
+fn play_match(strong: Strength, weak: Strength, games: usize, move_time: u64) -> Score {
+    let mut score = Score::default();
+    for i in 0..games {
+        let opening = OPENINGS[i / 2 % OPENINGS.len()];
+        let result = if i % 2 == 0 {
+            play_game(strong, weak, opening, move_time)
+        } else {
+            1. - play_game(weak, strong, opening, move_time)
+        };
+
+        if result == 1. {
+            score.wins += 1;
+        } else if result == 0. {
+            score.losses += 1;
+        } else {
+            score.draws += 1;
+        }
+    }
+    score
+}
+
+fn main() {
+    let args: Vec<_> = std::env::args().collect();
+    let games: usize = args.get(1).map(|g| g.parse().unwrap()).unwrap_or(20);
+    let move_time: u64 = args.get(2).map(|t| t.parse().unwrap()).unwrap_or(100);
+    let mut levels: Vec<u32> = args.iter().skip(3).map(|l| l.parse().unwrap()).collect();
+    if levels.is_empty() {
+        levels = (MIN_ELO..=MAX_ELO).step_by(400).collect();
+    }
+    levels.sort();
+
+    let pairs = levels
+        .windows(2)
+        .map(|w| (Strength::new(w[1]), Strength::new(w[0])))
+        .collect::<Vec<_>>();
+
+    let results = std::thread::scope(|scope| {
+        pairs
+            .iter()
+            .map(|&(strong, weak)| scope.spawn(move || play_match(strong, weak, games, move_time)))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let mut monotonic = true;
+    for ((strong, weak), score) in pairs.iter().zip(results.iter()) {
+        println!(
+            "{} vs {}: +{} ={} -{}, {:.1}%, Elo difference {:+.0}",
+            strong.elo(),
+            weak.elo(),
+            score.wins,
+            score.draws,
+            score.losses,
+            score.points() * 100.,
+            score.elo_diff()
+        );
+        monotonic &= score.points() > 0.5;
+    }
+
+    if monotonic {
+        println!("Strength is monotonic");
+    } else {
+        println!("Strength is NOT monotonic");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,19 @@
+use chess_lib::eval::kpk::{generate, POSITIONS};
+
+// Builds the KPK bitbase compiled into the library.
+//
+// Usage: kpk [output=src/eval/kpk.bin]
+
+fn main() {
+    let output = std::env::args()
+        .nth(1)
+        .unwrap_or("src/eval/kpk.bin".to_string());
+
+    let bits = generate();
+    let wins = bits.iter().map(|b| b.count_ones()).sum::<u32>();
+    std::fs::write(&output, &bits).expect("Failed to write bitbase");
+    println!(
+        "{} of {} positions are wins, written to {}",
+        wins, POSITIONS, output
+    );
+}
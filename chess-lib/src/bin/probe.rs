@@ -0,0 +1,41 @@
+use chess_lib::{board::Board, movegen::san::to_san, syzygy::Tablebases};
+
+// Prints the tablebase result of a position and of all of its moves, handy
+// for checking the probing code against other tools.
+//
+// Usage: probe <tablebase dir> <fen>
+
+fn main() {
+    let args: Vec<_> = std::env::args().collect();
+    if args.len() < 3 {
+        panic!("Usage: probe <tablebase dir> <fen>");
+    }
+    let tb = Tablebases::open(&args[1]).expect("Failed to open tablebases");
+    println!(
+        "Found {} tablebases with up to {} pieces",
+        tb.len(),
+        tb.max_pieces()
+    );
+
+    let board = Board::from_fen(&args[2..].join(" "));
+    let (Some(wdl), dtz) = (tb.probe_wdl(&board), tb.probe_dtz(&board)) else {
+        println!("Position is not in the tablebases");
+        return;
+    };
+    match dtz {
+        Some(dtz) => println!("WDL {:?}, DTZ {}", wdl, dtz),
+        None => println!("WDL {:?}, no DTZ table", wdl),
+    }
+
+    if let Some(mut moves) = tb.root_dtz(&board) {
+        // Quickest wins first, then draws, then the longest losses
+        moves.sort_by_key(|&(_, dtz)| match dtz.signum() {
+            1 => dtz,
+            0 => 1000,
+            _ => 2000 + dtz,
+        });
+        for (m, dtz) in moves {
+            println!("{:<8}{}", to_san(&board, &m), dtz);
+        }
+    }
+}
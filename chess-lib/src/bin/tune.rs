@@ -0,0 +1,254 @@
+use chess_lib::{
+    board::Board,
+    eval::{evaluate_with_params, EvalParams, Tapered},
+    piece::Color,
+};
+use std::fmt::Write;
+
+// Texel tuning: minimizes the squared error between the game result and the
+// win probability predicted from the static evaluation of quiet positions.
+//
+// Each line of the input holds a FEN followed by the result of the game it
+// was taken from, either as "1-0", "0-1", "1/2-1/2" or as "[1.0]", "[0.5]",
+// "[0.0]" from white's point of view.
+
+struct Position {
+    board: Board,
+    result: f64,
+}
+
+fn parse_line(line: &str) -> Option<Position> {
+    let result = if line.contains("1/2-1/2") || line.contains("[0.5]") {
+        0.5
+    } else if line.contains("1-0") || line.contains("[1.0]") {
+        1.0
+    } else if line.contains("0-1") || line.contains("[0.0]") {
+        0.0
+    } else {
+        return None;
+    };
+
+    let fields = line.split_whitespace().take(4).collect::<Vec<_>>();
+    if fields.len() < 4 {
+        return None;
+    }
+    let fen = fields.join(" ").replace(';', "");
+    Some(Position {
+        board: Board::from_fen(&fen),
+        result,
+    })
+}
+
+fn white_eval(board: &Board, params: &EvalParams) -> f64 {
+    let eval = evaluate_with_params(board, params);
+    if board.side_to_move == Color::White {
+        eval as f64
+    } else {
+        -eval as f64
+    }
+}
+
+fn sigmoid(k: f64, eval: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+fn error(positions: &[Position], params: &EvalParams, k: f64) -> f64 {
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let chunk = positions.len().div_ceil(threads).max(1);
+
+    let total: f64 = std::thread::scope(|scope| {
+        positions
+            .chunks(chunk)
+            .map(|c| {
+                scope.spawn(move || {
+                    c.iter()
+                        .map(|p| (p.result - sigmoid(k, white_eval(&p.board, params))).powi(2))
+                        .sum::<f64>()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .sum()
+    });
+    total / positions.len() as f64
+}
+
+// The scaling constant that best fits the current evaluation
+fn find_k(positions: &[Position], params: &EvalParams) -> f64 {
+    let mut best = (1.0, error(positions, params, 1.0));
+    let mut step = 0.1;
+    for _ in 0..3 {
+        let center = best.0;
+        for i in -10..=10 {
+            let k = center + i as f64 * step;
+            if k <= 0.0 {
+                continue;
+            }
+            let e = error(positions, params, k);
+            if e < best.1 {
+                best = (k, e);
+            }
+        }
+        step /= 10.0;
+    }
+    best.0
+}
+
+fn weight_mut(params: &mut EvalParams, idx: usize) -> &mut i32 {
+    let t = params.weights_mut().into_iter().nth(idx / 2).unwrap();
+    if idx % 2 == 0 {
+        &mut t.mg
+    } else {
+        &mut t.eg
+    }
+}
+
+fn tapered_list(values: &[Tapered], indent: &str) -> String {
+    let items = values
+        .iter()
+        .map(|t| format!("s({}, {})", t.mg, t.eg))
+        .collect::<Vec<_>>();
+    let line = format!("[{}]", items.join(", "));
+    if line.len() <= 60 {
+        return line;
+    }
+
+    let mut res = "[\n".to_string();
+    for item in items {
+        writeln!(res, "{indent}    {item},").unwrap();
+    }
+    res + indent + "]"
+}
+
+fn table(name: &str, values: impl Iterator<Item = i32>) -> String {
+    let values = values.collect::<Vec<_>>();
+    let mut res = format!("#[rustfmt::skip]\nconst {name}: [i32; 64] = [\n");
+    for row in values.chunks(8) {
+        let row = row.iter().map(|v| format!("{v:>3},")).collect::<Vec<_>>();
+        writeln!(res, "    {}", row.join(" ")).unwrap();
+    }
+    res + "];\n"
+}
+
+fn rust_source(p: &EvalParams) -> String {
+    let mut res = String::new();
+    writeln!(
+        res,
+        "// Evaluation weights, regenerated by `cargo run --release --bin tune`"
+    )
+    .unwrap();
+    writeln!(res, "use super::{{s, EvalParams, Tapered}};\n").unwrap();
+    writeln!(res, "pub const DEFAULT_PARAMS: EvalParams = EvalParams {{").unwrap();
+    writeln!(
+        res,
+        "    piece_value: {},",
+        tapered_list(&p.piece_value, "    ")
+    )
+    .unwrap();
+    writeln!(
+        res,
+        "    // Piece square tables, from white's point of view with a1 first"
+    )
+    .unwrap();
+    writeln!(res, "    psqt: [").unwrap();
+    for name in PSQT_NAMES {
+        writeln!(res, "        zip(&{name}_MG, &{name}_EG),").unwrap();
+    }
+    writeln!(res, "    ],").unwrap();
+    for (name, t) in [
+        ("bishop_pair", p.bishop_pair),
+        ("doubled_pawn", p.doubled_pawn),
+        ("isolated_pawn", p.isolated_pawn),
+        ("backward_pawn", p.backward_pawn),
+    ] {
+        writeln!(res, "    {name}: s({}, {}),", t.mg, t.eg).unwrap();
+    }
+    writeln!(
+        res,
+        "    passed_pawn: {},",
+        tapered_list(&p.passed_pawn, "    ")
+    )
+    .unwrap();
+    for (name, t) in [
+        ("rook_open_file", p.rook_open_file),
+        ("rook_semi_open_file", p.rook_semi_open_file),
+    ] {
+        writeln!(res, "    {name}: s({}, {}),", t.mg, t.eg).unwrap();
+    }
+    writeln!(res, "    mobility: {},", tapered_list(&p.mobility, "    ")).unwrap();
+    writeln!(
+        res,
+        "    king_shield: {},",
+        tapered_list(&p.king_shield, "    ")
+    )
+    .unwrap();
+    writeln!(res, "}};\n").unwrap();
+
+    res += "const fn zip(mg: &[i32; 64], eg: &[i32; 64]) -> [Tapered; 64] {
+    let mut res = [s(0, 0); 64];
+    let mut i = 0;
+    while i < 64 {
+        res[i] = s(mg[i], eg[i]);
+        i += 1;
+    }
+    res
+}
+";
+    for (name, psqt) in PSQT_NAMES.iter().zip(p.psqt.iter()) {
+        res += "\n";
+        res += &table(&format!("{name}_MG"), psqt.iter().map(|t| t.mg));
+        res += "\n";
+        res += &table(&format!("{name}_EG"), psqt.iter().map(|t| t.eg));
+    }
+    res
+}
+
+const PSQT_NAMES: [&str; 6] = ["PAWN", "KNIGHT", "BISHOP", "ROOK", "QUEEN", "KING"];
+
+fn main() {
+    let args: Vec<_> = std::env::args().collect();
+    if args.len() < 2 {
+        panic!("Usage: tune <positions> [output] [iterations]");
+    }
+    let output = args.get(2).map(String::as_str).unwrap_or("params.rs");
+    let iterations: usize = args.get(3).map(|i| i.parse().unwrap()).unwrap_or(100);
+
+    let positions = std::fs::read_to_string(&args[1])
+        .expect("Failed to read positions")
+        .lines()
+        .filter_map(parse_line)
+        .collect::<Vec<_>>();
+    println!("Loaded {} positions", positions.len());
+
+    let mut params = EvalParams::default();
+    let k = find_k(&positions, &params);
+    let mut best = error(&positions, &params, k);
+    println!("K = {:.3}, error {:.6}", k, best);
+
+    let weights = params.weights_mut().len() * 2;
+    for iteration in 1..=iterations {
+        let mut improved = false;
+        for idx in 0..weights {
+            for delta in [1, -1] {
+                let mut p = params.clone();
+                *weight_mut(&mut p, idx) += delta;
+                let e = error(&positions, &p, k);
+                if e < best {
+                    best = e;
+                    params = p;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+
+        println!("Iteration {}: error {:.6}", iteration, best);
+        std::fs::write(output, rust_source(&params)).expect("Failed to write params");
+        if !improved {
+            break;
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use chess_lib::{
+    board::{Board, DEFAULT_FEN},
+    bot::{Bot, SearchInfo, SearchLimits},
+    movegen::{legal_moves, Move},
+    piece::Color,
+    score::Score,
+    syzygy::Tablebases,
+};
+use std::{
+    io::BufRead,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+const TT_ENTRIES: usize = 1 << 20;
+
+// Minimal UCI front end for running the engine in GUIs and match tools
+
+fn parse_position(tokens: &[&str]) -> Board {
+    let moves_at = tokens.iter().position(|t| *t == "moves");
+    let setup = &tokens[..moves_at.unwrap_or(tokens.len())];
+
+    let mut board = match setup.first() {
+        Some(&"fen") => Board::from_fen(&setup[1..].join(" ")),
+        _ => Board::from_fen(DEFAULT_FEN),
+    };
+
+    if let Some(idx) = moves_at {
+        for mv in &tokens[idx + 1..] {
+            match legal_moves(&board).iter().find(|m| m.to_str() == *mv) {
+                Some(m) => {
+                    board.make_move(m);
+                }
+                None => {
+                    eprintln!("Illegal move {}", mv);
+                    break;
+                }
+            }
+        }
+    }
+    board
+}
+
+fn value<T: std::str::FromStr>(tokens: &[&str], name: &str) -> Option<T> {
+    let idx = tokens.iter().position(|t| *t == name)?;
+    tokens.get(idx + 1)?.parse().ok()
+}
+
+fn score_str(score: i32) -> String {
+    match Score::from_search(score) {
+        Score::Centipawns(cp) => format!("cp {}", cp),
+        Score::Mate(n) => format!("mate {}", n),
+        Score::MateGiven => unreachable!("Searches never start with the opponent mated"),
+    }
+}
+
+fn pv_str(pv: &[Move]) -> String {
+    pv.iter().map(|m| m.to_str()).collect::<Vec<_>>().join(" ")
+}
+
+fn limits(board: &Board, tokens: &[&str]) -> SearchLimits {
+    let (time, inc) = match board.side_to_move {
+        Color::White => (value::<u64>(tokens, "wtime"), value(tokens, "winc")),
+        Color::Black => (value::<u64>(tokens, "btime"), value(tokens, "binc")),
+    };
+    let movetime = match (value(tokens, "movetime"), time) {
+        (Some(ms), _) => Some(ms),
+        (None, Some(time)) => Some(time / 30 + inc.unwrap_or(0) / 2),
+        (None, None) => None,
+    };
+
+    SearchLimits {
+        depth: value(tokens, "depth"),
+        nodes: value(tokens, "nodes"),
+        movetime,
+        mate: value(tokens, "mate"),
+        infinite: tokens.contains(&"infinite"),
+    }
+}
+
+fn print_info(info: &SearchInfo, multi_pv: bool) {
+    for (i, line) in info.lines.iter().enumerate() {
+        let multi_pv = if multi_pv {
+            format!(" multipv {}", i + 1)
+        } else {
+            String::new()
+        };
+        println!(
+            "info depth {}{} score {} nodes {} tbhits {} time {} pv {}",
+            info.depth,
+            multi_pv,
+            score_str(line.score),
+            info.nodes,
+            info.tb_hits,
+            info.time,
+            pv_str(&line.pv)
+        );
+    }
+}
+
+fn go(
+    board: Board,
+    limits: SearchLimits,
+    options: &Options,
+    stop: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    let mut bot = Bot::with_limits(TT_ENTRIES, limits);
+    bot.threads = options.threads;
+    bot.tablebases.clone_from(&options.tablebases);
+    bot.set_stop_handle(stop.clone());
+    let multi_pv = options.multi_pv;
+
+    std::thread::spawn(move || {
+        let best = if multi_pv > 1 {
+            let lines = bot.analyze(board, multi_pv, |info| print_info(info, true));
+            lines.first().map(|l| l.pv[0])
+        } else {
+            bot.best_move(board, |info| print_info(info, false))
+                .map(|l| l.pv[0])
+        };
+
+        // An infinite search reports its move only after being stopped
+        while limits.infinite && !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        match best {
+            Some(m) => println!("bestmove {}", m.to_str()),
+            None => println!("bestmove 0000"),
+        }
+    })
+}
+
+struct Options {
+    threads: usize,
+    multi_pv: usize,
+    tablebases: Option<Arc<Tablebases>>,
+}
+
+fn open_tablebases(path: &str) -> Option<Arc<Tablebases>> {
+    if path.is_empty() || path == "<empty>" {
+        return None;
+    }
+    match Tablebases::open(path) {
+        Ok(tb) => {
+            println!(
+                "info string Found {} tablebases with up to {} pieces",
+                tb.len(),
+                tb.max_pieces()
+            );
+            Some(Arc::new(tb))
+        }
+        Err(e) => {
+            println!("info string Failed to open tablebases in {}: {}", path, e);
+            None
+        }
+    }
+}
+
+fn main() {
+    let mut board = Board::from_fen(DEFAULT_FEN);
+    let mut options = Options {
+        threads: 1,
+        multi_pv: 1,
+        tablebases: None,
+    };
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut search: Option<JoinHandle<()>> = None;
+
+    // Ends the running search, if any, and waits for its `bestmove`
+    let finish = |search: &mut Option<JoinHandle<()>>| {
+        stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = search.take() {
+            handle.join().expect("Search thread panicked");
+        }
+    };
+
+    for line in std::io::stdin().lock().lines() {
+        let line = line.expect("Failed to read input");
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+        match tokens.first() {
+            Some(&"uci") => {
+                println!("id name chess-lib");
+                println!("id author Sc1pex");
+                println!("option name Threads type spin default 1 min 1 max 256");
+                println!("option name MultiPV type spin default 1 min 1 max 256");
+                println!("option name SyzygyPath type string default <empty>");
+                println!("uciok");
+            }
+            Some(&"isready") => println!("readyok"),
+            Some(&"ucinewgame") => board = Board::from_fen(DEFAULT_FEN),
+            Some(&"position") => board = parse_position(&tokens[1..]),
+            Some(&"setoption") => {
+                let name: String = value(&tokens, "name").unwrap_or_default();
+                let number = || value(&tokens, "value").unwrap_or(1).max(1);
+                match name.to_lowercase().as_str() {
+                    "threads" => options.threads = number(),
+                    "multipv" => options.multi_pv = number(),
+                    // Paths may contain spaces
+                    "syzygypath" => {
+                        let path = line.split_once(" value ").map_or("", |(_, p)| p.trim());
+                        options.tablebases = open_tablebases(path);
+                    }
+                    _ => {}
+                }
+            }
+            Some(&"go") => {
+                finish(&mut search);
+                stop.store(false, Ordering::Relaxed);
+                let limits = limits(&board, &tokens[1..]);
+                search = Some(go(board.clone(), limits, &options, stop.clone()));
+            }
+            Some(&"stop") => finish(&mut search),
+            Some(&"quit") => break,
+            _ => {}
+        }
+    }
+    finish(&mut search);
+}
@@ -162,6 +162,9 @@ pub const FILE_GH: u64 = FILE_G | FILE_H;
 pub const RANK_12: u64 = RANK_1 | RANK_2;
 pub const RANK_78: u64 = RANK_7 | RANK_8;
 
+pub const LIGHT_SQUARES: u64 = 0x55AA55AA55AA55AA;
+pub const DARK_SQUARES: u64 = !LIGHT_SQUARES;
+
 // pub const NOT_FILE_A: u64 = !FILE_A;
 // pub const NOT_FILE_H: u64 = !FILE_H;
 // pub const NOT_FILE_AB: u64 = !(FILE_A | FILE_B);
@@ -3,6 +3,7 @@ use crate::{
     movegen::{square_attacked, Move, SpecialMove},
     piece::*,
     square::Square,
+    zobrist::{pawn_hash, pawn_key},
 };
 use serde::{Deserialize, Serialize};
 pub const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
@@ -28,6 +29,7 @@ pub struct Board {
 
     pub side_to_move: Color,
     pub in_check: bool,
+    pub pawn_key: u64,
 
     pub(crate) en_passant: Option<Square>,
     pub(crate) can_castle: u8,
@@ -48,8 +50,19 @@ impl Board {
         bb.set(m.to);
         self.en_passant = None;
 
+        if m.piece.kind == PieceKind::Pawn {
+            self.pawn_key ^= pawn_key(m.piece.color, m.from as u64);
+            if !matches!(m.special, Some(SpecialMove::Promotion(_))) {
+                self.pawn_key ^= pawn_key(m.piece.color, m.to as u64);
+            }
+        }
+
         if m.capture {
-            for b in self.boards_color(self.side_to_move.opposite()) {
+            let opponent = self.side_to_move.opposite();
+            if self.board(Piece::new(PieceKind::Pawn, opponent)).get(m.to) {
+                self.pawn_key ^= pawn_key(opponent, m.to as u64);
+            }
+            for b in self.boards_color(opponent) {
                 if b.get(m.to) {
                     b.clear(m.to);
                     break;
@@ -78,9 +91,11 @@ impl Board {
             }
             Some(SpecialMove::EnPassant) => {
                 if self.side_to_move == Color::White {
-                    self.b_pawn.clear(m.to as u64 - 8)
+                    self.b_pawn.clear(m.to as u64 - 8);
+                    self.pawn_key ^= pawn_key(Color::Black, m.to as u64 - 8);
                 } else {
-                    self.w_pawn.clear(m.to as u64 + 8)
+                    self.w_pawn.clear(m.to as u64 + 8);
+                    self.pawn_key ^= pawn_key(Color::White, m.to as u64 + 8);
                 }
             }
             Some(SpecialMove::Promotion(p)) => {
@@ -296,8 +311,63 @@ impl Board {
         // Fullmove number
 
         s.update_occ();
+        s.pawn_key = pawn_hash(&s);
         s
     }
+
+    // The board keeps no move counters, `Game::fen` fills them in
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.piece(rank * 8 + file) {
+                    Some(p) => {
+                        if empty > 0 {
+                            fen += &empty.to_string();
+                            empty = 0;
+                        }
+                        fen += &match p.color {
+                            Color::White => p.kind.letter().to_uppercase(),
+                            Color::Black => p.kind.letter().to_string(),
+                        };
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen += &empty.to_string();
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen += match self.side_to_move {
+            Color::White => " w ",
+            Color::Black => " b ",
+        };
+
+        for (castle, c) in [
+            (Castle::WhiteKing, 'K'),
+            (Castle::WhiteQueen, 'Q'),
+            (Castle::BlackKing, 'k'),
+            (Castle::BlackQueen, 'q'),
+        ] {
+            if self.can_castle & castle as u8 != 0 {
+                fen.push(c);
+            }
+        }
+        if self.can_castle == 0 {
+            fen.push('-');
+        }
+
+        match self.en_passant {
+            Some(sq) => fen += &format!(" {} 0 1", sq),
+            None => fen += " - 0 1",
+        }
+        fen
+    }
 }
 
 impl Board {
@@ -421,3 +491,42 @@ impl std::fmt::Display for Board {
         writeln!(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::legal_moves;
+
+    // Perft that checks the incrementally updated pawn key after every move
+    fn perft(board: &Board, depth: i32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let mut nodes = 0;
+        for m in legal_moves(board).iter() {
+            let mut b = board.clone();
+            b.make_move(m);
+            assert_eq!(b.pawn_key, pawn_hash(&b), "{} after {}", board, m);
+            nodes += perft(&b, depth - 1);
+        }
+        nodes
+    }
+
+    #[test]
+    fn pawn_key_stays_in_sync() {
+        let positions = [
+            (DEFAULT_FEN, 3, 8_902),
+            // Castling, en passant and promotions
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                3,
+                97_862,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4, 43_238),
+            ("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1", 3, 9_483),
+        ];
+        for (fen, depth, nodes) in positions {
+            assert_eq!(perft(&Board::from_fen(fen), depth), nodes, "{}", fen);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    board::Board,
+    movegen::{legal_moves, Move, SpecialMove},
+    piece::PieceKind,
+};
+use rand::Rng;
+
+use self::keys::book_key;
+
+pub mod keys;
+
+// Polyglot opening books: 16 byte big endian entries sorted by key
+//   key     u64
+//   move    u16  | promotion: 3 | from: 6 | to: 6 |
+//   weight  u16
+//   learn   u32
+// Castling is stored as the king capturing its own rook, e.g. e1h1.
+
+const ENTRY_SIZE: usize = 16;
+
+static BUILTIN: &[u8] = include_bytes!("../../books/builtin.bin");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    fn from_bytes(b: &[u8]) -> Self {
+        Self {
+            key: u64::from_be_bytes(b[0..8].try_into().unwrap()),
+            mv: u16::from_be_bytes([b[8], b[9]]),
+            weight: u16::from_be_bytes([b[10], b[11]]),
+            learn: u32::from_be_bytes(b[12..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut b = [0; ENTRY_SIZE];
+        b[0..8].copy_from_slice(&self.key.to_be_bytes());
+        b[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        b[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        b[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        b
+    }
+}
+
+pub struct Book {
+    entries: Box<[BookEntry]>,
+    // Positions after this many plies are never looked up
+    pub max_ply: usize,
+}
+
+impl Book {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
+        if bytes.len() % ENTRY_SIZE != 0 {
+            return Err("Book size is not a multiple of the entry size");
+        }
+        let entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(BookEntry::from_bytes)
+            .collect::<Box<[_]>>();
+        if entries.windows(2).any(|w| w[0].key > w[1].key) {
+            return Err("Book entries are not sorted");
+        }
+
+        Ok(Self {
+            entries,
+            max_ply: usize::MAX,
+        })
+    }
+
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> Self {
+        entries.sort_by_key(|e| (e.key, std::cmp::Reverse(e.weight)));
+        Self {
+            entries: entries.into(),
+            max_ply: usize::MAX,
+        }
+    }
+
+    // The small repertoire bundled with the library, built from
+    // `books/builtin.pgn`
+    pub fn builtin() -> Self {
+        Self::from_bytes(BUILTIN).expect("Bundled book is invalid")
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries.iter().flat_map(|e| e.to_bytes()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Legal book moves for the position with their weights
+    pub fn moves(&self, board: &Board) -> Vec<(Move, u16)> {
+        let key = book_key(board);
+        let start = self.entries.partition_point(|e| e.key < key);
+        self.entries[start..]
+            .iter()
+            .take_while(|e| e.key == key)
+            .filter_map(|e| Some((decode_move(board, e.mv)?, e.weight)))
+            .collect()
+    }
+
+    // A book move chosen at random, proportionally to its weight. `ply` is
+    // the number of moves played since the start of the game.
+    pub fn pick(&self, board: &Board, ply: usize, rng: &mut impl Rng) -> Option<Move> {
+        if ply >= self.max_ply {
+            return None;
+        }
+
+        let moves = self.moves(board);
+        let total = moves.iter().map(|(_, w)| *w as u32).sum::<u32>();
+        if total == 0 {
+            return None;
+        }
+
+        let mut r = rng.gen_range(0..total);
+        for (m, w) in moves {
+            if r < w as u32 {
+                return Some(m);
+            }
+            r -= w as u32;
+        }
+        None
+    }
+}
+
+pub fn encode_move(m: &Move) -> u16 {
+    let to = if matches!(m.special, Some(SpecialMove::Castle)) {
+        // The rook's square
+        let file = if m.to.file() == 6 { 7 } else { 0 };
+        m.to.rank() * 8 + file
+    } else {
+        m.to as u64
+    };
+    let promotion = match m.special {
+        Some(SpecialMove::Promotion(p)) => match p {
+            PieceKind::Knight => 1,
+            PieceKind::Bishop => 2,
+            PieceKind::Rook => 3,
+            _ => 4,
+        },
+        _ => 0,
+    };
+    ((promotion << 12) | ((m.from as u64) << 6) | to) as u16
+}
+
+pub fn decode_move(board: &Board, mv: u16) -> Option<Move> {
+    legal_moves(board)
+        .iter()
+        .find(|m| encode_move(m) == mv)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The bundled book has to be rebuilt whenever the keys change
+    #[test]
+    fn builtin_book_knows_the_start_position() {
+        let moves = Book::builtin().moves(&Board::from_fen(crate::board::DEFAULT_FEN));
+        assert!(!moves.is_empty());
+    }
+}
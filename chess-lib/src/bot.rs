@@ -1,54 +1,159 @@
+#[cfg(feature = "nnue")]
+use crate::nnue::{Accumulator, Network};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::syzygy::Tablebases;
 use crate::{
     bitboardindex::BitBoardIdx,
     board::Board,
+    eval::{self, params::DEFAULT_PARAMS, pawns::PawnTable},
     movegen::{legal_moves, Move, SpecialMove},
-    piece::{Color, Piece, PieceKind},
-    square::Square,
+    piece::PieceKind,
+    score::{mated_in, Score, INFINITY},
+    see::see,
     transposition::{TranspositionKind, TranspositionTable},
     zobrist::Zobrist,
 };
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 use zduny_wasm_timer::Instant;
 
+const PAWN_TABLE_ENTRIES: usize = 1 << 14;
+pub const MAX_DEPTH: i32 = 64;
+// Nodes searched between two reads of the clock
+const CLOCK_INTERVAL: u32 = 1024;
+
+#[derive(Clone)]
+pub enum Evaluator {
+    Handcrafted,
+    #[cfg(feature = "nnue")]
+    Network(Arc<Network>),
+}
+
+// One line of a MultiPV analysis, scores are from the side to move's view
+#[derive(Debug, Clone, PartialEq)]
+pub struct PvLine {
+    pub score: i32,
+    pub depth: i32,
+    pub pv: Vec<Move>,
+}
+
+// Unset limits don't apply, an infinite search only ends through the stop
+// handle or at `MAX_DEPTH`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<i32>,
+    pub nodes: Option<u64>,
+    // Milliseconds
+    pub movetime: Option<u64>,
+    // Stop once a mate in at most this many moves is found
+    pub mate: Option<i32>,
+    pub infinite: bool,
+}
+
+impl SearchLimits {
+    pub fn new(depth: i32, movetime: u64) -> Self {
+        Self {
+            depth: Some(depth),
+            movetime: Some(movetime),
+            ..Default::default()
+        }
+    }
+
+    fn max_depth(&self) -> i32 {
+        match self.depth {
+            Some(depth) if !self.infinite => depth.min(MAX_DEPTH),
+            _ => MAX_DEPTH,
+        }
+    }
+}
+
+// Reported after every completed iteration
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo {
+    pub depth: i32,
+    pub nodes: u64,
+    pub tb_hits: u64,
+    // Milliseconds since the search started
+    pub time: u64,
+    pub lines: Vec<PvLine>,
+}
+
 pub struct Bot {
-    depth: i32,
+    pub limits: SearchLimits,
 
-    transposition_table: TranspositionTable,
+    transposition_table: Arc<TranspositionTable>,
+    pawn_table: PawnTable,
     zobrist: Zobrist,
 
-    time: u64,
-    should_stop: bool,
+    // Set by the caller to end the search early, `abort` is set by the
+    // search itself when it runs out of time or nodes
+    stop: Arc<AtomicBool>,
+    abort: Arc<AtomicBool>,
     start: Instant,
+    clock_nodes: u32,
+
+    // Lazy SMP helper threads, only used by native builds
+    pub threads: usize,
+    pub evaluator: Evaluator,
+    #[cfg(feature = "nnue")]
+    accumulators: Vec<Accumulator>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub tablebases: Option<Arc<Tablebases>>,
+    // Root moves that keep the tablebase result, empty when the root isn't
+    // in the tablebases
+    tb_root_moves: Vec<Move>,
 
     pub pv_table: Vec<Box<[Move]>>,
     pub pv_len: Vec<usize>,
     pub nodes_searched: u64,
     pub tt_hits: u64,
+    pub tb_hits: u64,
     pub score: i32,
     pub reached_depth: i32,
 }
 
 impl Bot {
     pub fn tt_stores(&self) -> usize {
-        self.transposition_table.stored_cnt
+        self.transposition_table.stored_cnt()
     }
 }
 
 impl Bot {
     pub fn new(depth: i32, tt_entries: usize, ms: u64) -> Self {
+        Self::with_limits(tt_entries, SearchLimits::new(depth, ms))
+    }
+
+    pub fn with_limits(tt_entries: usize, limits: SearchLimits) -> Self {
+        Self::with_table(Arc::new(TranspositionTable::new(tt_entries)), limits)
+    }
+
+    fn with_table(transposition_table: Arc<TranspositionTable>, limits: SearchLimits) -> Self {
         let pv_table_size = 128;
         let pv_table = (0..pv_table_size)
             .map(|i| vec![Move::empty(); pv_table_size - i].into_boxed_slice())
             .collect::<Vec<_>>();
 
         Self {
-            depth,
+            limits,
 
-            transposition_table: TranspositionTable::new(tt_entries),
+            transposition_table,
+            pawn_table: PawnTable::new(PAWN_TABLE_ENTRIES),
             zobrist: Zobrist::new(),
 
-            time: ms,
-            should_stop: false,
+            stop: Arc::new(AtomicBool::new(false)),
+            abort: Arc::new(AtomicBool::new(false)),
             start: Instant::now(),
+            clock_nodes: 0,
+
+            threads: 1,
+            evaluator: Evaluator::Handcrafted,
+            #[cfg(feature = "nnue")]
+            accumulators: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            tablebases: None,
+            tb_root_moves: Vec::new(),
 
             pv_table,
             pv_len: vec![0; pv_table_size],
@@ -56,88 +161,399 @@ impl Bot {
             nodes_searched: 0,
             score: 0,
             tt_hits: 0,
+            tb_hits: 0,
             reached_depth: 0,
         }
     }
 
+    // Setting the returned flag stops a running search, it is never cleared
+    // by the bot itself
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    pub fn set_stop_handle(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = stop;
+    }
+
     pub fn make_move(&mut self, board: Board, hard_diff: bool) -> Box<[(Move, i32)]> {
         if hard_diff {
-            let mut moves = vec![];
-            self.start = Instant::now();
-            for depth in 1..=self.depth {
-                let s = self.search(board.clone(), -500_000, 500_000, depth, 0, true);
-                self.reached_depth = depth;
-                if self.should_stop {
-                    break;
-                }
-                moves = vec![(self.pv_table[0][0], s)];
-            }
-            moves.into()
+            self.best_move(board, |_| {})
+                .map(|line| (line.pv[0], line.score))
+                .into_iter()
+                .collect()
         } else {
+            self.start_search(&board);
+
             let mut moves = vec![];
-            for depth in 1..=self.depth {
+            for depth in 1..=self.limits.max_depth() {
                 let mut new_moves = vec![];
                 let m = legal_moves(&board);
                 for m in m.iter() {
-                    let mut board = board.clone();
-
-                    board.make_move(m);
-                    let score = -self.search(board, -500_000, 500_000, depth, 1, true);
+                    let board = self.play(&board, m, 0);
+                    let score = -self.search(board, -INFINITY, INFINITY, depth, 1, true);
 
                     new_moves.push((m.clone(), score));
                 }
 
-                self.reached_depth = depth;
-                if self.should_stop {
+                if self.should_stop() {
                     break;
                 }
+                self.reached_depth = depth;
                 moves = new_moves;
             }
             moves.sort_by(|a, b| b.1.cmp(&a.1));
+            if let Some((_, s)) = moves.first() {
+                self.score = *s;
+            }
             moves.into()
         }
     }
-}
 
-const PIECE_KINDS: [PieceKind; 6] = [
-    PieceKind::Pawn,
-    PieceKind::Knight,
-    PieceKind::Bishop,
-    PieceKind::Rook,
-    PieceKind::Queen,
-    PieceKind::King,
-];
+    // Searches the best `lines` root moves, each with its own principal
+    // variation. Moves that can't beat the worst line kept so far are only
+    // searched with a null window, so extra lines come cheap.
+    pub fn analyze(
+        &mut self,
+        board: Board,
+        lines: usize,
+        mut on_iteration: impl FnMut(&SearchInfo),
+    ) -> Vec<PvLine> {
+        self.start_search(&board);
+
+        let mut root_moves = legal_moves(&board)
+            .iter()
+            .filter(|m| self.tb_root_moves.is_empty() || self.tb_root_moves.contains(m))
+            .map(|m| (*m, -INFINITY))
+            .collect::<Vec<_>>();
+
+        let mut result = vec![];
+        for depth in 1..=self.limits.max_depth() {
+            let found = self.search_multipv(&board, &mut root_moves, lines.max(1), depth);
+            if self.should_stop() {
+                break;
+            }
+            self.reached_depth = depth;
+            on_iteration(&self.info(depth, &found));
+            result = found;
+            if result.first().is_some_and(|l| self.mate_found(l.score)) {
+                break;
+            }
+        }
+
+        if let Some(line) = result.first() {
+            self.score = line.score;
+        }
+        result
+    }
+
+    // The best move with its principal variation, `on_iteration` is called
+    // after every completed depth
+    pub fn best_move(
+        &mut self,
+        board: Board,
+        mut on_iteration: impl FnMut(&SearchInfo),
+    ) -> Option<PvLine> {
+        self.start_search(&board);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.threads > 1 {
+            return self.search_smp(&board, &mut on_iteration);
+        }
+        self.iterative_deepening(&board, 1, &mut on_iteration)
+    }
+}
 
 impl Bot {
-    fn check_time(&mut self) {
+    // Counters are per search, so a reused bot gets its full node limit
+    // every time
+    fn start_search(&mut self, board: &Board) {
+        self.nodes_searched = 0;
+        self.clock_nodes = 0;
+        self.tt_hits = 0;
+        self.tb_hits = 0;
+        self.reached_depth = 0;
+        self.prepare_root(board);
+        self.tb_root_moves.clear();
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(moves) = self
+            .tablebases
+            .as_ref()
+            .and_then(|tb| tb.best_root_moves(board))
+        {
+            self.tb_hits += 1;
+            self.tb_root_moves = moves;
+        }
+        self.abort.store(false, Ordering::Relaxed);
+        self.start = Instant::now();
+    }
+
+    fn iterative_deepening(
+        &mut self,
+        board: &Board,
+        start_depth: i32,
+        on_iteration: &mut dyn FnMut(&SearchInfo),
+    ) -> Option<PvLine> {
+        if legal_moves(board).is_empty() {
+            return None;
+        }
+
+        let mut best = None;
+        for depth in start_depth..=self.limits.max_depth() {
+            let s = self.search(board.clone(), -INFINITY, INFINITY, depth, 0, true);
+            if self.should_stop() {
+                break;
+            }
+            self.reached_depth = depth;
+
+            let mut pv = self.pv_table[0][..self.pv_len[0]].to_vec();
+            self.extend_pv(board, &mut pv, depth);
+            let line = PvLine {
+                score: s,
+                depth,
+                pv,
+            };
+            self.score = s;
+            on_iteration(&self.info(depth, std::slice::from_ref(&line)));
+            best = Some(line);
+
+            if self.mate_found(s) {
+                break;
+            }
+        }
+        best
+    }
+
+    fn info(&self, depth: i32, lines: &[PvLine]) -> SearchInfo {
+        SearchInfo {
+            depth,
+            nodes: self.nodes_searched,
+            tb_hits: self.tb_hits,
+            time: self.elapsed(),
+            lines: lines.to_vec(),
+        }
+    }
+
+    fn mate_found(&self, score: i32) -> bool {
+        match (self.limits.mate, Score::from_search(score)) {
+            (Some(limit), Score::Mate(n)) => n > 0 && n <= limit,
+            _ => false,
+        }
+    }
+
+    // Lazy SMP: helper threads search the same position through the shared
+    // transposition table, starting at staggered depths so they don't all
+    // follow the main thread. Only the main thread's result is used.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn search_smp(
+        &mut self,
+        board: &Board,
+        on_iteration: &mut dyn FnMut(&SearchInfo),
+    ) -> Option<PvLine> {
+        let mut helpers = (1..self.threads).map(|_| self.helper()).collect::<Vec<_>>();
+
+        let best = std::thread::scope(|scope| {
+            for (i, helper) in helpers.iter_mut().enumerate() {
+                let start_depth = 1 + (i as i32 + 1) % 2;
+                scope.spawn(move || helper.iterative_deepening(board, start_depth, &mut |_| {}));
+            }
+
+            let best = self.iterative_deepening(board, 1, on_iteration);
+            self.abort.store(true, Ordering::Relaxed);
+            best
+        });
+
+        self.nodes_searched += helpers.iter().map(|h| h.nodes_searched).sum::<u64>();
+        self.tb_hits += helpers.iter().map(|h| h.tb_hits).sum::<u64>();
+        best
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn helper(&self) -> Bot {
+        // The node limit counts the main thread's nodes only, helpers run
+        // until it stops them
+        let limits = SearchLimits {
+            nodes: None,
+            ..self.limits
+        };
+        let mut bot = Bot::with_table(self.transposition_table.clone(), limits);
+        bot.zobrist = self.zobrist.clone();
+        bot.stop = self.stop.clone();
+        bot.abort = self.abort.clone();
+        bot.start = self.start;
+        bot.evaluator = self.evaluator.clone();
+        bot.tablebases.clone_from(&self.tablebases);
+        bot.tb_root_moves.clone_from(&self.tb_root_moves);
+        #[cfg(feature = "nnue")]
+        bot.accumulators.clone_from(&self.accumulators);
+        bot
+    }
+
+    fn search_multipv(
+        &mut self,
+        board: &Board,
+        root_moves: &mut [(Move, i32)],
+        lines: usize,
+        depth: i32,
+    ) -> Vec<PvLine> {
+        let mut best: Vec<PvLine> = vec![];
+        for (m, score) in root_moves.iter_mut() {
+            let full = best.len() < lines;
+            let alpha = if full {
+                -INFINITY
+            } else {
+                best[lines - 1].score
+            };
+
+            let b = self.play(board, m, 0);
+            let window = if full { -INFINITY } else { -alpha - 1 };
+            let mut s = -self.search(b, window, -alpha, depth - 1, 1, false);
+
+            // Until all lines are filled moves get a full window, after that
+            // only the moves that beat the worst kept line are searched again
+            // to get their exact score and principal variation
+            if !full && s > alpha && !self.should_stop() {
+                let b = self.play(board, m, 0);
+                s = -self.search(b, -INFINITY, -alpha, depth - 1, 1, false);
+            }
+            if self.should_stop() {
+                return best;
+            }
+            *score = s;
+
+            if s > alpha {
+                let mut pv = vec![*m];
+                pv.extend_from_slice(&self.pv_table[1][..self.pv_len[1]]);
+                self.extend_pv(board, &mut pv, depth);
+
+                let idx = best.partition_point(|l| l.score >= s);
+                best.insert(
+                    idx,
+                    PvLine {
+                        score: s,
+                        depth,
+                        pv,
+                    },
+                );
+                best.truncate(lines);
+            }
+        }
+
+        // The next iteration searches the best lines first
+        root_moves.sort_by_key(|(_, s)| -s);
+        best
+    }
+
+    // Fills in the end of a principal variation cut short by transposition
+    // table hits
+    fn extend_pv(&self, board: &Board, pv: &mut Vec<Move>, depth: i32) {
+        let mut board = board.clone();
+        for m in pv.iter() {
+            board.make_move(m);
+        }
+
+        while pv.len() < depth as usize {
+            let m = match self
+                .transposition_table
+                .best_move(self.zobrist.hash(&board))
+            {
+                Some(m) if legal_moves(&board).contains(&m) => m,
+                _ => break,
+            };
+            board.make_move(&m);
+            pv.push(m);
+        }
+    }
+
+    fn should_stop(&self) -> bool {
+        self.stop.load(Ordering::Relaxed) || self.abort.load(Ordering::Relaxed)
+    }
+
+    #[cfg_attr(not(feature = "nnue"), allow(unused_variables))]
+    fn prepare_root(&mut self, board: &Board) {
+        #[cfg(feature = "nnue")]
+        if let Evaluator::Network(net) = &self.evaluator {
+            // Quiescence can go past the principal variation table
+            let plies = 2 * self.pv_table.len();
+            self.accumulators = (0..plies).map(|_| Accumulator::new(net)).collect();
+            self.accumulators[0].refresh(net, board);
+        }
+    }
+
+    // Plays `m` on a copy of `board`, keeping the network inputs up to date
+    #[cfg_attr(not(feature = "nnue"), allow(unused_variables))]
+    fn play(&mut self, board: &Board, m: &Move, ply: i32) -> Board {
+        let mut b = board.clone();
+        #[cfg(feature = "nnue")]
+        if let Evaluator::Network(net) = &self.evaluator {
+            let (parent, child) = self.accumulators.split_at_mut(ply as usize + 1);
+            b.make_move_nnue(m, net, &parent[ply as usize], &mut child[0]);
+            return b;
+        }
+        b.make_move(m);
+        b
+    }
+
+    #[cfg_attr(not(feature = "nnue"), allow(unused_variables))]
+    fn static_eval(&mut self, board: &Board, ply: i32) -> i32 {
+        match &self.evaluator {
+            Evaluator::Handcrafted => {
+                let pawns = self.pawn_table.probe(board);
+                eval::evaluate_with_pawns(board, pawns, &DEFAULT_PARAMS)
+            }
+            #[cfg(feature = "nnue")]
+            Evaluator::Network(net) => eval::endgame::evaluate(board).unwrap_or_else(|| {
+                net.evaluate(&self.accumulators[ply as usize], board.side_to_move)
+            }),
+        }
+    }
+
+    fn elapsed(&self) -> u64 {
         let ms = Instant::now() - self.start;
-        let ms = ms.as_millis() as u64;
-        if ms >= self.time {
-            self.should_stop = true;
+        ms.as_millis() as u64
+    }
+
+    // Called once per node, returns true when the search has to stop. Node
+    // limits are exact, weak levels search only a few hundred nodes, the
+    // clock is only read every `CLOCK_INTERVAL` nodes.
+    fn check_limits(&mut self) -> bool {
+        if !self.limits.infinite {
+            let out_of_nodes = self.limits.nodes.is_some_and(|n| self.nodes_searched >= n);
+            self.clock_nodes += 1;
+            let read_clock = self.clock_nodes == CLOCK_INTERVAL;
+            if read_clock {
+                self.clock_nodes = 0;
+            }
+            let out_of_time =
+                read_clock && self.limits.movetime.is_some_and(|ms| self.elapsed() >= ms);
+            if out_of_time || out_of_nodes {
+                self.abort.store(true, Ordering::Relaxed);
+            }
         }
+        self.should_stop()
     }
 
     fn quiescence(&mut self, board: Board, mut alpha: i32, beta: i32, ply: i32) -> i32 {
-        if self.nodes_searched % 5000 == 0 {
-            self.check_time();
-            if self.should_stop {
-                return 0;
-            }
+        self.nodes_searched += 1;
+        if self.check_limits() {
+            return 0;
         }
 
-        let score = Self::evaluate(&board);
+        let score = self.static_eval(&board, ply);
         if score >= beta {
             return beta;
         }
         alpha = alpha.max(score);
 
         let moves = self.sorted_moves(ply, &board, false);
-        for m in moves.into_iter().filter(|m| m.capture) {
-            let mut b = board.clone();
-            b.make_move(m);
+        for m in moves
+            .into_iter()
+            .filter(|m| m.capture && see(&board, m) >= 0)
+        {
+            let b = self.play(&board, m, ply);
             let score = -self.quiescence(b, -beta, -alpha, ply + 1);
 
-            if self.should_stop {
+            if self.should_stop() {
                 return 0;
             }
 
@@ -159,25 +575,38 @@ impl Bot {
         ply: i32,
         mut follow_pv: bool,
     ) -> i32 {
-        self.nodes_searched += 1;
-        if self.nodes_searched % 5000 == 0 {
-            self.check_time();
-            if self.should_stop {
-                return 0;
-            }
-        }
-
-        self.pv_len[ply as usize] = 1;
-
+        self.pv_len[ply as usize] = 0;
+        // Quiescence counts its own nodes
         if depth == 0 {
             return self.quiescence(board, alpha, beta, ply);
         }
 
+        self.nodes_searched += 1;
+        if self.check_limits() {
+            return 0;
+        }
+
+        // The root always gets searched so it has a move and a PV
         let hash = self.zobrist.hash(&board);
-        if let Some(score) = self.transposition_table.probe(hash, depth, alpha, beta) {
+        if let Some(score) = self
+            .transposition_table
+            .probe(hash, depth, ply, alpha, beta)
+            .filter(|_| ply > 0)
+        {
             return score;
         }
 
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(wdl) = self
+            .tablebases
+            .as_ref()
+            .filter(|_| ply > 0)
+            .and_then(|tb| tb.probe_wdl(&board))
+        {
+            self.tb_hits += 1;
+            return wdl.score(ply);
+        }
+
         let moves = legal_moves(&board);
         if follow_pv {
             follow_pv = false;
@@ -189,26 +618,32 @@ impl Bot {
             }
         }
 
-        let moves = self.sorted_moves(ply, &board, follow_pv);
+        let mut moves = self.sorted_moves(ply, &board, follow_pv);
+        if ply == 0 && !self.tb_root_moves.is_empty() {
+            moves = moves
+                .iter()
+                .filter(|m| self.tb_root_moves.contains(m))
+                .copied()
+                .collect();
+        }
         if moves.is_empty() {
-            return if board.in_check { -490_000 + ply } else { 0 };
+            return if board.in_check { mated_in(ply) } else { 0 };
         }
 
         let mut tt_entry_kind = TranspositionKind::Alpha;
 
         let next_depth = if board.in_check { depth } else { depth - 1 };
         for m in moves.iter() {
-            let mut b = board.clone();
-            b.make_move(m);
+            let b = self.play(&board, m, ply);
             let score = -self.search(b, -beta, -alpha, next_depth, ply + 1, follow_pv);
 
-            if self.should_stop {
+            if self.should_stop() {
                 return 0;
             }
 
             if score >= beta {
                 self.transposition_table
-                    .store(hash, depth, beta, TranspositionKind::Beta, *m);
+                    .store(hash, depth, ply, beta, TranspositionKind::Beta, *m);
                 return beta;
             }
 
@@ -228,6 +663,7 @@ impl Bot {
         self.transposition_table.store(
             hash,
             depth,
+            ply,
             alpha,
             tt_entry_kind,
             self.pv_table[ply as usize][0],
@@ -237,30 +673,7 @@ impl Bot {
     }
 
     pub fn evaluate(board: &Board) -> i32 {
-        let score = |c: Color| {
-            let mut s = 0;
-            for p in PIECE_KINDS.into_iter() {
-                let mut b = board.board(Piece::new(p, c)).0;
-                while b != 0 {
-                    let square = b.trailing_zeros() as usize;
-                    b &= b - 1;
-                    s += Self::piece_value(p);
-                    s += if c == Color::White {
-                        Self::piece_square_value(p, square)
-                    } else {
-                        Self::piece_square_value(p, Self::mirror_square(square))
-                    };
-                }
-            }
-            s
-        };
-
-        let eval = score(Color::White) - score(Color::Black);
-        if board.side_to_move == Color::White {
-            eval
-        } else {
-            -eval
-        }
+        eval::evaluate(board)
     }
 
     fn move_score(&self, ply: i32, m: &Move, board: &Board, score_pv: bool) -> i32 {
@@ -268,12 +681,22 @@ impl Bot {
             return 10_000;
         }
 
+        if !m.capture {
+            return 0;
+        }
+
+        // Losing captures are searched after the quiet moves
+        let see = see(board, m);
+        if see < 0 {
+            return see;
+        }
+
         if matches!(m.special, Some(SpecialMove::EnPassant)) {
-            return Self::capture_value(PieceKind::Pawn, PieceKind::Pawn);
+            return 1_000 + Self::capture_value(PieceKind::Pawn, PieceKind::Pawn);
         }
 
         if let Some(p) = board.piece(m.to.idx()) {
-            Self::capture_value(m.piece.kind, p.kind)
+            1_000 + Self::capture_value(m.piece.kind, p.kind)
         } else {
             0
         }
@@ -303,84 +726,36 @@ impl Bot {
             PieceKind::King => 10000,
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Scholar's mate threatened, most lines end in captures
+    const FEN: &str = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+
+    #[test]
+    fn quiescence_nodes_are_counted() {
+        // exd5 is the only capture, nothing can be taken back
+        let board = Board::from_fen("4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1");
+        let mut bot = Bot::with_limits(1 << 16, SearchLimits::default());
+        bot.start_search(&board);
+        bot.quiescence(board, -INFINITY, INFINITY, 0);
+        assert_eq!(bot.nodes_searched, 2);
+    }
 
-    // Piece square tables
-    // Stolen from https://www.youtube.com/playlist?list=PLmN0neTso3Jxh8ZIylk74JpwfiWNI76Cs
-    #[rustfmt::skip]
-    const PAWN_SCORE: [i32; 64] = [
-         0,   0,   0,   0,   0,   0,   0,   0,
-         0,   0,   0, -10, -10,   0,   0,   0,
-         0,   0,   0,   5,   5,   0,   0,   0,
-         5,   5,  10,  20,  20,   5,   5,   5,
-        10,  10,  10,  20,  20,  10,  10,  10,
-        20,  20,  20,  30,  30,  30,  20,  20,
-        30,  30,  30,  40,  40,  30,  30,  30,
-        90,  90,  90,  90,  90,  90,  90,  90,
-    ];
-
-    #[rustfmt::skip]
-    const KNIGHT_SCORE: [i32; 64] = [
-        -5, -10,   0,   0,   0,   0, -10,  -5,
-        -5,   0,   0,   0,   0,   0,   0,  -5,
-        -5,   5,  20,  10,  10,  20,   5,  -5,
-        -5,  10,  20,  30,  30,  20,  10,  -5,
-        -5,  10,  20,  30,  30,  20,  10,  -5,
-        -5,   5,  20,  20,  20,  20,   5,  -5,
-        -5,   0,   0,  10,  10,   0,   0,  -5,
-        -5,   0,   0,   0,   0,   0,   0,  -5,
-    ];
-
-    #[rustfmt::skip]
-    const BISHOP_SCORE: [i32; 64] =  [
-         0,   0, -10,   0,   0, -10,   0,   0,
-         0,  30,   0,   0,   0,   0,  30,   0,
-         0,  10,   0,   0,   0,   0,  10,   0,
-         0,   0,  10,  20,  20,  10,   0,   0,
-         0,   0,  10,  20,  20,  10,   0,   0,
-         0,   0,   0,  10,  10,   0,   0,   0,
-         0,   0,   0,   0,   0,   0,   0,   0,
-         0,   0,   0,   0,   0,   0,   0,   0,
-    ];
-
-    #[rustfmt::skip]
-    const ROOK_SCORE: [i32; 64] = [
-         0,   0,   0,  20,  20,   0,   0,   0,
-         0,   0,  10,  20,  20,  10,   0,   0,
-         0,   0,  10,  20,  20,  10,   0,   0,
-         0,   0,  10,  20,  20,  10,   0,   0,
-         0,   0,  10,  20,  20,  10,   0,   0,
-         0,   0,  10,  20,  20,  10,   0,   0,
-        50,  50,  50,  50,  50,  50,  50,  50,
-        50,  50,  50,  50,  50,  50,  50,  50,
-    ];
-
-    #[rustfmt::skip]
-    const KING_SCORE: [i32; 64] = [
-         0,   0,   5,   0, -15,   0,  10,   0,
-         0,   5,   5,  -5,  -5,   0,   5,   0,
-         0,   0,   5,  10,  10,   5,   0,   0,
-         0,   5,  10,  20,  20,  10,   5,   0,
-         0,   5,  10,  20,  20,  10,   5,   0,
-         0,   5,   5,  10,  10,   5,   5,   0,
-         0,   0,   5,   5,   5,   5,   0,   0,
-         0,   0,   0,   0,   0,   0,   0,   0,
-    ];
-
-    fn piece_square_value(piece: PieceKind, square: usize) -> i32 {
-        match piece {
-            PieceKind::Pawn => Self::PAWN_SCORE[square],
-            PieceKind::Knight => Self::KNIGHT_SCORE[square],
-            PieceKind::Bishop => Self::BISHOP_SCORE[square],
-            PieceKind::Rook => Self::ROOK_SCORE[square],
-            PieceKind::Queen => 0,
-            PieceKind::King => Self::KING_SCORE[square],
-        }
-    }
-
-    pub fn mirror_square(square: usize) -> usize {
-        let s = Square::from(square as u64);
-        let rank = s.rank() as usize;
-        let file = s.file() as usize;
-        (7 - rank) * 8 + file
+    #[test]
+    fn node_limits_are_exact() {
+        let board = Board::from_fen(FEN);
+        for nodes in [100, 1_000, 20_000] {
+            let limits = SearchLimits {
+                nodes: Some(nodes),
+                ..Default::default()
+            };
+            let mut bot = Bot::with_limits(1 << 16, limits);
+            bot.best_move(board.clone(), |_| {});
+            assert_eq!(bot.nodes_searched, nodes);
+        }
     }
 }
@@ -0,0 +1,460 @@
+use crate::{
+    board::Board,
+    book::keys::book_key,
+    movegen::{san::from_san, Move},
+};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+// Names openings with their ECO code. Lines are matched by the position
+// they lead to, so transposed move orders get the same name. A game gets
+// the name of the last known position it went through.
+
+pub struct Opening {
+    pub eco: &'static str,
+    pub name: &'static str,
+    // In SAN, from the starting position
+    pub moves: &'static str,
+}
+
+const fn opening(eco: &'static str, name: &'static str, moves: &'static str) -> Opening {
+    Opening { eco, name, moves }
+}
+
+pub const OPENINGS: &[Opening] = &[
+    opening("A00", "Polish Opening", "b4"),
+    opening("A00", "Grob Opening", "g4"),
+    opening("A00", "Van't Kruijs Opening", "e3"),
+    opening("A00", "Mieses Opening", "d3"),
+    opening("A00", "Hungarian Opening", "g3"),
+    opening("A00", "Saragossa Opening", "c3"),
+    opening("A00", "Van Geet Opening", "Nc3"),
+    opening("A00", "Amar Opening", "Nh3"),
+    opening("A00", "Durkin Opening", "Na3"),
+    opening("A00", "Anderssen's Opening", "a3"),
+    opening("A00", "Ware Opening", "a4"),
+    opening("A00", "Clemenz Opening", "h3"),
+    opening("A00", "Kádas Opening", "h4"),
+    opening("A00", "Barnes Opening", "f3"),
+    opening("A01", "Nimzo-Larsen Attack", "b3"),
+    opening("A02", "Bird Opening", "f4"),
+    opening("A03", "Bird Opening: Dutch Variation", "f4 d5"),
+    opening("A04", "Zukertort Opening", "Nf3"),
+    opening("A05", "Zukertort Opening: Quiet System", "Nf3 Nf6"),
+    opening("A06", "Réti Opening", "Nf3 d5"),
+    opening("A07", "King's Indian Attack", "Nf3 d5 g3"),
+    opening("A09", "Réti Opening: Réti Gambit", "Nf3 d5 c4"),
+    opening("A10", "English Opening", "c4"),
+    opening("A13", "English Opening: Agincourt Defense", "c4 e6"),
+    opening("A15", "English Opening: Anglo-Indian Defense", "c4 Nf6"),
+    opening(
+        "A16",
+        "English Opening: Anglo-Indian Defense, Queen's Knight Variation",
+        "c4 Nf6 Nc3",
+    ),
+    opening("A20", "English Opening: King's English Variation", "c4 e5"),
+    opening(
+        "A22",
+        "English Opening: King's English Variation, Two Knights Variation",
+        "c4 e5 Nc3 Nf6",
+    ),
+    opening(
+        "A25",
+        "English Opening: King's English Variation, Reversed Closed Sicilian",
+        "c4 e5 Nc3 Nc6",
+    ),
+    opening("A30", "English Opening: Symmetrical Variation", "c4 c5"),
+    opening("A40", "Queen's Pawn Game", "d4"),
+    opening("A40", "Englund Gambit", "d4 e5"),
+    opening("A41", "Queen's Pawn Game: Modern Defense", "d4 d6"),
+    opening("A43", "Benoni Defense: Old Benoni", "d4 c5"),
+    opening("A45", "Indian Defense", "d4 Nf6"),
+    opening("A45", "Trompowsky Attack", "d4 Nf6 Bg5"),
+    opening("A45", "Indian Defense: London System", "d4 Nf6 Bf4"),
+    opening("A46", "Indian Defense: Knights Variation", "d4 Nf6 Nf3"),
+    opening("A48", "East Indian Defense", "d4 Nf6 Nf3 g6"),
+    opening("A50", "Indian Defense: Normal Variation", "d4 Nf6 c4"),
+    opening("A51", "Indian Defense: Budapest Defense", "d4 Nf6 c4 e5"),
+    opening("A53", "Old Indian Defense", "d4 Nf6 c4 d6"),
+    opening("A56", "Benoni Defense", "d4 Nf6 c4 c5"),
+    opening("A57", "Benko Gambit", "d4 Nf6 c4 c5 d5 b5"),
+    opening(
+        "A60",
+        "Benoni Defense: Modern Variation",
+        "d4 Nf6 c4 c5 d5 e6",
+    ),
+    opening("A80", "Dutch Defense", "d4 f5"),
+    opening("B00", "King's Pawn Game", "e4"),
+    opening("B00", "Nimzowitsch Defense", "e4 Nc6"),
+    opening("B00", "Owen Defense", "e4 b6"),
+    opening("B00", "St. George Defense", "e4 a6"),
+    opening("B01", "Scandinavian Defense", "e4 d5"),
+    opening(
+        "B01",
+        "Scandinavian Defense: Mieses-Kotroc Variation",
+        "e4 d5 exd5 Qxd5",
+    ),
+    opening(
+        "B01",
+        "Scandinavian Defense: Modern Variation",
+        "e4 d5 exd5 Nf6",
+    ),
+    opening("B02", "Alekhine Defense", "e4 Nf6"),
+    opening("B03", "Alekhine Defense", "e4 Nf6 e5 Nd5 d4"),
+    opening("B06", "Modern Defense", "e4 g6"),
+    opening("B07", "Pirc Defense", "e4 d6 d4 Nf6"),
+    opening("B07", "Pirc Defense: Main Line", "e4 d6 d4 Nf6 Nc3 g6"),
+    opening("B10", "Caro-Kann Defense", "e4 c6"),
+    opening(
+        "B12",
+        "Caro-Kann Defense: Advance Variation",
+        "e4 c6 d4 d5 e5",
+    ),
+    opening(
+        "B13",
+        "Caro-Kann Defense: Exchange Variation",
+        "e4 c6 d4 d5 exd5 cxd5",
+    ),
+    opening("B15", "Caro-Kann Defense", "e4 c6 d4 d5 Nc3"),
+    opening(
+        "B18",
+        "Caro-Kann Defense: Classical Variation",
+        "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Bf5",
+    ),
+    opening("B20", "Sicilian Defense", "e4 c5"),
+    opening(
+        "B21",
+        "Sicilian Defense: Smith-Morra Gambit",
+        "e4 c5 d4 cxd4 c3",
+    ),
+    opening("B22", "Sicilian Defense: Alapin Variation", "e4 c5 c3"),
+    opening("B23", "Sicilian Defense: Closed", "e4 c5 Nc3"),
+    opening("B27", "Sicilian Defense", "e4 c5 Nf3"),
+    opening("B30", "Sicilian Defense: Old Sicilian", "e4 c5 Nf3 Nc6"),
+    opening(
+        "B31",
+        "Sicilian Defense: Rossolimo Variation",
+        "e4 c5 Nf3 Nc6 Bb5",
+    ),
+    opening(
+        "B32",
+        "Sicilian Defense: Open",
+        "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4",
+    ),
+    opening(
+        "B33",
+        "Sicilian Defense: Sveshnikov Variation",
+        "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 Nf6 Nc3 e5",
+    ),
+    opening(
+        "B35",
+        "Sicilian Defense: Accelerated Dragon",
+        "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 g6",
+    ),
+    opening("B40", "Sicilian Defense: French Variation", "e4 c5 Nf3 e6"),
+    opening(
+        "B41",
+        "Sicilian Defense: Kan Variation",
+        "e4 c5 Nf3 e6 d4 cxd4 Nxd4 a6",
+    ),
+    opening(
+        "B44",
+        "Sicilian Defense: Taimanov Variation",
+        "e4 c5 Nf3 e6 d4 cxd4 Nxd4 Nc6",
+    ),
+    opening("B50", "Sicilian Defense: Modern Variations", "e4 c5 Nf3 d6"),
+    opening(
+        "B51",
+        "Sicilian Defense: Moscow Variation",
+        "e4 c5 Nf3 d6 Bb5+",
+    ),
+    opening(
+        "B56",
+        "Sicilian Defense: Classical Variation",
+        "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6",
+    ),
+    opening(
+        "B70",
+        "Sicilian Defense: Dragon Variation",
+        "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6",
+    ),
+    opening(
+        "B80",
+        "Sicilian Defense: Scheveningen Variation",
+        "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6",
+    ),
+    opening(
+        "B90",
+        "Sicilian Defense: Najdorf Variation",
+        "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6",
+    ),
+    opening("C00", "French Defense", "e4 e6"),
+    opening(
+        "C01",
+        "French Defense: Exchange Variation",
+        "e4 e6 d4 d5 exd5",
+    ),
+    opening("C02", "French Defense: Advance Variation", "e4 e6 d4 d5 e5"),
+    opening(
+        "C03",
+        "French Defense: Tarrasch Variation",
+        "e4 e6 d4 d5 Nd2",
+    ),
+    opening(
+        "C10",
+        "French Defense: Paulsen Variation",
+        "e4 e6 d4 d5 Nc3",
+    ),
+    opening(
+        "C10",
+        "French Defense: Rubinstein Variation",
+        "e4 e6 d4 d5 Nc3 dxe4",
+    ),
+    opening(
+        "C11",
+        "French Defense: Classical Variation",
+        "e4 e6 d4 d5 Nc3 Nf6",
+    ),
+    opening(
+        "C15",
+        "French Defense: Winawer Variation",
+        "e4 e6 d4 d5 Nc3 Bb4",
+    ),
+    opening("C20", "King's Pawn Game", "e4 e5"),
+    opening("C20", "King's Pawn Game: Wayward Queen Attack", "e4 e5 Qh5"),
+    opening("C21", "Danish Gambit", "e4 e5 d4 exd4 c3"),
+    opening("C22", "Center Game", "e4 e5 d4 exd4 Qxd4"),
+    opening("C23", "Bishop's Opening", "e4 e5 Bc4"),
+    opening("C25", "Vienna Game", "e4 e5 Nc3"),
+    opening("C30", "King's Gambit", "e4 e5 f4"),
+    opening("C33", "King's Gambit Accepted", "e4 e5 f4 exf4"),
+    opening("C40", "King's Knight Opening", "e4 e5 Nf3"),
+    opening("C40", "Latvian Gambit", "e4 e5 Nf3 f5"),
+    opening("C41", "Philidor Defense", "e4 e5 Nf3 d6"),
+    opening("C42", "Petrov's Defense", "e4 e5 Nf3 Nf6"),
+    opening(
+        "C44",
+        "King's Knight Opening: Normal Variation",
+        "e4 e5 Nf3 Nc6",
+    ),
+    opening("C44", "Ponziani Opening", "e4 e5 Nf3 Nc6 c3"),
+    opening("C44", "Scotch Game", "e4 e5 Nf3 Nc6 d4"),
+    opening("C45", "Scotch Game", "e4 e5 Nf3 Nc6 d4 exd4 Nxd4"),
+    opening("C46", "Three Knights Opening", "e4 e5 Nf3 Nc6 Nc3"),
+    opening("C47", "Four Knights Game", "e4 e5 Nf3 Nc6 Nc3 Nf6"),
+    opening("C50", "Italian Game", "e4 e5 Nf3 Nc6 Bc4"),
+    opening("C50", "Italian Game: Giuoco Piano", "e4 e5 Nf3 Nc6 Bc4 Bc5"),
+    opening(
+        "C51",
+        "Italian Game: Evans Gambit",
+        "e4 e5 Nf3 Nc6 Bc4 Bc5 b4",
+    ),
+    opening(
+        "C53",
+        "Italian Game: Classical Variation",
+        "e4 e5 Nf3 Nc6 Bc4 Bc5 c3",
+    ),
+    opening(
+        "C55",
+        "Italian Game: Two Knights Defense",
+        "e4 e5 Nf3 Nc6 Bc4 Nf6",
+    ),
+    opening(
+        "C57",
+        "Italian Game: Two Knights Defense, Knight Attack",
+        "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5",
+    ),
+    opening(
+        "C57",
+        "Italian Game: Two Knights Defense, Fried Liver Attack",
+        "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5 d5 exd5 Nxd5 Nxf7",
+    ),
+    opening("C60", "Ruy Lopez", "e4 e5 Nf3 Nc6 Bb5"),
+    opening("C62", "Ruy Lopez: Steinitz Defense", "e4 e5 Nf3 Nc6 Bb5 d6"),
+    opening("C65", "Ruy Lopez: Berlin Defense", "e4 e5 Nf3 Nc6 Bb5 Nf6"),
+    opening(
+        "C68",
+        "Ruy Lopez: Exchange Variation",
+        "e4 e5 Nf3 Nc6 Bb5 a6 Bxc6",
+    ),
+    opening(
+        "C70",
+        "Ruy Lopez: Morphy Defense",
+        "e4 e5 Nf3 Nc6 Bb5 a6 Ba4",
+    ),
+    opening(
+        "C78",
+        "Ruy Lopez: Morphy Defense",
+        "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O",
+    ),
+    opening(
+        "C80",
+        "Ruy Lopez: Open",
+        "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Nxe4",
+    ),
+    opening(
+        "C84",
+        "Ruy Lopez: Closed",
+        "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7",
+    ),
+    opening(
+        "C89",
+        "Ruy Lopez: Marshall Attack",
+        "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d5",
+    ),
+    opening("D00", "Queen's Pawn Game", "d4 d5"),
+    opening("D00", "Queen's Pawn Game: London System", "d4 d5 Bf4"),
+    opening("D01", "Richter-Veresov Attack", "d4 d5 Nc3 Nf6 Bg5"),
+    opening("D02", "Queen's Pawn Game: Zukertort Variation", "d4 d5 Nf3"),
+    opening("D06", "Queen's Gambit", "d4 d5 c4"),
+    opening(
+        "D07",
+        "Queen's Gambit Declined: Chigorin Defense",
+        "d4 d5 c4 Nc6",
+    ),
+    opening(
+        "D08",
+        "Queen's Gambit Declined: Albin Countergambit",
+        "d4 d5 c4 e5",
+    ),
+    opening("D10", "Slav Defense", "d4 d5 c4 c6"),
+    opening("D11", "Slav Defense: Modern Line", "d4 d5 c4 c6 Nf3"),
+    opening("D20", "Queen's Gambit Accepted", "d4 d5 c4 dxc4"),
+    opening("D30", "Queen's Gambit Declined", "d4 d5 c4 e6"),
+    opening(
+        "D31",
+        "Queen's Gambit Declined: Queen's Knight Variation",
+        "d4 d5 c4 e6 Nc3",
+    ),
+    opening("D32", "Tarrasch Defense", "d4 d5 c4 e6 Nc3 c5"),
+    opening(
+        "D35",
+        "Queen's Gambit Declined: Exchange Variation",
+        "d4 d5 c4 e6 Nc3 Nf6 cxd5",
+    ),
+    opening(
+        "D37",
+        "Queen's Gambit Declined: Three Knights Variation",
+        "d4 d5 c4 e6 Nc3 Nf6 Nf3",
+    ),
+    opening("D43", "Semi-Slav Defense", "d4 d5 c4 c6 Nf3 Nf6 Nc3 e6"),
+    opening(
+        "D50",
+        "Queen's Gambit Declined: Modern Variation",
+        "d4 d5 c4 e6 Nc3 Nf6 Bg5",
+    ),
+    opening("D80", "Grünfeld Defense", "d4 Nf6 c4 g6 Nc3 d5"),
+    opening(
+        "D85",
+        "Grünfeld Defense: Exchange Variation",
+        "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5",
+    ),
+    opening("E00", "Indian Defense", "d4 Nf6 c4 e6"),
+    opening("E01", "Catalan Opening", "d4 Nf6 c4 e6 g3"),
+    opening(
+        "E10",
+        "Indian Defense: Anti-Nimzo-Indian",
+        "d4 Nf6 c4 e6 Nf3",
+    ),
+    opening("E11", "Bogo-Indian Defense", "d4 Nf6 c4 e6 Nf3 Bb4+"),
+    opening("E12", "Queen's Indian Defense", "d4 Nf6 c4 e6 Nf3 b6"),
+    opening("E20", "Nimzo-Indian Defense", "d4 Nf6 c4 e6 Nc3 Bb4"),
+    opening(
+        "E32",
+        "Nimzo-Indian Defense: Classical Variation",
+        "d4 Nf6 c4 e6 Nc3 Bb4 Qc2",
+    ),
+    opening(
+        "E40",
+        "Nimzo-Indian Defense: Normal Variation",
+        "d4 Nf6 c4 e6 Nc3 Bb4 e3",
+    ),
+    opening("E60", "King's Indian Defense", "d4 Nf6 c4 g6"),
+    opening("E61", "King's Indian Defense", "d4 Nf6 c4 g6 Nc3 Bg7"),
+    opening(
+        "E70",
+        "King's Indian Defense: Normal Variation",
+        "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6",
+    ),
+    opening(
+        "E80",
+        "King's Indian Defense: Sämisch Variation",
+        "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3",
+    ),
+    opening(
+        "E97",
+        "King's Indian Defense: Orthodox Variation",
+        "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O Nc6",
+    ),
+];
+
+lazy_static! {
+    // Position after each line to its index in `OPENINGS`
+    static ref POSITIONS: HashMap<u64, usize> = {
+        let mut positions = HashMap::new();
+        for (i, opening) in OPENINGS.iter().enumerate() {
+            let mut board = Board::start_pos();
+            for san in opening.moves.split(' ') {
+                let m = from_san(&board, san)
+                    .unwrap_or_else(|| panic!("Illegal move {} in {}", san, opening.name));
+                board.make_move(&m);
+            }
+            positions.insert(book_key(&board), i);
+        }
+        positions
+    };
+    // No line is longer, later positions are never looked up
+    static ref MAX_PLY: usize = OPENINGS
+        .iter()
+        .map(|o| o.moves.split(' ').count())
+        .max()
+        .unwrap_or(0);
+}
+
+pub fn classify(moves: &[Move]) -> Option<&'static Opening> {
+    let mut board = Board::start_pos();
+    let mut opening = None;
+    for m in moves.iter().take(*MAX_PLY) {
+        board.make_move(m);
+        if let Some(&i) = POSITIONS.get(&book_key(&board)) {
+            opening = Some(&OPENINGS[i]);
+        }
+    }
+    opening
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(sans: &str) -> Vec<Move> {
+        let mut board = Board::start_pos();
+        sans.split(' ')
+            .map(|san| {
+                let m = from_san(&board, san).unwrap();
+                board.make_move(&m);
+                m
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_line_is_legal() {
+        // Builds the table, which panics on an illegal move
+        assert!(!POSITIONS.is_empty());
+    }
+
+    #[test]
+    fn sicilian() {
+        assert_eq!(classify(&moves("e4 c5")).unwrap().eco, "B20");
+        // Later moves without a name of their own keep the last one
+        assert_eq!(classify(&moves("e4 c5 a3 a6")).unwrap().eco, "B20");
+        // Transposed into e4 c5 Nf3
+        assert_eq!(classify(&moves("Nf3 c5 e4")).unwrap().eco, "B27");
+    }
+
+    #[test]
+    fn no_moves_no_name() {
+        assert!(classify(&[]).is_none());
+    }
+}
@@ -0,0 +1,257 @@
+use super::kpk;
+use crate::{
+    bitboard::DARK_SQUARES,
+    board::Board,
+    movegen::legal_moves,
+    piece::{Color, Piece, PieceKind},
+    score::KNOWN_WIN,
+};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+// Endgames the general evaluation gets wrong: some are evaluated by their
+// own functions, chosen by the material on the board, others keep the
+// general evaluation but scale it towards a draw.
+
+pub const SCALE_NORMAL: i32 = 64;
+
+const KNIGHT: i32 = 300;
+const BISHOP: i32 = 300;
+const ROOK: i32 = 500;
+const QUEEN: i32 = 900;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endgame {
+    Kpk,
+    Krk,
+    Kqk,
+    Kbnk,
+}
+
+lazy_static! {
+    static ref ENDGAMES: HashMap<u64, (Endgame, Color)> = {
+        let mut endgames = HashMap::new();
+        for (code, endgame) in [
+            ("KPvK", Endgame::Kpk),
+            ("KRvK", Endgame::Krk),
+            ("KQvK", Endgame::Kqk),
+            ("KBNvK", Endgame::Kbnk),
+        ] {
+            for strong in [Color::White, Color::Black] {
+                endgames.insert(code_key(code, strong), (endgame, strong));
+            }
+        }
+        endgames
+    };
+}
+
+const KEY_PIECES: [PieceKind; 5] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+];
+
+fn key_shift(kind: PieceKind, color: Color) -> u64 {
+    4 * (kind as u64 + 5 * (color == Color::Black) as u64)
+}
+
+// Piece counts, kings aside, four bits for each kind and color
+pub fn material_key(board: &Board) -> u64 {
+    let mut key = 0;
+    for color in [Color::White, Color::Black] {
+        for kind in KEY_PIECES {
+            let count = board.board(Piece::new(kind, color)).0.count_ones() as u64;
+            key |= count.min(15) << key_shift(kind, color);
+        }
+    }
+    key
+}
+
+// Key of a signature like "KBNvK", the first side playing `strong`
+fn code_key(code: &str, strong: Color) -> u64 {
+    let (first, second) = code.split_once('v').unwrap();
+    let mut key = 0;
+    for (side, color) in [(first, strong), (second, strong.opposite())] {
+        for c in side.chars().filter(|&c| c != 'K') {
+            let kind = match c {
+                'P' => PieceKind::Pawn,
+                'N' => PieceKind::Knight,
+                'B' => PieceKind::Bishop,
+                'R' => PieceKind::Rook,
+                _ => PieceKind::Queen,
+            };
+            key += 1 << key_shift(kind, color);
+        }
+    }
+    key
+}
+
+fn square(board: &Board, kind: PieceKind, color: Color) -> usize {
+    board.board(Piece::new(kind, color)).0.trailing_zeros() as usize
+}
+
+fn distance(a: usize, b: usize) -> i32 {
+    (a % 8).abs_diff(b % 8).max((a / 8).abs_diff(b / 8)) as i32
+}
+
+// Higher the closer the square is to an edge
+fn push_to_edge(sq: usize) -> i32 {
+    let edge = |x: usize| x.min(7 - x) as i32;
+    let (file, rank) = (edge(sq % 8), edge(sq / 8));
+    90 - (7 * file * file / 2 + 7 * rank * rank / 2)
+}
+
+// Higher the closer the square is to a1 or h8
+fn push_to_corner(sq: usize) -> i32 {
+    (7 - (sq / 8) as i32 - (sq % 8) as i32).abs()
+}
+
+fn push_close(a: usize, b: usize) -> i32 {
+    140 - 20 * distance(a, b)
+}
+
+// Evaluation of a specialized endgame from the side to move's view, `None`
+// for any other material
+pub fn evaluate(board: &Board) -> Option<i32> {
+    if board.occ.0.count_ones() > 4 {
+        return None;
+    }
+    let &(endgame, strong) = ENDGAMES.get(&material_key(board))?;
+    let weak = strong.opposite();
+    let strong_king = square(board, PieceKind::King, strong);
+    let weak_king = square(board, PieceKind::King, weak);
+
+    // A lone king without moves is stalemated, checkmates are left to the
+    // search
+    if endgame != Endgame::Kpk && board.side_to_move == weak && legal_moves(board).is_empty() {
+        return Some(0);
+    }
+
+    let eval = match endgame {
+        Endgame::Kpk => {
+            // The bitbase has the pawn moving up the board
+            let flip = if strong == Color::White { 0 } else { 56 };
+            let pawn = square(board, PieceKind::Pawn, strong) ^ flip;
+            let strong_to_move = board.side_to_move == strong;
+            if kpk::probe(strong_king ^ flip, pawn, weak_king ^ flip, strong_to_move) {
+                KNOWN_WIN + 100 + 10 * (pawn / 8) as i32
+            } else {
+                0
+            }
+        }
+        Endgame::Krk | Endgame::Kqk => {
+            let material = if endgame == Endgame::Krk { ROOK } else { QUEEN };
+            KNOWN_WIN + material + push_to_edge(weak_king) + push_close(strong_king, weak_king)
+        }
+        Endgame::Kbnk => {
+            // Mate only happens in a corner of the bishop's color, a1 is
+            // dark so light bishops use the mirrored board
+            let bishop = square(board, PieceKind::Bishop, strong);
+            let dark = (bishop / 8 + bishop % 8) % 2 == 0;
+            let corner_king = if dark { weak_king } else { weak_king ^ 7 };
+            KNOWN_WIN
+                + KNIGHT
+                + BISHOP
+                + push_close(strong_king, weak_king)
+                + 200 * push_to_corner(corner_king)
+        }
+    };
+
+    Some(if board.side_to_move == strong {
+        eval
+    } else {
+        -eval
+    })
+}
+
+fn count(board: &Board, kind: PieceKind, color: Color) -> i32 {
+    board.board(Piece::new(kind, color)).0.count_ones() as i32
+}
+
+fn non_pawn_material(board: &Board, color: Color) -> i32 {
+    KNIGHT * count(board, PieceKind::Knight, color)
+        + BISHOP * count(board, PieceKind::Bishop, color)
+        + ROOK * count(board, PieceKind::Rook, color)
+        + QUEEN * count(board, PieceKind::Queen, color)
+}
+
+// How much of a general evaluation, given from white's view, to keep, out
+// of `SCALE_NORMAL`
+pub fn scale(board: &Board, eval: i32) -> i32 {
+    let strong = if eval >= 0 {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let weak = strong.opposite();
+    let strong_material = non_pawn_material(board, strong);
+    let weak_material = non_pawn_material(board, weak);
+
+    // Without pawns a minor piece more isn't enough to win
+    if count(board, PieceKind::Pawn, strong) == 0 && strong_material - weak_material <= BISHOP {
+        return if strong_material < ROOK {
+            0
+        } else if weak_material <= BISHOP {
+            4
+        } else {
+            14
+        };
+    }
+
+    // Two knights can't force mate
+    if count(board, PieceKind::Pawn, strong) == 0
+        && count(board, PieceKind::Pawn, weak) == 0
+        && strong_material == 2 * KNIGHT
+        && count(board, PieceKind::Knight, strong) == 2
+        && weak_material == 0
+    {
+        return 0;
+    }
+
+    // Opposite colored bishops and pawns are hard to win even a couple of
+    // pawns up
+    if strong_material == BISHOP && weak_material == BISHOP {
+        let w = board.w_bishop.0;
+        let b = board.b_bishop.0;
+        if w != 0 && b != 0 && ((w & DARK_SQUARES) == 0) != ((b & DARK_SQUARES) == 0) {
+            let pawns = count(board, PieceKind::Pawn, strong) - count(board, PieceKind::Pawn, weak);
+            return (16 + 12 * (pawns - 1).max(0)).min(SCALE_NORMAL);
+        }
+    }
+
+    SCALE_NORMAL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(fen: &str) -> i32 {
+        evaluate(&Board::from_fen(fen)).unwrap()
+    }
+
+    #[test]
+    fn kpk_wins() {
+        assert!(eval("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1") >= KNOWN_WIN);
+        assert!(eval("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1") <= -KNOWN_WIN);
+        // The same with colors swapped
+        assert!(eval("8/8/8/8/4p3/4k3/8/4K3 b - - 0 1") >= KNOWN_WIN);
+    }
+
+    #[test]
+    fn kpk_draws() {
+        assert_eq!(eval("8/8/4k3/8/4K3/4P3/8/8 w - - 0 1"), 0);
+        assert_eq!(eval("8/8/8/4k3/4p3/4K3/8/8 b - - 0 1"), 0);
+    }
+
+    #[test]
+    fn other_material_is_left_to_the_evaluation() {
+        assert_eq!(evaluate(&Board::from_fen(crate::board::DEFAULT_FEN)), None);
+        assert_eq!(
+            evaluate(&Board::from_fen("4k3/8/8/8/8/8/4P3/3QK3 w - - 0 1")),
+            None
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use super::pawn_attacks;
+use crate::{movegen::precalc::PRECALC, piece::Color};
+
+// King and pawn against king: one bit per position telling whether the side
+// with the pawn wins. Positions are stored with the pawn moving up the board
+// and on files a-d, everything else is mirrored first. The bitbase is built
+// by `cargo run --bin kpk` and compiled in.
+
+// Side to move, both kings and 24 pawn squares
+pub const POSITIONS: usize = 2 * 64 * 64 * 24;
+
+static BITBASE: &[u8] = include_bytes!("kpk.bin");
+
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+fn index(strong_to_move: bool, strong_king: usize, weak_king: usize, pawn: usize) -> usize {
+    let pawn = (pawn / 8 - 1) * 4 + pawn % 8;
+    strong_to_move as usize | (strong_king << 1) | (weak_king << 7) | (pawn << 13)
+}
+
+fn decode(idx: usize) -> (bool, usize, usize, usize) {
+    let pawn = idx >> 13;
+    (
+        idx & 1 != 0,
+        (idx >> 1) & 63,
+        (idx >> 7) & 63,
+        (pawn / 4 + 1) * 8 + pawn % 4,
+    )
+}
+
+// Squares are given from the strong side's view, with its pawn moving up
+pub fn probe(strong_king: usize, pawn: usize, weak_king: usize, strong_to_move: bool) -> bool {
+    let mirror = if pawn % 8 > 3 { 7 } else { 0 };
+    let idx = index(
+        strong_to_move,
+        strong_king ^ mirror,
+        weak_king ^ mirror,
+        pawn ^ mirror,
+    );
+    BITBASE[idx / 8] & (1 << (idx % 8)) != 0
+}
+
+fn distance(a: usize, b: usize) -> usize {
+    (a % 8).abs_diff(b % 8).max((a / 8).abs_diff(b / 8))
+}
+
+fn king_attacks(sq: usize) -> u64 {
+    PRECALC.king[sq].0
+}
+
+// Results known without looking at any moves
+fn initial(idx: usize) -> u8 {
+    let (strong_to_move, sk, wk, pawn) = decode(idx);
+    let attacks = pawn_attacks(1 << pawn, Color::White);
+
+    if distance(sk, wk) <= 1
+        || sk == pawn
+        || wk == pawn
+        || (strong_to_move && attacks & (1 << wk) != 0)
+    {
+        return INVALID;
+    }
+
+    // The pawn promotes and can't be taken
+    let promotion = pawn + 8;
+    if strong_to_move
+        && pawn / 8 == 6
+        && sk != promotion
+        && wk != promotion
+        && (distance(wk, promotion) > 1 || distance(sk, promotion) == 1)
+    {
+        return WIN;
+    }
+
+    // Stalemate, or the pawn can be taken
+    if !strong_to_move {
+        let escapes = king_attacks(wk) & !(king_attacks(sk) | attacks);
+        if escapes == 0 || king_attacks(wk) & (1 << pawn) & !king_attacks(sk) != 0 {
+            return DRAW;
+        }
+    }
+
+    UNKNOWN
+}
+
+// A side wins or draws if one of its moves does, and otherwise gets the
+// other result once every move is known
+fn classify(db: &[u8], idx: usize) -> u8 {
+    let (strong_to_move, sk, wk, pawn) = decode(idx);
+    let mut result = INVALID;
+
+    let (good, bad) = if strong_to_move {
+        let mut moves = king_attacks(sk);
+        while moves != 0 {
+            let to = moves.trailing_zeros() as usize;
+            moves &= moves - 1;
+            result |= db[index(false, to, wk, pawn)];
+        }
+        if pawn / 8 < 6 {
+            result |= db[index(false, sk, wk, pawn + 8)];
+        }
+        if pawn / 8 == 1 && pawn + 8 != sk && pawn + 8 != wk {
+            result |= db[index(false, sk, wk, pawn + 16)];
+        }
+        (WIN, DRAW)
+    } else {
+        let mut moves = king_attacks(wk);
+        while moves != 0 {
+            let to = moves.trailing_zeros() as usize;
+            moves &= moves - 1;
+            result |= db[index(true, sk, to, pawn)];
+        }
+        (DRAW, WIN)
+    };
+
+    if result & good != 0 {
+        good
+    } else if result & UNKNOWN != 0 {
+        UNKNOWN
+    } else {
+        bad
+    }
+}
+
+// Retrograde analysis over all positions, returns the packed bitbase
+pub fn generate() -> Vec<u8> {
+    let mut db = (0..POSITIONS).map(initial).collect::<Vec<_>>();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for idx in 0..POSITIONS {
+            if db[idx] == UNKNOWN {
+                db[idx] = classify(&db, idx);
+                changed |= db[idx] != UNKNOWN;
+            }
+        }
+    }
+
+    let mut bits = vec![0; POSITIONS / 8];
+    for (idx, result) in db.iter().enumerate() {
+        if *result == WIN {
+            bits[idx / 8] |= 1 << (idx % 8);
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const E3: usize = 20;
+    const E4: usize = 28;
+    const E5: usize = 36;
+    const E6: usize = 44;
+    const E8: usize = 60;
+
+    #[test]
+    fn king_on_the_sixth_wins() {
+        // Ke6, Pe5 against Ke8 wins whoever moves
+        assert!(probe(E6, E5, E8, true));
+        assert!(probe(E6, E5, E8, false));
+    }
+
+    #[test]
+    fn opposition_decides() {
+        // Ke4, Pe3 against Ke6: black keeps the opposition with white to
+        // move, with black to move white takes it
+        assert!(!probe(E4, E3, E6, true));
+        assert!(probe(E4, E3, E6, false));
+    }
+
+    #[test]
+    fn rook_pawn_is_drawn_with_the_king_in_the_corner() {
+        // Kb1, Pa2 against Ka8
+        assert!(!probe(1, 8, 56, true));
+    }
+}
@@ -0,0 +1,296 @@
+use self::{params::DEFAULT_PARAMS, pawns::PawnStructure};
+use crate::{
+    bitboard::*,
+    board::Board,
+    movegen::precalc::PRECALC,
+    piece::{Color, Piece, PieceKind, PIECE_KINDS},
+};
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+pub mod endgame;
+pub mod kpk;
+pub mod params;
+pub mod pawns;
+
+// A pair of middlegame and endgame scores, blended by the game phase
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tapered {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+pub const fn s(mg: i32, eg: i32) -> Tapered {
+    Tapered { mg, eg }
+}
+
+impl Tapered {
+    pub fn taper(self, phase: i32) -> i32 {
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for Tapered {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        s(self.mg + rhs.mg, self.eg + rhs.eg)
+    }
+}
+impl AddAssign for Tapered {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+impl Sub for Tapered {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        s(self.mg - rhs.mg, self.eg - rhs.eg)
+    }
+}
+impl SubAssign for Tapered {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+impl Mul<i32> for Tapered {
+    type Output = Self;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        s(self.mg * rhs, self.eg * rhs)
+    }
+}
+impl Neg for Tapered {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        s(-self.mg, -self.eg)
+    }
+}
+
+pub const MAX_PHASE: i32 = 24;
+
+const MOBILITY_BASE: [i32; 6] = [0, 4, 7, 7, 14, 0];
+const KING_ATTACK_WEIGHT: [i32; 6] = [0, 2, 2, 3, 5, 0];
+const KING_DANGER_MAX: i32 = 500;
+
+// The tunable evaluation weights, see `params::DEFAULT_PARAMS`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalParams {
+    pub piece_value: [Tapered; 6],
+    pub psqt: [[Tapered; 64]; 6],
+    pub bishop_pair: Tapered,
+    pub doubled_pawn: Tapered,
+    pub isolated_pawn: Tapered,
+    pub backward_pawn: Tapered,
+    pub passed_pawn: [Tapered; 8],
+    pub rook_open_file: Tapered,
+    pub rook_semi_open_file: Tapered,
+    pub mobility: [Tapered; 6],
+    pub king_shield: [Tapered; 2],
+}
+
+impl EvalParams {
+    pub fn weights_mut(&mut self) -> Vec<&mut Tapered> {
+        self.piece_value
+            .iter_mut()
+            .chain(self.psqt.iter_mut().flatten())
+            .chain([
+                &mut self.bishop_pair,
+                &mut self.doubled_pawn,
+                &mut self.isolated_pawn,
+                &mut self.backward_pawn,
+            ])
+            .chain(self.passed_pawn.iter_mut())
+            .chain([&mut self.rook_open_file, &mut self.rook_semi_open_file])
+            .chain(self.mobility.iter_mut())
+            .chain(self.king_shield.iter_mut())
+            .collect()
+    }
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        DEFAULT_PARAMS
+    }
+}
+
+// Knights and bishops count 1, rooks 2 and queens 4; the starting position
+// is phase 24 and bare kings and pawns are phase 0
+pub fn game_phase(board: &Board) -> i32 {
+    let minors = board.w_knight | board.b_knight | board.w_bishop | board.b_bishop;
+    let rooks = board.w_rook | board.b_rook;
+    let queens = board.w_queen | board.b_queen;
+    let phase = minors.0.count_ones() + 2 * rooks.0.count_ones() + 4 * queens.0.count_ones();
+    (phase as i32).min(MAX_PHASE)
+}
+
+pub fn evaluate(board: &Board) -> i32 {
+    evaluate_with_params(board, &DEFAULT_PARAMS)
+}
+
+pub fn evaluate_with_params(board: &Board, params: &EvalParams) -> i32 {
+    let pawns = PawnStructure::with_params(board, params);
+    evaluate_with_pawns(board, &pawns, params)
+}
+
+pub fn evaluate_with_pawns(board: &Board, pawns: &PawnStructure, params: &EvalParams) -> i32 {
+    if let Some(eval) = endgame::evaluate(board) {
+        return eval;
+    }
+
+    let mut score = pawns.score;
+    score += material_and_position(board, Color::White, params);
+    score -= material_and_position(board, Color::Black, params);
+    score += pieces(board, Color::White, params);
+    score -= pieces(board, Color::Black, params);
+
+    let eval = score.taper(game_phase(board));
+    let eval = eval * endgame::scale(board, eval) / endgame::SCALE_NORMAL;
+    if board.side_to_move == Color::White {
+        eval
+    } else {
+        -eval
+    }
+}
+
+fn material_and_position(board: &Board, color: Color, params: &EvalParams) -> Tapered {
+    let mut score = Tapered::default();
+    for p in PIECE_KINDS.into_iter() {
+        let mut b = board.board(Piece::new(p, color)).0;
+        while b != 0 {
+            let square = b.trailing_zeros() as usize;
+            b &= b - 1;
+            score += params.piece_value[p as usize];
+            score += params.psqt[p as usize][relative_square(color, square)];
+        }
+    }
+
+    let bishops = board.board(Piece::new(PieceKind::Bishop, color));
+    if bishops.0.count_ones() >= 2 {
+        score += params.bishop_pair;
+    }
+    score
+}
+
+fn pieces(board: &Board, color: Color, params: &EvalParams) -> Tapered {
+    let us = color_occ(board, color);
+    let pawns = board.board(Piece::new(PieceKind::Pawn, color)).0;
+    let their_pawns = board.board(Piece::new(PieceKind::Pawn, color.opposite())).0;
+    let safe = !us.0 & !pawn_attacks(their_pawns, color.opposite());
+
+    let mut score = Tapered::default();
+    for p in [
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+    ] {
+        let mut b = board.board(Piece::new(p, color)).0;
+        while b != 0 {
+            let square = b.trailing_zeros() as usize;
+            b &= b - 1;
+
+            let attacks = piece_attacks(p, square, board.occ).0;
+            let mobility = (attacks & safe).count_ones() as i32;
+            score += params.mobility[p as usize] * (mobility - MOBILITY_BASE[p as usize]);
+
+            if p == PieceKind::Rook {
+                let file = FILE_A << (square % 8);
+                if (pawns | their_pawns) & file == 0 {
+                    score += params.rook_open_file;
+                } else if pawns & file == 0 {
+                    score += params.rook_semi_open_file;
+                }
+            }
+        }
+    }
+
+    score + king_safety(board, color, params)
+}
+
+fn king_safety(board: &Board, color: Color, params: &EvalParams) -> Tapered {
+    let king = board.board(Piece::new(PieceKind::King, color)).0;
+    if king == 0 {
+        return Tapered::default();
+    }
+    let square = king.trailing_zeros() as usize;
+    let pawns = board.board(Piece::new(PieceKind::Pawn, color)).0;
+
+    let mut score = Tapered::default();
+
+    // Pawn shield in front of a castled king
+    let rank = relative_square(color, square) / 8;
+    if rank <= 1 {
+        let files = adjacent_files(square % 8) | (FILE_A << (square % 8));
+        let ahead = |n: usize| files & relative_rank(color, rank + n);
+        score += params.king_shield[0] * (pawns & ahead(1)).count_ones() as i32;
+        score += params.king_shield[1] * (pawns & ahead(2)).count_ones() as i32;
+    }
+
+    // Enemy pieces attacking the squares around the king
+    let zone = PRECALC.king[square].0 | king;
+    let mut units = 0;
+    for p in [
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+    ] {
+        let mut b = board.board(Piece::new(p, color.opposite())).0;
+        while b != 0 {
+            let attacker = b.trailing_zeros() as usize;
+            b &= b - 1;
+            let hits = (piece_attacks(p, attacker, board.occ).0 & zone).count_ones() as i32;
+            units += KING_ATTACK_WEIGHT[p as usize] * hits;
+        }
+    }
+    score.mg -= (units * units / 2).min(KING_DANGER_MAX);
+
+    score
+}
+
+pub fn piece_attacks(piece: PieceKind, square: usize, occ: BitBoard) -> BitBoard {
+    match piece {
+        PieceKind::Knight => PRECALC.knight[square],
+        PieceKind::Bishop => PRECALC.bishop_attack(square, occ),
+        PieceKind::Rook => PRECALC.rook_attack(square, occ),
+        PieceKind::Queen => PRECALC.queen_attack(square, occ),
+        PieceKind::King => PRECALC.king[square],
+        PieceKind::Pawn => unreachable!(),
+    }
+}
+
+pub fn pawn_attacks(pawns: u64, color: Color) -> u64 {
+    match color {
+        Color::White => ((pawns << 7) & !FILE_H) | ((pawns << 9) & !FILE_A),
+        Color::Black => ((pawns >> 9) & !FILE_H) | ((pawns >> 7) & !FILE_A),
+    }
+}
+
+pub fn relative_square(color: Color, square: usize) -> usize {
+    match color {
+        Color::White => square,
+        Color::Black => square ^ 56,
+    }
+}
+
+// The rank `rank` counted from the given side's back rank
+fn relative_rank(color: Color, rank: usize) -> u64 {
+    match color {
+        Color::White => RANK_1 << (8 * rank),
+        Color::Black => RANK_8 >> (8 * rank),
+    }
+}
+
+pub fn adjacent_files(file: usize) -> u64 {
+    let file = FILE_A << file;
+    ((file << 1) & !FILE_A) | ((file >> 1) & !FILE_H)
+}
+
+fn color_occ(board: &Board, color: Color) -> BitBoard {
+    match color {
+        Color::White => board.w_occ,
+        Color::Black => board.b_occ,
+    }
+}
@@ -0,0 +1,194 @@
+// Evaluation weights, regenerated by `cargo run --release --bin tune`
+use super::{s, EvalParams, Tapered};
+
+pub const DEFAULT_PARAMS: EvalParams = EvalParams {
+    piece_value: [
+        s(82, 94),
+        s(337, 281),
+        s(365, 297),
+        s(477, 512),
+        s(1025, 936),
+        s(0, 0),
+    ],
+    // Piece square tables, from white's point of view with a1 first
+    psqt: [
+        zip(&PAWN_MG, &PAWN_EG),
+        zip(&KNIGHT_MG, &KNIGHT_EG),
+        zip(&BISHOP_MG, &BISHOP_EG),
+        zip(&ROOK_MG, &ROOK_EG),
+        zip(&QUEEN_MG, &QUEEN_EG),
+        zip(&KING_MG, &KING_EG),
+    ],
+    bishop_pair: s(30, 50),
+    doubled_pawn: s(-10, -20),
+    isolated_pawn: s(-10, -15),
+    backward_pawn: s(-8, -10),
+    passed_pawn: [
+        s(0, 0),
+        s(5, 10),
+        s(10, 15),
+        s(15, 30),
+        s(25, 55),
+        s(40, 90),
+        s(60, 130),
+        s(0, 0),
+    ],
+    rook_open_file: s(25, 10),
+    rook_semi_open_file: s(12, 6),
+    mobility: [s(0, 0), s(4, 4), s(5, 5), s(2, 4), s(1, 2), s(0, 0)],
+    king_shield: [s(15, 0), s(8, 0)],
+};
+
+const fn zip(mg: &[i32; 64], eg: &[i32; 64]) -> [Tapered; 64] {
+    let mut res = [s(0, 0); 64];
+    let mut i = 0;
+    while i < 64 {
+        res[i] = s(mg[i], eg[i]);
+        i += 1;
+    }
+    res
+}
+
+#[rustfmt::skip]
+const PAWN_MG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     -5,  -2,  -4, -12, -12,  10,  15,  -5,
+     -5,  -2,   2,   0,   0,   0,   5,  -5,
+     -5,   0,   5,  15,  15,   5,   0,  -5,
+      0,   5,  10,  20,  20,  10,   5,   0,
+     10,  15,  20,  30,  30,  20,  15,  10,
+     40,  40,  40,  45,  45,  40,  40,  40,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const PAWN_EG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,   5,   5,   5,   5,   5,   5,   5,
+     15,  15,  15,  15,  15,  15,  15,  15,
+     30,  30,  30,  30,  30,  30,  30,  30,
+     55,  55,  55,  55,  55,  55,  55,  55,
+     90,  90,  90,  90,  90,  90,  90,  90,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_MG: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const KNIGHT_EG: [i32; 64] = [
+    -40, -30, -20, -20, -20, -20, -30, -40,
+    -30, -15,  -5,   0,   0,  -5, -15, -30,
+    -20,  -5,   5,  10,  10,   5,  -5, -20,
+    -20,   0,  10,  15,  15,  10,   0, -20,
+    -20,   0,  10,  15,  15,  10,   0, -20,
+    -20,  -5,   5,  10,  10,   5,  -5, -20,
+    -30, -15,  -5,   0,   0,  -5, -15, -30,
+    -40, -30, -20, -20, -20, -20, -30, -40,
+];
+
+#[rustfmt::skip]
+const BISHOP_MG: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const BISHOP_EG: [i32; 64] = [
+    -15, -10, -10,  -5,  -5, -10, -10, -15,
+    -10,  -5,   0,   0,   0,   0,  -5, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,  10,  10,   5,   0,  -5,
+     -5,   0,   5,  10,  10,   5,   0,  -5,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,  -5,   0,   0,   0,   0,  -5, -10,
+    -15, -10, -10,  -5,  -5, -10, -10, -15,
+];
+
+#[rustfmt::skip]
+const ROOK_MG: [i32; 64] = [
+      0,   0,   0,   5,   5,   0,   0,   0,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      5,  10,  10,  10,  10,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const ROOK_EG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+     10,  10,  10,  10,  10,  10,  10,  10,
+      5,   5,   5,   5,   5,   5,   5,   5,
+];
+
+#[rustfmt::skip]
+const QUEEN_MG: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const QUEEN_EG: [i32; 64] = [
+    -20, -15, -10, -10, -10, -10, -15, -20,
+    -15,  -5,   0,   0,   0,   0,  -5, -15,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,  10,  15,  15,  10,   0, -10,
+    -10,   0,  10,  15,  15,  10,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -15,  -5,   0,   0,   0,   0,  -5, -15,
+    -20, -15, -10, -10, -10, -10, -15, -20,
+];
+
+#[rustfmt::skip]
+const KING_MG: [i32; 64] = [
+     20,  30,  10,   0,   0,  10,  30,  20,
+     20,  20,   0,   0,   0,   0,  20,  20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+];
+
+#[rustfmt::skip]
+const KING_EG: [i32; 64] = [
+    -50, -30, -30, -30, -30, -30, -30, -50,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -50, -40, -30, -20, -20, -30, -40, -50,
+];
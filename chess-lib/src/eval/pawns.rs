@@ -0,0 +1,130 @@
+use super::*;
+
+// Pawn masks are indexed by `Color`, the score is from white's point of view
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PawnStructure {
+    pub passed: [u64; 2],
+    pub isolated: [u64; 2],
+    pub doubled: [u64; 2],
+    pub backward: [u64; 2],
+    pub score: Tapered,
+}
+
+impl PawnStructure {
+    pub fn new(board: &Board) -> Self {
+        Self::with_params(board, &DEFAULT_PARAMS)
+    }
+
+    pub fn with_params(board: &Board, params: &EvalParams) -> Self {
+        let mut s = Self::default();
+        s.analyze(board, Color::White);
+        s.analyze(board, Color::Black);
+
+        let side_score = |c: Color| {
+            let c = c as usize;
+            let mut score = Tapered::default();
+            score += params.doubled_pawn * s.doubled[c].count_ones() as i32;
+            score += params.isolated_pawn * s.isolated[c].count_ones() as i32;
+            score += params.backward_pawn * s.backward[c].count_ones() as i32;
+            score
+        };
+        s.score = side_score(Color::White) - side_score(Color::Black);
+        for color in [Color::White, Color::Black] {
+            let mut b = s.passed[color as usize];
+            while b != 0 {
+                let square = b.trailing_zeros() as usize;
+                b &= b - 1;
+                let bonus = params.passed_pawn[relative_square(color, square) / 8];
+                if color == Color::White {
+                    s.score += bonus;
+                } else {
+                    s.score -= bonus;
+                }
+            }
+        }
+        s
+    }
+
+    fn analyze(&mut self, board: &Board, color: Color) {
+        let pawns = board.board(Piece::new(PieceKind::Pawn, color)).0;
+        let their_pawns = board.board(Piece::new(PieceKind::Pawn, color.opposite())).0;
+        let their_attacks = pawn_attacks(their_pawns, color.opposite());
+        let c = color as usize;
+
+        let mut b = pawns;
+        while b != 0 {
+            let square = b.trailing_zeros() as usize;
+            b &= b - 1;
+            let bit = 1 << square;
+
+            if pawns & front_span(color, square) != 0 {
+                self.doubled[c] |= bit;
+            }
+            if their_pawns & passed_pawn_mask(color, square) == 0 {
+                self.passed[c] |= bit;
+            }
+
+            let neighbours = adjacent_files(square % 8);
+            if pawns & neighbours == 0 {
+                self.isolated[c] |= bit;
+                continue;
+            }
+
+            // No pawn on a neighbouring file can come up to support it and
+            // the square in front is controlled by an enemy pawn
+            let supporters = neighbours & !passed_pawn_mask(color, square);
+            let stop = match color {
+                Color::White => bit << 8,
+                Color::Black => bit >> 8,
+            };
+            if pawns & supporters == 0 && their_attacks & stop != 0 {
+                self.backward[c] |= bit;
+            }
+        }
+    }
+}
+
+pub struct PawnTable {
+    table: Box<[(u64, PawnStructure)]>,
+
+    pub hits: u64,
+}
+
+impl PawnTable {
+    pub fn new(entries: usize) -> Self {
+        Self {
+            table: vec![(0, PawnStructure::default()); entries].into_boxed_slice(),
+            hits: 0,
+        }
+    }
+
+    // Positions without pawns hash to 0, which also matches the empty entries
+    pub fn probe(&mut self, board: &Board) -> &PawnStructure {
+        let idx = board.pawn_key as usize % self.table.len();
+        if self.table[idx].0 == board.pawn_key {
+            self.hits += 1;
+        } else {
+            self.table[idx] = (board.pawn_key, PawnStructure::new(board));
+        }
+        &self.table[idx].1
+    }
+}
+
+// Squares in front of a pawn on its own file
+pub fn front_span(color: Color, square: usize) -> u64 {
+    FILE_A << (square % 8) & ahead(color, square)
+}
+
+// Squares in front of a pawn on its own and the adjacent files
+pub fn passed_pawn_mask(color: Color, square: usize) -> u64 {
+    let files = adjacent_files(square % 8) | (FILE_A << (square % 8));
+    files & ahead(color, square)
+}
+
+fn ahead(color: Color, square: usize) -> u64 {
+    let rank = square / 8;
+    match color {
+        Color::White => u64::MAX.checked_shl(8 * (rank as u32 + 1)).unwrap_or(0),
+        Color::Black => (1 << (8 * rank)) - 1,
+    }
+}
@@ -1,7 +1,8 @@
 use crate::{
+    bitboard::LIGHT_SQUARES,
     board::Board,
     movegen::{legal_moves, square_attacked, Move},
-    piece::PieceKind,
+    piece::{Color, Piece, PieceKind},
     wasm::GameState,
 };
 
@@ -30,6 +31,30 @@ impl Game {
         game
     }
 
+    // Replays `moves`, checking each one is legal and that the game hadn't
+    // ended before it. The error is the index of the first bad move.
+    pub fn from_legal_moves(moves: &[Move]) -> Result<Game, usize> {
+        let mut game = Game::default();
+        for (i, mv) in moves.iter().enumerate() {
+            if game.game_state != GameState::InProgress || !legal_moves(&game.board).contains(mv) {
+                return Err(i);
+            }
+            game.make_move(*mv);
+        }
+        Ok(game)
+    }
+
+    pub fn fen(&self) -> String {
+        let mut fen = self.board.to_fen();
+        fen.truncate(fen.len() - " 0 1".len());
+        format!(
+            "{} {} {}",
+            fen,
+            self.fifty_move_rule,
+            self.moves.len() / 2 + 1
+        )
+    }
+
     pub fn moves(&self) -> Vec<Move> {
         self.moves.clone()
     }
@@ -80,15 +105,9 @@ impl Game {
             self.game_state = GameState::DrawByFiftyMoveRule;
         }
 
-        // Unsufficient material
-        if self.board.w_pawn == 0
-            && self.board.b_pawn == 0
-            && self.board.w_rook == 0
-            && self.board.b_rook == 0
-            && self.board.w_queen == 0
-            && self.board.b_queen == 0
-            && self.board.w_knight.0.count_ones() + self.board.w_bishop.0.count_ones() <= 1
-            && self.board.b_knight.0.count_ones() + self.board.b_bishop.0.count_ones() <= 1
+        // Neither side can checkmate by any sequence of legal moves
+        if insufficient_material(&self.board, Color::White)
+            && insufficient_material(&self.board, Color::Black)
         {
             self.game_state = GameState::DrawByInsufficientMaterial;
         }
@@ -105,3 +124,102 @@ impl Game {
         }
     }
 }
+
+// Whether `color` can never checkmate, whatever the opponent plays. Used on
+// its own when `color`'s opponent runs out of time, a game where both sides
+// lack mating material is a dead position.
+pub fn insufficient_material(board: &Board, color: Color) -> bool {
+    let count = |kind, color| board.board(Piece::new(kind, color)).0.count_ones();
+    let pawns_and_majors = |color| {
+        count(PieceKind::Pawn, color)
+            + count(PieceKind::Rook, color)
+            + count(PieceKind::Queen, color)
+    };
+    if pawns_and_majors(color) != 0 {
+        return false;
+    }
+
+    let opponent = color.opposite();
+    let knights = count(PieceKind::Knight, color);
+    let bishops = board.board(Piece::new(PieceKind::Bishop, color)).0;
+
+    match (knights, bishops) {
+        (0, 0) => true,
+        // A knight needs the opponent's own pieces to block its king in
+        (1, 0) => {
+            let opp_occ = match opponent {
+                Color::White => board.w_occ,
+                Color::Black => board.b_occ,
+            };
+            opp_occ.0.count_ones() == 1
+        }
+        // Bishops on one color never attack the other, the opponent has to
+        // be able to block a square of that color
+        (0, bishops) => {
+            let light = bishops & LIGHT_SQUARES != 0;
+            let dark = bishops & !LIGHT_SQUARES != 0;
+            let opp_bishops = board.board(Piece::new(PieceKind::Bishop, opponent)).0;
+            let opp_other_color = if light {
+                opp_bishops & !LIGHT_SQUARES
+            } else {
+                opp_bishops & LIGHT_SQUARES
+            };
+            !(light && dark)
+                && pawns_and_majors(opponent) == 0
+                && count(PieceKind::Knight, opponent) == 0
+                && opp_other_color == 0
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Neither side can checkmate
+    fn dead(fen: &str) -> bool {
+        let board = Board::from_fen(fen);
+        insufficient_material(&board, Color::White) && insufficient_material(&board, Color::Black)
+    }
+
+    #[test]
+    fn lone_minor_pieces_are_dead() {
+        assert!(dead("8/8/4k3/8/8/4K3/8/8 w - - 0 1"));
+        assert!(dead("8/8/4k3/8/8/4K3/8/5N2 w - - 0 1"));
+        assert!(dead("8/8/4k3/8/8/4K3/8/2B5 w - - 0 1"));
+    }
+
+    #[test]
+    fn bishops_on_one_color() {
+        // c1 and f8 are both dark
+        assert!(dead("5b2/8/4k3/8/8/4K3/8/2B5 w - - 0 1"));
+        // c8 is light, either side can mate if the other helps
+        let board = Board::from_fen("2b5/8/4k3/8/8/4K3/8/2B5 w - - 0 1");
+        assert!(!insufficient_material(&board, Color::White));
+        assert!(!insufficient_material(&board, Color::Black));
+    }
+
+    #[test]
+    fn mates_are_possible() {
+        // Two knights can't force mate, but it can happen
+        let board = Board::from_fen("8/8/4k3/8/8/4K3/8/1N4N1 w - - 0 1");
+        assert!(!insufficient_material(&board, Color::White));
+        assert!(insufficient_material(&board, Color::Black));
+
+        let board = Board::from_fen("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1");
+        assert!(!insufficient_material(&board, Color::White));
+        assert!(insufficient_material(&board, Color::Black));
+
+        // A knight mates a king boxed in by its own pawn
+        let board = Board::from_fen("8/8/4k3/4p3/8/4K3/8/5N2 w - - 0 1");
+        assert!(!insufficient_material(&board, Color::White));
+    }
+
+    #[test]
+    fn dead_positions_end_the_game() {
+        let mut game = Game::from_fen("5b2/8/4k3/8/8/4K3/8/2B5 w - - 0 1");
+        game.update_state();
+        assert_eq!(game.game_state, GameState::DrawByInsufficientMaterial);
+    }
+}
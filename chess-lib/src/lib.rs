@@ -1,10 +1,22 @@
+pub mod analysis;
 pub mod bitboard;
 pub mod bitboardindex;
 pub mod board;
+pub mod book;
+pub mod eco;
+pub mod eval;
 pub mod game;
 pub mod movegen;
+#[cfg(feature = "nnue")]
+pub mod nnue;
 pub mod piece;
+pub mod review;
+pub mod score;
+pub mod see;
 pub mod square;
+pub mod strength;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod syzygy;
 pub mod transposition;
 pub mod wasm;
 pub mod zobrist;
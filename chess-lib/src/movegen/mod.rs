@@ -11,6 +11,7 @@ use std::fmt::Display;
 
 pub mod magic;
 pub mod precalc;
+pub mod san;
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Move {
@@ -53,6 +54,34 @@ impl Move {
     pub fn to_str(&self) -> String {
         self.to_string()
     }
+
+    // | special: 7 | capture: 1 | piece: 4 | to: 6 | from: 6 |
+    pub fn to_bits(&self) -> u32 {
+        let special = match self.special {
+            Some(s) => 0b1000000 | s.to_bits(),
+            None => 0,
+        };
+        (special << 17)
+            | ((self.capture as u32) << 16)
+            | (self.piece.to_bits() << 12)
+            | ((self.to as u32) << 6)
+            | self.from as u32
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        let special = bits >> 17;
+        Self {
+            from: Square::from((bits & 0b111111) as u64),
+            to: Square::from(((bits >> 6) & 0b111111) as u64),
+            piece: Piece::from_bits((bits >> 12) & 0b1111),
+            capture: (bits >> 16) & 1 == 1,
+            special: if special & 0b1000000 != 0 {
+                Some(SpecialMove::from_bits(special))
+            } else {
+                None
+            },
+        }
+    }
 }
 
 impl Display for Move {
@@ -700,6 +729,19 @@ pub fn square_attacked(board: &Board, square: impl BitBoardIdx, side: Color) ->
     false
 }
 
+pub fn attackers_to(board: &Board, square: impl BitBoardIdx, occ: BitBoard) -> BitBoard {
+    let s = square.idx_usize();
+    let bishops = board.w_bishop | board.b_bishop | board.w_queen | board.b_queen;
+    let rooks = board.w_rook | board.b_rook | board.w_queen | board.b_queen;
+
+    (PRECALC.pawns[s][1] & board.w_pawn)
+        | (PRECALC.pawns[s][0] & board.b_pawn)
+        | (PRECALC.knight[s] & (board.w_knight | board.b_knight))
+        | (PRECALC.king[s] & (board.w_king | board.b_king))
+        | (PRECALC.bishop_attack(square, occ) & bishops)
+        | (PRECALC.rook_attack(square, occ) & rooks)
+}
+
 pub fn attacked_suares(board: &Board, side: Color) -> BitBoard {
     let mut bb = BitBoard(0);
     for square in 0..64 {
@@ -86,7 +86,12 @@ pub fn calc_bishop_magic() -> Box<[[BitBoard; 512]; 64]> {
 }
 
 pub fn calc_rook_magic() -> Box<[[BitBoard; 4096]; 64]> {
-    let mut res = Box::new([[BitBoard(0); 4096]; 64]);
+    // 2MB, built on the heap so it doesn't overflow the stack of small
+    // threads in debug builds
+    let mut res: Box<[[BitBoard; 4096]; 64]> = vec![[BitBoard(0); 4096]; 64]
+        .into_boxed_slice()
+        .try_into()
+        .unwrap();
     for square in 0..64 {
         let attack_mask = rook_attack(square);
         let attack_bits = attack_mask.0.count_ones();
@@ -0,0 +1,76 @@
+use super::{legal_moves, Move, SpecialMove};
+use crate::{board::Board, piece::PieceKind};
+
+// Standard algebraic notation, e.g. "Nbd2", "exd5", "O-O", "e8=Q+"
+pub fn to_san(board: &Board, m: &Move) -> String {
+    let mut san = if matches!(m.special, Some(SpecialMove::Castle)) {
+        if m.to.file() == 6 {
+            "O-O".to_string()
+        } else {
+            "O-O-O".to_string()
+        }
+    } else {
+        let mut san = String::new();
+        if m.piece.kind == PieceKind::Pawn {
+            if m.capture {
+                san.push((b'a' + m.from.file() as u8) as char);
+            }
+        } else {
+            san += &m.piece.kind.letter().to_uppercase();
+            san += &disambiguation(board, m);
+        }
+        if m.capture {
+            san.push('x');
+        }
+        san += &m.to.to_string();
+        if let Some(SpecialMove::Promotion(p)) = m.special {
+            san.push('=');
+            san += &p.letter().to_uppercase();
+        }
+        san
+    };
+
+    let mut after = board.clone();
+    after.make_move(m);
+    if after.in_check {
+        san.push(if legal_moves(&after).is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    san
+}
+
+fn disambiguation(board: &Board, m: &Move) -> String {
+    let others = legal_moves(board)
+        .iter()
+        .filter(|o| o.piece == m.piece && o.to == m.to && o.from != m.from)
+        .map(|o| o.from)
+        .collect::<Vec<_>>();
+
+    if others.is_empty() {
+        String::new()
+    } else if others.iter().all(|s| s.file() != m.from.file()) {
+        ((b'a' + m.from.file() as u8) as char).to_string()
+    } else if others.iter().all(|s| s.rank() != m.from.rank()) {
+        (m.from.rank() + 1).to_string()
+    } else {
+        m.from.to_string()
+    }
+}
+
+// Accepts the usual variations: missing or extra check marks, annotations
+// like "!?", "0-0" for castling and promotions without "="
+pub fn from_san(board: &Board, san: &str) -> Option<Move> {
+    let normalize = |s: &str| {
+        s.trim_end_matches(['+', '#', '!', '?'])
+            .replace('0', "O")
+            .replace('=', "")
+    };
+    let san = normalize(san);
+    legal_moves(board)
+        .iter()
+        .find(|m| normalize(&to_san(board, m)) == san)
+        .copied()
+}
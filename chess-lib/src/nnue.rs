@@ -0,0 +1,283 @@
+use crate::{
+    board::Board,
+    movegen::Move,
+    piece::{Color, Piece, PIECE_KINDS},
+};
+
+// A small efficiently updatable network: 768 inputs (color, piece kind and
+// square, seen from each side's perspective), one hidden layer with clipped
+// ReLU activation and a single output. Inference only uses integers so it
+// runs the same natively and in WASM.
+//
+// File format, all values little endian:
+//   magic            b"CLNN"
+//   version          u32 (1)
+//   inputs           u32 (768)
+//   hidden           u32
+//   feature weights  i16 * inputs * hidden, grouped by input
+//   feature biases   i16 * hidden
+//   output weights   i16 * 2 * hidden, side to move half first
+//   output bias      i32
+
+pub const INPUTS: usize = 768;
+
+// Activations are clipped to QA, output weights are scaled by QB
+const QA: i32 = 255;
+const QB: i32 = 64;
+const SCALE: i32 = 400;
+
+const MAGIC: &[u8; 4] = b"CLNN";
+const VERSION: u32 = 1;
+
+static REFERENCE: &[u8] = include_bytes!("../nets/reference.nnue");
+
+#[derive(Debug, Clone)]
+pub struct Network {
+    hidden: usize,
+    feature_weights: Box<[i16]>,
+    feature_biases: Box<[i16]>,
+    output_weights: Box<[i16]>,
+    output_bias: i32,
+}
+
+impl Network {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
+        let mut r = Reader(bytes);
+        if r.take(4)? != MAGIC {
+            return Err("Not a network file");
+        }
+        if r.u32()? != VERSION {
+            return Err("Unsupported network version");
+        }
+        if r.u32()? as usize != INPUTS {
+            return Err("Unsupported network inputs");
+        }
+        let hidden = r.u32()? as usize;
+
+        let net = Self {
+            hidden,
+            feature_weights: r.i16s(INPUTS * hidden)?,
+            feature_biases: r.i16s(hidden)?,
+            output_weights: r.i16s(2 * hidden)?,
+            output_bias: r.i32()?,
+        };
+        if !r.0.is_empty() {
+            return Err("Trailing data after network");
+        }
+        Ok(net)
+    }
+
+    // The tiny network bundled with the library; it only counts material
+    pub fn reference() -> Self {
+        Self::from_bytes(REFERENCE).expect("Bundled network is invalid")
+    }
+
+    pub fn hidden(&self) -> usize {
+        self.hidden
+    }
+
+    // Side to move relative score in centipawns
+    pub fn evaluate(&self, acc: &Accumulator, side_to_move: Color) -> i32 {
+        let us = &acc.values[side_to_move as usize];
+        let them = &acc.values[side_to_move.opposite() as usize];
+        let (w_us, w_them) = self.output_weights.split_at(self.hidden);
+
+        let mut sum = self.output_bias;
+        for i in 0..self.hidden {
+            sum += (us[i] as i32).clamp(0, QA) * w_us[i] as i32;
+            sum += (them[i] as i32).clamp(0, QA) * w_them[i] as i32;
+        }
+        sum * SCALE / (QA * QB)
+    }
+
+    pub fn evaluate_board(&self, board: &Board) -> i32 {
+        let mut acc = Accumulator::new(self);
+        acc.refresh(self, board);
+        self.evaluate(&acc, board.side_to_move)
+    }
+
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+}
+
+fn feature(perspective: Color, piece: Piece, square: usize) -> usize {
+    let (side, square) = match perspective {
+        Color::White => (piece.color as usize, square),
+        Color::Black => (piece.color.opposite() as usize, square ^ 56),
+    };
+    side * 384 + piece.kind as usize * 64 + square
+}
+
+// Hidden layer inputs for both perspectives, indexed by `Color`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Accumulator {
+    values: [Vec<i16>; 2],
+}
+
+impl Accumulator {
+    pub fn new(net: &Network) -> Self {
+        Self {
+            values: [net.feature_biases.to_vec(), net.feature_biases.to_vec()],
+        }
+    }
+
+    pub fn refresh(&mut self, net: &Network, board: &Board) {
+        for v in self.values.iter_mut() {
+            v.copy_from_slice(&net.feature_biases);
+        }
+        for (piece, b) in piece_boards(board) {
+            let mut b = b;
+            while b != 0 {
+                self.add(net, piece, b.trailing_zeros() as usize);
+                b &= b - 1;
+            }
+        }
+    }
+
+    pub fn copy_from(&mut self, other: &Accumulator) {
+        for (v, o) in self.values.iter_mut().zip(other.values.iter()) {
+            v.copy_from_slice(o);
+        }
+    }
+
+    fn add(&mut self, net: &Network, piece: Piece, square: usize) {
+        for perspective in [Color::White, Color::Black] {
+            let w = net.weights(feature(perspective, piece, square));
+            for (v, w) in self.values[perspective as usize].iter_mut().zip(w) {
+                *v = v.wrapping_add(*w);
+            }
+        }
+    }
+
+    fn remove(&mut self, net: &Network, piece: Piece, square: usize) {
+        for perspective in [Color::White, Color::Black] {
+            let w = net.weights(feature(perspective, piece, square));
+            for (v, w) in self.values[perspective as usize].iter_mut().zip(w) {
+                *v = v.wrapping_sub(*w);
+            }
+        }
+    }
+
+    // Applies the pieces that moved between two positions
+    pub fn update(&mut self, net: &Network, before: &Board, after: &Board) {
+        for ((piece, old), (_, new)) in piece_boards(before).into_iter().zip(piece_boards(after)) {
+            let mut removed = old & !new;
+            while removed != 0 {
+                self.remove(net, piece, removed.trailing_zeros() as usize);
+                removed &= removed - 1;
+            }
+            let mut added = new & !old;
+            while added != 0 {
+                self.add(net, piece, added.trailing_zeros() as usize);
+                added &= added - 1;
+            }
+        }
+    }
+}
+
+impl Board {
+    // `make_move` that also brings `acc` from the position before the move,
+    // `parent`, to the position after it
+    pub fn make_move_nnue(
+        &mut self,
+        m: &Move,
+        net: &Network,
+        parent: &Accumulator,
+        acc: &mut Accumulator,
+    ) -> bool {
+        let before = self.clone();
+        if !self.make_move(m) {
+            return false;
+        }
+        acc.copy_from(parent);
+        acc.update(net, &before, self);
+        true
+    }
+}
+
+fn piece_boards(board: &Board) -> [(Piece, u64); 12] {
+    let mut res = [(Piece::new(PIECE_KINDS[0], Color::White), 0); 12];
+    for (i, color) in [Color::White, Color::Black].into_iter().enumerate() {
+        for (j, kind) in PIECE_KINDS.into_iter().enumerate() {
+            let piece = Piece::new(kind, color);
+            res[i * 6 + j] = (piece, board.board(piece).0);
+        }
+    }
+    res
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], &'static str> {
+        if self.0.len() < n {
+            return Err("Unexpected end of network file");
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, &'static str> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, &'static str> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i16s(&mut self, n: usize) -> Result<Box<[i16]>, &'static str> {
+        let bytes = self.take(n * 2)?;
+        Ok(bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::DEFAULT_FEN, movegen::san::from_san};
+
+    // Plays `sans` from `fen` with incremental updates, checking the
+    // accumulator against a full refresh after every move
+    fn check_updates(fen: &str, sans: &[&str]) {
+        let net = Network::reference();
+        let mut board = Board::from_fen(fen);
+        let mut acc = Accumulator::new(&net);
+        acc.refresh(&net, &board);
+
+        for san in sans {
+            let m = from_san(&board, san).unwrap();
+            let parent = acc.clone();
+            assert!(board.make_move_nnue(&m, &net, &parent, &mut acc), "{}", san);
+
+            let mut refreshed = Accumulator::new(&net);
+            refreshed.refresh(&net, &board);
+            assert_eq!(acc, refreshed, "after {}", san);
+            assert_eq!(
+                net.evaluate(&acc, board.side_to_move),
+                net.evaluate_board(&board)
+            );
+        }
+    }
+
+    #[test]
+    fn incremental_updates_match_a_refresh() {
+        // Captures, en passant, both castlings and plain king moves
+        check_updates(
+            DEFAULT_FEN,
+            &[
+                "e4", "Nf6", "e5", "d5", "exd6", "Qxd6", "Nf3", "Bg4", "Be2", "Nc6", "O-O",
+                "O-O-O", "Kh1", "Kb8", "d4", "Qxd4", "Nxd4", "Rxd4",
+            ],
+        );
+        // Promotions, with and without a capture
+        check_updates(
+            "1n6/P6k/8/8/8/8/1p4K1/R7 w - - 0 1",
+            &["a8=Q", "bxa1=N", "Qxb8", "Kg6"],
+        );
+    }
+}
@@ -12,6 +12,15 @@ pub enum PieceKind {
     King,
 }
 
+pub const PIECE_KINDS: [PieceKind; 6] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
+
 impl PieceKind {
     pub fn letter(&self) -> &'static str {
         match self {
@@ -0,0 +1,262 @@
+use crate::{
+    board::Board,
+    bot::Bot,
+    game::Game,
+    movegen::{legal_moves, san::to_san, Move},
+    piece::Color,
+    score::{is_mate, mated_in, Score, MATE_BOUND},
+};
+use serde::{Deserialize, Serialize};
+
+// Post-game review: every position of a game is searched once, each move is
+// judged by how much of the mover's winning chances it gave away compared to
+// the best move. Classification and accuracy follow the win percentage
+// model used by lichess, so a pawn lost in a won position weighs less than
+// one lost in a balanced one.
+
+// Evaluations are capped before comparing them, mates and known wins count
+// as this many centipawns
+const CP_CAP: i32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MoveClass {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+    // A forced mate was available and is gone after the move
+    MissedMate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveReview {
+    pub mv: Move,
+    pub san: String,
+    pub best: Option<Move>,
+    pub best_san: Option<String>,
+    // Evaluation after the move, from white's view
+    pub eval: Score,
+    pub cp_loss: i32,
+    pub class: MoveClass,
+    // 0 to 100
+    pub accuracy: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PlayerReview {
+    pub accuracy: f64,
+    // Average centipawn loss
+    pub acpl: i32,
+    pub inaccuracies: u32,
+    pub mistakes: u32,
+    pub blunders: u32,
+    pub missed_mates: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameReview {
+    pub moves: Vec<MoveReview>,
+    pub white: PlayerReview,
+    pub black: PlayerReview,
+    // The shallowest depth a position was fully searched to
+    pub depth: i32,
+}
+
+impl MoveClass {
+    fn classify(before: i32, after: i32, best: bool) -> Self {
+        if is_mate(before) && before > 0 && after < MATE_BOUND && !best {
+            return MoveClass::MissedMate;
+        }
+        if best {
+            return MoveClass::Best;
+        }
+
+        match win_percent(before) - win_percent(after) {
+            d if d >= 30. => MoveClass::Blunder,
+            d if d >= 20. => MoveClass::Mistake,
+            d if d >= 10. => MoveClass::Inaccuracy,
+            _ => MoveClass::Good,
+        }
+    }
+}
+
+// Chances of winning, 0 to 100, of the side with evaluation `cp`
+fn win_percent(cp: i32) -> f64 {
+    let cp = cp.clamp(-CP_CAP, CP_CAP) as f64;
+    50. + 50. * (2. / (1. + (-0.00368208 * cp).exp()) - 1.)
+}
+
+fn move_accuracy(before: i32, after: i32) -> f64 {
+    let drop = (win_percent(before) - win_percent(after)).max(0.);
+    (103.1668 * (-0.04354 * drop).exp() - 3.1669).clamp(0., 100.)
+}
+
+// Search score and best move of a position from the side to move's view.
+// Finished games are scored without searching.
+fn evaluate(bot: &mut Bot, board: &Board) -> (i32, Option<Move>) {
+    if legal_moves(board).is_empty() {
+        return (if board.in_check { mated_in(0) } else { 0 }, None);
+    }
+    match bot.best_move(board.clone(), |_| {}) {
+        Some(line) => (line.score, line.pv.first().copied()),
+        None => (0, None),
+    }
+}
+
+// Reviews `game` with the bot's limits applied to every position, each
+// position is a search of its own with the full node and time budget.
+// `on_progress` gets the number of positions searched and the total.
+pub fn review_game(
+    game: &Game,
+    bot: &mut Bot,
+    mut on_progress: impl FnMut(usize, usize),
+) -> GameReview {
+    let boards = &game.board_history[..game.moves.len() + 1];
+    let mut depth = i32::MAX;
+    let mut evals = Vec::with_capacity(boards.len());
+    for (i, board) in boards.iter().enumerate() {
+        evals.push(evaluate(bot, board));
+        if bot.reached_depth > 0 {
+            depth = depth.min(bot.reached_depth);
+        }
+        on_progress(i + 1, boards.len());
+    }
+
+    let mut moves = Vec::with_capacity(game.moves.len());
+    // Totals of centipawn loss, accuracy and moves of each player
+    let mut players = [(PlayerReview::default(), 0, 0., 0u32); 2];
+    for (i, mv) in game.moves.iter().enumerate() {
+        let board = &boards[i];
+        let (before, best) = evals[i];
+        // The next position is scored for the opponent
+        let after = -evals[i + 1].0;
+        let is_best = best == Some(*mv);
+
+        let cp_loss = if is_best {
+            0
+        } else {
+            (before.clamp(-CP_CAP, CP_CAP) - after.clamp(-CP_CAP, CP_CAP)).max(0)
+        };
+        let class = MoveClass::classify(before, after, is_best);
+        let accuracy = if is_best {
+            100.
+        } else {
+            move_accuracy(before, after)
+        };
+
+        let white_view = match board.side_to_move {
+            Color::White => after,
+            Color::Black => -after,
+        };
+
+        let (player, loss, total_accuracy, count) = &mut players[board.side_to_move as usize];
+        *loss += cp_loss;
+        *total_accuracy += accuracy;
+        *count += 1;
+        match class {
+            MoveClass::Inaccuracy => player.inaccuracies += 1,
+            MoveClass::Mistake => player.mistakes += 1,
+            MoveClass::Blunder => player.blunders += 1,
+            MoveClass::MissedMate => player.missed_mates += 1,
+            _ => {}
+        }
+
+        moves.push(MoveReview {
+            mv: *mv,
+            san: to_san(board, mv),
+            best,
+            best_san: best.map(|b| to_san(board, &b)),
+            eval: Score::from_search(white_view),
+            cp_loss,
+            class,
+            accuracy,
+        });
+    }
+
+    let [white, black] = players.map(|(mut player, loss, total_accuracy, count)| {
+        if count > 0 {
+            player.acpl = loss / count as i32;
+            player.accuracy = total_accuracy / count as f64;
+        } else {
+            player.accuracy = 100.;
+        }
+        player
+    });
+
+    GameReview {
+        moves,
+        white,
+        black,
+        depth: if depth == i32::MAX { 0 } else { depth },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bot::SearchLimits, movegen::san::from_san};
+
+    fn game(sans: &[&str]) -> Game {
+        let mut game = Game::default();
+        for san in sans {
+            let board = &game.board_history[game.moves.len()];
+            let m = from_san(board, san).unwrap();
+            game = Game::from_moves(game.moves.iter().copied().chain([m]).collect());
+        }
+        game
+    }
+
+    fn reviewed(sans: &[&str]) -> GameReview {
+        let limits = SearchLimits {
+            nodes: Some(20_000),
+            ..Default::default()
+        };
+        let mut bot = Bot::with_limits(1 << 16, limits);
+        review_game(&game(sans), &mut bot, |_, _| {})
+    }
+
+    #[test]
+    fn blunders_are_found() {
+        // The queen is left to the knight
+        let review = reviewed(&["e4", "e5", "Nf3", "Qh4", "Nxh4"]);
+        assert_eq!(review.moves[3].class, MoveClass::Blunder);
+        assert_eq!(review.moves[4].class, MoveClass::Best);
+        assert_eq!(review.black.blunders, 1);
+        assert!(review.depth > 0);
+
+        // Nf6 allows Qxf7#
+        let review = reviewed(&["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7"]);
+        assert_eq!(review.moves[5].class, MoveClass::Blunder);
+        assert_eq!(review.moves[6].class, MoveClass::Best);
+        assert!(review.black.accuracy < review.white.accuracy);
+    }
+
+    #[test]
+    fn accuracy_is_a_percentage() {
+        let review = reviewed(&[
+            "e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "Ng5", "d5", "exd5", "Nxd5", "Nxf7",
+        ]);
+        assert_eq!(review.moves.len(), 11);
+        for m in &review.moves {
+            assert!(
+                (0. ..=100.).contains(&m.accuracy),
+                "{}: {}",
+                m.san,
+                m.accuracy
+            );
+        }
+        for player in [review.white, review.black] {
+            assert!((0. ..=100.).contains(&player.accuracy));
+        }
+    }
+
+    #[test]
+    fn mates_keep_the_winner() {
+        let review = reviewed(&["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7"]);
+        assert_eq!(review.moves.last().unwrap().eval, Score::MateGiven);
+
+        let review = reviewed(&["f3", "e5", "g4", "Qh4"]);
+        assert_eq!(review.moves.last().unwrap().eval, Score::Mate(0));
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+// Search scores are plain integers: centipawns, or for forced mates
+// `MATE - ply` where ply is the distance from the root to the mate.
+pub const INFINITY: i32 = 500_000;
+pub const MATE: i32 = 490_000;
+// Anything further from zero than this is a mate score
+pub const MATE_BOUND: i32 = MATE - 1000;
+// Tablebase wins, `TB_WIN - ply`, rank above any evaluation but below mates
+pub const TB_WIN: i32 = 20_000;
+// Endgames the evaluation knows to be won score above this
+pub const KNOWN_WIN: i32 = 10_000;
+
+pub fn mated_in(ply: i32) -> i32 {
+    -MATE + ply
+}
+
+pub fn is_mate(score: i32) -> bool {
+    score.abs() >= MATE_BOUND
+}
+
+// The transposition table stores mate scores relative to the node they were
+// found in, so they stay correct when the position is reached at another ply
+pub fn to_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply
+    } else if score <= -MATE_BOUND {
+        score - ply
+    } else {
+        score
+    }
+}
+
+pub fn from_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply
+    } else if score <= -MATE_BOUND {
+        score + ply
+    } else {
+        score
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Score {
+    Centipawns(i32),
+    // Mate in that many moves, negative when the side to move gets mated.
+    // `Mate(0)` is the side to move checkmated on the board.
+    Mate(i32),
+    // The other side is checkmated on the board, only seen when a mated
+    // position is scored from the winner's view
+    MateGiven,
+}
+
+impl Score {
+    pub fn from_search(score: i32) -> Self {
+        if score == MATE {
+            Score::MateGiven
+        } else if score >= MATE_BOUND {
+            Score::Mate((MATE - score + 1) / 2)
+        } else if score <= -MATE_BOUND {
+            Score::Mate(-(MATE + score + 1) / 2)
+        } else {
+            Score::Centipawns(score)
+        }
+    }
+
+    pub fn to_search(self) -> i32 {
+        match self {
+            Score::Centipawns(cp) => cp,
+            Score::Mate(n) if n > 0 => MATE - (2 * n - 1),
+            Score::Mate(n) => -MATE + 2 * -n,
+            Score::MateGiven => MATE,
+        }
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Centipawns(cp) => write!(f, "{:+.2}", *cp as f64 / 100.),
+            Score::Mate(n) if *n < 0 => write!(f, "-M{}", -n),
+            Score::Mate(n) => write!(f, "M{}", n),
+            Score::MateGiven => write!(f, "#"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_scores_round_trip() {
+        let mut scores = vec![0, 1, -1, 250, -250, KNOWN_WIN, -TB_WIN, MATE, mated_in(0)];
+        // Mates are given on odd plies and received on even ones
+        for ply in 0..20 {
+            scores.push(MATE - (2 * ply + 1));
+            scores.push(mated_in(2 * ply));
+        }
+        for score in scores {
+            assert_eq!(Score::from_search(score).to_search(), score, "{}", score);
+        }
+    }
+
+    #[test]
+    fn mates_on_the_board() {
+        assert_eq!(Score::from_search(mated_in(0)), Score::Mate(0));
+        assert_eq!(Score::from_search(MATE), Score::MateGiven);
+        assert_eq!(Score::from_search(MATE - 1), Score::Mate(1));
+        assert_eq!(Score::from_search(mated_in(2)), Score::Mate(-1));
+    }
+}
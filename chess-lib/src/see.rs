@@ -0,0 +1,134 @@
+use crate::{
+    bitboard::BitBoard,
+    board::Board,
+    movegen::{attackers_to, Move, SpecialMove},
+    piece::{Color, Piece, PieceKind, PIECE_KINDS},
+};
+
+pub fn see_value(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::Pawn => 100,
+        PieceKind::Knight => 300,
+        PieceKind::Bishop => 300,
+        PieceKind::Rook => 500,
+        PieceKind::Queen => 900,
+        PieceKind::King => 10000,
+    }
+}
+
+// Static exchange evaluation: the material balance (from the side making
+// the move) after both sides keep recapturing on the target square with
+// their least valuable attacker. Sliders hidden behind a capturing piece
+// join the exchange as soon as the square in front of them is vacated.
+pub fn see(board: &Board, mv: &Move) -> i32 {
+    let to = mv.to;
+    let mut occ = board.occ;
+    let mut gain = [0; 32];
+
+    gain[0] = match mv.special {
+        Some(SpecialMove::EnPassant) => {
+            let captured = if mv.piece.color == Color::White {
+                to as u64 - 8
+            } else {
+                to as u64 + 8
+            };
+            occ.clear(captured);
+            see_value(PieceKind::Pawn)
+        }
+        _ => board
+            .piece(to as u64)
+            .map(|p| see_value(p.kind))
+            .unwrap_or(0),
+    };
+
+    let mut attacker = mv.piece.kind;
+    if let Some(SpecialMove::Promotion(p)) = mv.special {
+        gain[0] += see_value(p) - see_value(PieceKind::Pawn);
+        attacker = p;
+    }
+    occ.clear(mv.from);
+
+    let mut side = mv.piece.color.opposite();
+    let mut depth = 0;
+    loop {
+        depth += 1;
+        gain[depth] = see_value(attacker) - gain[depth - 1];
+        if (-gain[depth - 1]).max(gain[depth]) < 0 || depth == gain.len() - 1 {
+            break;
+        }
+
+        let attackers = attackers_to(board, to, occ) & occ;
+        let Some((square, kind)) = least_valuable_attacker(board, attackers, side) else {
+            break;
+        };
+
+        // The king may only take last
+        if kind == PieceKind::King
+            && attackers & color_occ(board, side.opposite()) & occ != BitBoard(0)
+        {
+            break;
+        }
+
+        occ.clear(square);
+        attacker = kind;
+        side = side.opposite();
+    }
+
+    while depth > 1 {
+        depth -= 1;
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+    }
+    gain[0]
+}
+
+fn least_valuable_attacker(
+    board: &Board,
+    attackers: BitBoard,
+    side: Color,
+) -> Option<(u64, PieceKind)> {
+    PIECE_KINDS.into_iter().find_map(|kind| {
+        let b = attackers & *board.board(Piece::new(kind, side));
+        if b != 0 {
+            Some((b.0.trailing_zeros() as u64, kind))
+        } else {
+            None
+        }
+    })
+}
+
+fn color_occ(board: &Board, color: Color) -> BitBoard {
+    match color {
+        Color::White => board.w_occ,
+        Color::Black => board.b_occ,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::san::from_san;
+
+    fn see_of(fen: &str, san: &str) -> i32 {
+        let board = Board::from_fen(fen);
+        see(&board, &from_san(&board, san).unwrap())
+    }
+
+    #[test]
+    fn undefended_piece() {
+        assert_eq!(see_of("4k3/8/8/3b4/8/4N3/8/4K3 w - - 0 1", "Nxd5"), 300);
+    }
+
+    #[test]
+    fn defended_pawn_taken_by_the_queen() {
+        assert_eq!(see_of("4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1", "Qxe5"), -800);
+    }
+
+    #[test]
+    fn x_ray_battery() {
+        // The rook on e1 joins once the one in front of it took
+        let fen = "4r1k1/8/8/4n3/8/8/4R3/4R1K1 w - - 0 1";
+        assert_eq!(see_of(fen, "Rxe5"), 300);
+        // Without it the knight costs the rook
+        assert_eq!(see_of("4r1k1/8/8/4n3/8/8/4R3/6K1 w - - 0 1", "Rxe5"), -200);
+    }
+}
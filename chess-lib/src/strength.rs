@@ -0,0 +1,133 @@
+use crate::{
+    board::Board,
+    bot::{Bot, SearchLimits},
+    movegen::{legal_moves, Move},
+    score::MATE_BOUND,
+};
+use rand::Rng;
+
+pub const MIN_ELO: u32 = 400;
+pub const MAX_ELO: u32 = 2400;
+
+// Every level searches on the clock too, so weak levels stay responsive
+const MOVE_TIME: u64 = 5000;
+const MAX_LEVEL_DEPTH: f64 = 11.;
+// Node budget doubles every 1/12 of the range, 500 at the bottom to about
+// two million just below full strength
+const MIN_NODES: f64 = 500.;
+const NODE_DOUBLINGS: f64 = 12.;
+// Standard deviation of the noise added to move scores, in centipawns
+const MAX_NOISE: f64 = 200.;
+const MAX_BLUNDER_CHANCE: f64 = 0.15;
+const LINES: usize = 6;
+// Plies played from the opening book, weak levels leave it early
+const MIN_BOOK_PLIES: f64 = 2.;
+const MAX_BOOK_PLIES: f64 = 16.;
+
+// A playing strength between `MIN_ELO` and `MAX_ELO`. Lower levels search
+// less, misjudge moves by a random amount and now and then play a random
+// move. Everything scales with the square of the distance to full strength,
+// so the difference between low levels stays noticeable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Strength {
+    elo: u32,
+}
+
+impl Strength {
+    pub fn new(elo: u32) -> Self {
+        Self {
+            elo: elo.clamp(MIN_ELO, MAX_ELO),
+        }
+    }
+
+    pub fn elo(&self) -> u32 {
+        self.elo
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.elo == MAX_ELO
+    }
+
+    // 0 at `MIN_ELO`, 1 at `MAX_ELO`
+    fn level(&self) -> f64 {
+        (self.elo - MIN_ELO) as f64 / (MAX_ELO - MIN_ELO) as f64
+    }
+
+    fn weakness(&self) -> f64 {
+        (1. - self.level()).powi(2)
+    }
+
+    pub fn limits(&self) -> SearchLimits {
+        if self.is_full() {
+            return SearchLimits {
+                movetime: Some(MOVE_TIME),
+                ..Default::default()
+            };
+        }
+
+        let depth = 1 + (self.level() * MAX_LEVEL_DEPTH).round() as i32;
+        let nodes = MIN_NODES * 2f64.powf(self.level() * NODE_DOUBLINGS);
+        SearchLimits {
+            depth: Some(depth),
+            nodes: Some(nodes as u64),
+            movetime: Some(MOVE_TIME),
+            ..Default::default()
+        }
+    }
+
+    pub fn eval_noise(&self) -> f64 {
+        MAX_NOISE * self.weakness()
+    }
+
+    pub fn blunder_chance(&self) -> f64 {
+        MAX_BLUNDER_CHANCE * self.weakness()
+    }
+
+    pub fn book_plies(&self) -> usize {
+        (MIN_BOOK_PLIES + self.level() * (MAX_BOOK_PLIES - MIN_BOOK_PLIES)).round() as usize
+    }
+
+    fn lines(&self) -> usize {
+        if self.is_full() {
+            1
+        } else {
+            LINES
+        }
+    }
+}
+
+// Picks a move the way a player of the given strength would. `bot` should
+// have been created with `strength.limits()`.
+pub fn pick_move(
+    bot: &mut Bot,
+    board: Board,
+    strength: Strength,
+    rng: &mut impl Rng,
+) -> Option<Move> {
+    let moves = legal_moves(&board);
+    let lines = bot.analyze(board, strength.lines(), |_| {});
+    let best = lines.first()?;
+
+    // Forced mates are never missed
+    if best.score >= MATE_BOUND || strength.is_full() {
+        return Some(best.pv[0]);
+    }
+
+    if rng.gen_bool(strength.blunder_chance()) {
+        return Some(moves[rng.gen_range(0..moves.len())]);
+    }
+
+    let noise = strength.eval_noise();
+    lines
+        .iter()
+        .map(|l| (l.pv[0], l.score as f64 + gaussian(rng) * noise))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(m, _)| m)
+}
+
+// Standard normal sample, Box-Muller
+fn gaussian(rng: &mut impl Rng) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.);
+    let u2: f64 = rng.gen();
+    (-2. * u1.ln()).sqrt() * (2. * std::f64::consts::PI * u2).cos()
+}
@@ -0,0 +1,435 @@
+use self::table::{Material, Table, TableKind, TableProbe};
+use crate::{
+    board::Board,
+    movegen::{legal_moves, Move},
+    piece::{Color, Piece, PieceKind},
+    score::TB_WIN,
+};
+use std::{collections::HashMap, path::Path};
+
+mod table;
+
+// Syzygy endgame tablebases, probed by native builds only. WDL tables tell
+// whether a position is won, drawn or lost, DTZ tables how many plies it
+// takes to the next capture or pawn move while keeping that result.
+// Tables assume the fifty move counter was just reset and no castling
+// rights remain.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    // Lost, but drawn by the fifty move rule
+    BlessedLoss = -1,
+    Draw = 0,
+    // Won, but drawn by the fifty move rule
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Self {
+        match value {
+            ..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    fn signum(self) -> i32 {
+        (self as i32).signum()
+    }
+
+    // Search score at `ply`, results the fifty move rule turns into draws
+    // are only worth a little more or less than one
+    pub fn score(self, ply: i32) -> i32 {
+        match self {
+            Wdl::Win => TB_WIN - ply,
+            Wdl::Loss => -TB_WIN + ply,
+            _ => self as i32,
+        }
+    }
+}
+
+impl std::ops::Neg for Wdl {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::from_value(-(self as i32))
+    }
+}
+
+struct Entry {
+    wdl: Table,
+    dtz: Table,
+}
+
+pub struct Tablebases {
+    // By file name, e.g. "KRvK"
+    entries: HashMap<String, Entry>,
+    max_pieces: usize,
+}
+
+impl Tablebases {
+    // Finds the WDL tables in `dir`, files are only mapped once they are
+    // first probed. DTZ tables are optional.
+    pub fn open(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let dir = dir.as_ref();
+        let mut entries = HashMap::new();
+        let mut max_pieces = 0;
+
+        for file in std::fs::read_dir(dir)? {
+            let path = file?.path();
+            if path.extension().map_or(true, |e| e != "rtbw") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let Some(material) = Material::from_name(name) else {
+                continue;
+            };
+
+            max_pieces = max_pieces.max(material.pieces);
+            let dtz = Table::new(
+                dir.join(format!("{}.rtbz", name)),
+                TableKind::Dtz,
+                material.clone(),
+            );
+            let wdl = Table::new(path.clone(), TableKind::Wdl, material);
+            entries.insert(name.to_string(), Entry { wdl, dtz });
+        }
+
+        Ok(Self {
+            entries,
+            max_pieces,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Positions with at most this many pieces, kings included, can be probed
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    pub fn can_probe(&self, board: &Board) -> bool {
+        board.can_castle == 0 && (board.occ.0.count_ones() as usize) <= self.max_pieces
+    }
+
+    // Result of the position for the side to move
+    pub fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+        if !self.can_probe(board) {
+            return None;
+        }
+        self.search(board, false).map(|(wdl, _)| wdl)
+    }
+
+    // Plies to the next capture or pawn move for the side to move, positive
+    // when winning and negative when losing. Results beyond +-100 are wins
+    // or losses drawn by the fifty move rule. The count may be one ply too
+    // high, never too low.
+    pub fn probe_dtz(&self, board: &Board) -> Option<i32> {
+        if !self.can_probe(board) {
+            return None;
+        }
+        self.dtz(board)
+    }
+
+    // The DTZ of every legal move counted from the root, the result of a
+    // capture or pawn move is the plain WDL value
+    pub fn root_dtz(&self, board: &Board) -> Option<Vec<(Move, i32)>> {
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        legal_moves(board)
+            .iter()
+            .map(|m| {
+                let mut b = board.clone();
+                b.make_move(m);
+
+                let mut dtz = if zeroing(m) {
+                    dtz_before_zeroing(-self.search(&b, false)?.0)
+                } else {
+                    let dtz = -self.dtz(&b)?;
+                    dtz + dtz.signum()
+                };
+                if dtz == 2 && b.in_check && legal_moves(&b).is_empty() {
+                    dtz = 1;
+                }
+                Some((*m, dtz))
+            })
+            .collect()
+    }
+
+    // The root moves that keep the best result: the quickest progress when
+    // winning, the longest resistance when losing. Falls back to WDL values
+    // when the DTZ tables are missing.
+    pub fn best_root_moves(&self, board: &Board) -> Option<Vec<Move>> {
+        let ranked = match self.root_dtz(board) {
+            Some(moves) => moves
+                .into_iter()
+                .map(|(m, dtz)| {
+                    let rank = match dtz.signum() {
+                        1 => 1000 - dtz,
+                        -1 => -1000 - dtz,
+                        _ => 0,
+                    };
+                    (m, rank)
+                })
+                .collect::<Vec<_>>(),
+            None => legal_moves(board)
+                .iter()
+                .map(|m| {
+                    let mut b = board.clone();
+                    b.make_move(m);
+                    Some((*m, -(self.probe_wdl(&b)? as i32)))
+                })
+                .collect::<Option<Vec<_>>>()?,
+        };
+
+        let best = ranked.iter().map(|(_, r)| *r).max()?;
+        Some(
+            ranked
+                .into_iter()
+                .filter(|(_, r)| *r == best)
+                .map(|(m, _)| m)
+                .collect(),
+        )
+    }
+}
+
+impl Tablebases {
+    // Captures may be stored as "don't care" values, and en passant isn't
+    // stored at all, so they are searched before trusting the table. The
+    // flag is set when the best result comes from a capture (or a pawn
+    // move, when `zeroing_moves` is set), the DTZ table has no valid value
+    // then.
+    fn search(&self, board: &Board, zeroing_moves: bool) -> Option<(Wdl, bool)> {
+        let moves = legal_moves(board);
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+
+        for m in moves.iter() {
+            let pawn_move = zeroing_moves && m.piece.kind == PieceKind::Pawn;
+            if !m.capture && !pawn_move {
+                continue;
+            }
+            searched += 1;
+
+            let mut b = board.clone();
+            b.make_move(m);
+            let value = -self.search(&b, false)?.0;
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // When every move was searched the table isn't needed, it could
+        // even be wrong when en passant is possible
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = if all_searched {
+            best
+        } else {
+            match self.probe_table(board, TableKind::Wdl, Wdl::Draw)? {
+                TableProbe::Value(v) => Wdl::from_value(v),
+                TableProbe::OtherSide => return None,
+            }
+        };
+
+        if best >= value {
+            Some((best, best > Wdl::Draw || all_searched))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    fn dtz(&self, board: &Board) -> Option<i32> {
+        let (wdl, zeroing_best) = self.search(board, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing_best {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        match self.probe_table(board, TableKind::Dtz, wdl)? {
+            TableProbe::Value(dtz) => {
+                let fifty = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+                Some((dtz + 100 * fifty as i32) * wdl.signum())
+            }
+            // Only the other side to move is stored, take the best of the
+            // moves instead
+            TableProbe::OtherSide => {
+                let mut best = i32::MAX;
+                for m in legal_moves(board).iter() {
+                    let mut b = board.clone();
+                    b.make_move(m);
+
+                    let mut dtz = if zeroing(m) {
+                        -dtz_before_zeroing(self.search(&b, false)?.0)
+                    } else {
+                        -self.dtz(&b)?
+                    };
+                    if dtz == 1 && b.in_check && legal_moves(&b).is_empty() {
+                        best = 1;
+                    }
+                    if !zeroing(m) {
+                        dtz += dtz.signum();
+                    }
+                    if dtz < best && dtz.signum() == wdl.signum() {
+                        best = dtz;
+                    }
+                }
+                Some(if best == i32::MAX { -1 } else { best })
+            }
+        }
+    }
+
+    fn probe_table(&self, board: &Board, kind: TableKind, wdl: Wdl) -> Option<TableProbe> {
+        if board.occ.0.count_ones() == 2 {
+            return Some(TableProbe::Value(0));
+        }
+
+        let (entry, black_stronger) = self.entry(board)?;
+        let table = match kind {
+            TableKind::Wdl => &entry.wdl,
+            TableKind::Dtz => &entry.dtz,
+        };
+        table.probe(board, black_stronger, wdl)
+    }
+
+    // The table of the position's material and whether black has the
+    // pieces of the first side in its name
+    fn entry(&self, board: &Board) -> Option<(&Entry, bool)> {
+        let white = side_name(board, Color::White);
+        let black = side_name(board, Color::Black);
+        if let Some(entry) = self.entries.get(&format!("{}v{}", white, black)) {
+            return Some((entry, false));
+        }
+        self.entries
+            .get(&format!("{}v{}", black, white))
+            .map(|entry| (entry, true))
+    }
+}
+
+fn side_name(board: &Board, color: Color) -> String {
+    let mut name = String::new();
+    for (kind, letter) in [
+        (PieceKind::King, 'K'),
+        (PieceKind::Queen, 'Q'),
+        (PieceKind::Rook, 'R'),
+        (PieceKind::Bishop, 'B'),
+        (PieceKind::Knight, 'N'),
+        (PieceKind::Pawn, 'P'),
+    ] {
+        let count = board.board(Piece::new(kind, color)).0.count_ones();
+        name.extend(std::iter::repeat(letter).take(count as usize));
+    }
+    name
+}
+
+fn zeroing(m: &Move) -> bool {
+    m.capture || m.piece.kind == PieceKind::Pawn
+}
+
+// DTZ tables have no value for positions whose best move resets the fifty
+// move counter, the DTZ is then one ply more than that of the move
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+        Wdl::Draw => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::san::{from_san, to_san};
+
+    // The 3 and 4 piece tables used here have to be copied into this
+    // directory, see the README there
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy");
+    const TABLES: [&str; 3] = ["KQvK", "KRvK", "KPvK"];
+
+    fn tablebases() -> Tablebases {
+        for name in TABLES {
+            for ext in ["rtbw", "rtbz"] {
+                let path = Path::new(FIXTURES).join(format!("{}.{}", name, ext));
+                assert!(
+                    path.exists(),
+                    "{} is missing, see {}/README.md",
+                    path.display(),
+                    FIXTURES
+                );
+            }
+        }
+        Tablebases::open(FIXTURES).unwrap()
+    }
+
+    #[test]
+    fn probe_wdl() {
+        let tb = tablebases();
+        let positions = [
+            ("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1", Wdl::Win),
+            ("k7/8/1K6/8/8/8/8/6Q1 b - - 0 1", Wdl::Loss),
+            ("8/8/4k3/8/8/8/8/R3K3 w - - 0 1", Wdl::Win),
+            // The rook is lost
+            ("8/8/8/8/8/8/6kR/K7 b - - 0 1", Wdl::Draw),
+            ("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", Wdl::Win),
+            ("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1", Wdl::Loss),
+            // The defending king holds the corner against a rook pawn
+            ("k7/8/8/8/8/8/P7/K7 w - - 0 1", Wdl::Draw),
+        ];
+        for (fen, wdl) in positions {
+            assert_eq!(tb.probe_wdl(&Board::from_fen(fen)), Some(wdl), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn probe_dtz() {
+        let tb = tablebases();
+        // DTZ may be one ply too high, exact values are only promised for
+        // the root moves
+        let positions = [
+            // Mate in one
+            ("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1", 1..=2),
+            // Mated after the only move
+            ("k7/8/1K6/8/8/8/8/6Q1 b - - 0 1", -3..=-2),
+            ("8/8/8/8/8/8/6kR/K7 b - - 0 1", 0..=0),
+            // Any pawn move wins, black is too far away
+            ("k7/8/8/8/8/8/7P/7K w - - 0 1", 1..=2),
+        ];
+        for (fen, range) in positions {
+            let dtz = tb.probe_dtz(&Board::from_fen(fen)).unwrap();
+            assert!(range.contains(&dtz), "{}: {}", fen, dtz);
+        }
+    }
+
+    #[test]
+    fn best_root_moves() {
+        let tb = tablebases();
+        let board = Board::from_fen("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1");
+        let mate = from_san(&board, "Qg8").unwrap();
+        let dtz = tb.root_dtz(&board).unwrap();
+        assert!(dtz.contains(&(mate, 1)));
+
+        let best = tb.best_root_moves(&board).unwrap();
+        let best = best.iter().map(|m| to_san(&board, m)).collect::<Vec<_>>();
+        assert_eq!(best, ["Qg8#"]);
+    }
+}
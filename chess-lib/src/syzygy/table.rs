@@ -0,0 +1,843 @@
+use super::Wdl;
+use crate::{
+    board::Board,
+    piece::{Color, PieceKind},
+};
+use lazy_static::lazy_static;
+use memmap2::Mmap;
+use std::{fs::File, path::PathBuf, sync::OnceLock};
+
+// Reader for the Syzygy .rtbw (WDL) and .rtbz (DTZ) formats. Every table
+// stores one value per position index, compressed with recursive pairing
+// and canonical Huffman codes. Positions are mapped to indices by grouping
+// pieces of the same kind and color and mirroring the board so the leading
+// piece or pawn sits in a canonical part of it.
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+const MAX_PIECES: usize = 7;
+
+// Flags of a single table
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+pub enum TableProbe {
+    Value(i32),
+    // DTZ tables only store one side to move
+    OtherSide,
+}
+
+// Piece codes used by the files: pawn 1 to king 6, black pieces have bit 3 set
+fn piece_code(kind: PieceKind, color: Color) -> u8 {
+    kind as u8 + 1 + 8 * (color == Color::Black) as u8
+}
+
+lazy_static! {
+    static ref INDEX: IndexTables = IndexTables::new();
+}
+
+fn off_diagonal(sq: usize) -> i32 {
+    (sq / 8) as i32 - (sq % 8) as i32
+}
+
+fn king_distance(a: usize, b: usize) -> usize {
+    let file = (a % 8).abs_diff(b % 8);
+    let rank = (a / 8).abs_diff(b / 8);
+    file.max(rank)
+}
+
+struct IndexTables {
+    // Squares a2-h7 to 0..47, the leading pawn is the one with the highest
+    // value: closest to the edge and lowest on its file
+    map_pawns: [usize; 64],
+    // Squares below the a1-h8 diagonal to 0..27
+    map_b1h1h7: [usize; 64],
+    // The a1-d1-d4 triangle to 0..9, diagonal squares last
+    map_a1d1d4: [usize; 64],
+    // The 462 legal placements of two kings with the first one in the
+    // a1-d1-d4 triangle
+    map_kk: [[usize; 64]; 10],
+    binomial: [[u64; 64]; MAX_PIECES],
+    lead_pawn_idx: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+impl IndexTables {
+    fn new() -> Self {
+        let mut t = IndexTables {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; MAX_PIECES],
+            lead_pawn_idx: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+        };
+
+        let mut code = 0;
+        for sq in 0..64 {
+            if off_diagonal(sq) < 0 {
+                t.map_b1h1h7[sq] = code;
+                code += 1;
+            }
+        }
+
+        let mut diagonal = vec![];
+        code = 0;
+        for sq in 0..=27 {
+            if sq % 8 > 3 {
+                continue;
+            }
+            if off_diagonal(sq) < 0 {
+                t.map_a1d1d4[sq] = code;
+                code += 1;
+            } else if off_diagonal(sq) == 0 {
+                diagonal.push(sq);
+            }
+        }
+        for sq in diagonal {
+            t.map_a1d1d4[sq] = code;
+            code += 1;
+        }
+
+        let mut both_on_diagonal = vec![];
+        code = 0;
+        for idx in 0..10 {
+            for s1 in 0..=27 {
+                // Squares outside the triangle are 0 too, b1 is the real 0
+                if s1 % 8 > 3 || t.map_a1d1d4[s1] != idx || (idx == 0 && s1 != 1) {
+                    continue;
+                }
+                for s2 in 0..64 {
+                    if king_distance(s1, s2) <= 1 || (off_diagonal(s1) == 0 && off_diagonal(s2) > 0)
+                    {
+                        continue;
+                    }
+                    if off_diagonal(s1) == 0 && off_diagonal(s2) == 0 {
+                        both_on_diagonal.push((idx, s2));
+                    } else {
+                        t.map_kk[idx][s2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            t.map_kk[idx][s2] = code;
+            code += 1;
+        }
+
+        t.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                t.binomial[k][n] = if k > 0 { t.binomial[k - 1][n - 1] } else { 0 }
+                    + if k < n { t.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        let mut available = 47;
+        for lead_pawns in 1..=5 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let sq = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        t.map_pawns[sq] = available;
+                        t.map_pawns[sq ^ 7] = available - 1;
+                        available = available.saturating_sub(2);
+                    }
+                    t.lead_pawn_idx[lead_pawns][sq] = idx;
+                    idx += t.binomial[lead_pawns - 1][t.map_pawns[sq]];
+                }
+                t.lead_pawns_size[lead_pawns][file] = idx;
+            }
+        }
+
+        t
+    }
+}
+
+// What a table file holds, derived from its name, e.g. "KRPvKR"
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub pieces: usize,
+    pub has_pawns: bool,
+    // Some piece other than a king appears exactly once
+    has_unique_pieces: bool,
+    // Pawns of the leading color and of the other one
+    pawns: [usize; 2],
+    // Both sides have the same pieces
+    pub symmetric: bool,
+}
+
+impl Material {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (white, black) = name.split_once('v')?;
+        if !white.starts_with('K') || !black.starts_with('K') {
+            return None;
+        }
+        let valid = |s: &str| s.chars().all(|c| "KQRBNP".contains(c));
+        if !valid(white) || !valid(black) || white.len() + black.len() > MAX_PIECES {
+            return None;
+        }
+
+        let count = |s: &str, c: char| s.chars().filter(|&p| p == c).count();
+        let has_unique_pieces = [white, black]
+            .iter()
+            .any(|side| "QRBNP".chars().any(|c| count(side, c) == 1));
+
+        let (w, b) = (count(white, 'P'), count(black, 'P'));
+        // The side with fewer pawns leads, it compresses better
+        let white_leads = b == 0 || (w > 0 && b >= w);
+        Some(Self {
+            pieces: white.len() + black.len(),
+            has_pawns: w + b > 0,
+            has_unique_pieces,
+            pawns: if white_leads { [w, b] } else { [b, w] },
+            symmetric: white == black,
+        })
+    }
+}
+
+// Indexing and decompression data of one table, there is one per side to
+// move and, for tables with pawns, per file of the leading pawn
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    min_sym_len: u8,
+    block_size: usize,
+    span: usize,
+    num_blocks: usize,
+    block_lengths_size: usize,
+    sparse_index_size: usize,
+    // Offsets into the file
+    lowest_sym: usize,
+    btree: usize,
+    block_lengths: usize,
+    sparse_index: usize,
+    data: usize,
+    // base64[l - min_sym_len] is the lowest symbol of length l, left aligned
+    base64: Vec<u64>,
+    // Number of values, minus one, each symbol expands to
+    symlen: Vec<u8>,
+    pieces: [u8; MAX_PIECES],
+    group_idx: [u64; MAX_PIECES + 1],
+    group_len: [usize; MAX_PIECES + 1],
+    // Start of the DTZ value maps for wins, losses, cursed wins and blessed
+    // losses
+    map_idx: [usize; 4],
+}
+
+struct TableData {
+    file: Mmap,
+    // [side to move][file of the leading pawn]
+    pairs: [[PairsData; 4]; 2],
+    // DTZ value maps
+    map: usize,
+}
+
+pub struct Table {
+    path: PathBuf,
+    kind: TableKind,
+    material: Material,
+    // Mapped at first access, `None` when the file is missing or invalid
+    data: OnceLock<Option<TableData>>,
+}
+
+impl Table {
+    pub fn new(path: PathBuf, kind: TableKind, material: Material) -> Self {
+        Self {
+            path,
+            kind,
+            material,
+            data: OnceLock::new(),
+        }
+    }
+
+    fn data(&self) -> Option<&TableData> {
+        self.data
+            .get_or_init(|| {
+                let file = File::open(&self.path).ok()?;
+                // Tablebase files are never modified while in use
+                let file = unsafe { Mmap::map(&file) }.ok()?;
+                TableData::parse(file, self.kind, &self.material)
+            })
+            .as_ref()
+    }
+
+    // `board` must have the material of this table, `black_stronger` when
+    // black has the pieces of the first side in the file name. DTZ values
+    // are converted to plies using the position's `wdl`.
+    pub fn probe(&self, board: &Board, black_stronger: bool, wdl: Wdl) -> Option<TableProbe> {
+        let data = self.data()?;
+        let material = &self.material;
+        let index = &*INDEX;
+
+        // Tables are stored with white as the first side, and symmetric
+        // ones only with white to move, anything else is probed with the
+        // colors swapped and the board mirrored vertically
+        let flip = black_stronger || (material.symmetric && board.side_to_move == Color::Black);
+        let (flip_color, flip_square) = if flip { (8, 56) } else { (0, 0) };
+        let stm = flip as usize ^ (board.side_to_move == Color::Black) as usize;
+
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut squares = [0usize; MAX_PIECES];
+        let mut size = 0;
+
+        // Pawns of the leading color come first, the one with the highest
+        // `map_pawns` value leads and picks the table for its file
+        let mut lead_pawns = 0;
+        let mut file = 0;
+        if material.has_pawns {
+            let lead = data.pairs[0][0].pieces[0];
+            for sq in 0..64 {
+                if let Some(p) = board.piece(sq) {
+                    if piece_code(p.kind, p.color) ^ flip_color == lead {
+                        pieces[size] = lead;
+                        squares[size] = sq as usize ^ flip_square;
+                        size += 1;
+                    }
+                }
+            }
+            lead_pawns = size;
+
+            let max = (0..lead_pawns)
+                .max_by_key(|&i| index.map_pawns[squares[i]])
+                .unwrap();
+            squares.swap(0, max);
+            file = squares[0] % 8;
+            if file > 3 {
+                file = 7 - file;
+            }
+        }
+
+        if self.kind == TableKind::Dtz {
+            // Symmetric pawnless tables are the same for both sides
+            let same_sides = material.symmetric && !material.has_pawns;
+            let flags = data.pairs[0][file].flags;
+            if !same_sides && (flags & FLAG_STM) as usize != stm {
+                return Some(TableProbe::OtherSide);
+            }
+        }
+
+        for sq in 0..64 {
+            if let Some(p) = board.piece(sq) {
+                let code = piece_code(p.kind, p.color) ^ flip_color;
+                if material.has_pawns && code == data.pairs[0][0].pieces[0] {
+                    continue;
+                }
+                pieces[size] = code;
+                squares[size] = sq as usize ^ flip_square;
+                size += 1;
+            }
+        }
+
+        // DTZ tables hold a single side to move
+        let side = if self.kind == TableKind::Wdl { stm } else { 0 };
+        let d = &data.pairs[side][file];
+
+        // Put the pieces in the order the table encodes them
+        for i in lead_pawns..size.saturating_sub(1) {
+            for j in i + 1..size {
+                if d.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // Mirror so the leading piece is on files a-d
+        if squares[0] % 8 > 3 {
+            for sq in squares[..size].iter_mut() {
+                *sq ^= 7;
+            }
+        }
+
+        let mut idx;
+        if material.has_pawns {
+            idx = index.lead_pawn_idx[lead_pawns][squares[0]];
+            squares[1..lead_pawns].sort_by_key(|&sq| index.map_pawns[sq]);
+            for (i, &sq) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                idx += index.binomial[i][index.map_pawns[sq]];
+            }
+        } else {
+            // Mirror so the leading piece is on ranks 1-4, then below the
+            // a1-h8 diagonal
+            if squares[0] / 8 > 3 {
+                for sq in squares[..size].iter_mut() {
+                    *sq ^= 56;
+                }
+            }
+            for i in 0..d.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for sq in squares[i..size].iter_mut() {
+                        *sq = ((*sq >> 3) | (*sq << 3)) & 63;
+                    }
+                }
+                break;
+            }
+            idx = lead_group_index(&squares, material.has_unique_pieces);
+        }
+
+        // The remaining groups, each encoded as a combination of the
+        // squares the previous groups left free
+        idx *= d.group_idx[0];
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = material.has_pawns && material.pawns[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] != 0 {
+            let len = d.group_len[next];
+            squares[start..start + len].sort();
+
+            let mut n = 0;
+            for i in 0..len {
+                let sq = squares[start + i];
+                let adjust = squares[..start].iter().filter(|&&s| sq > s).count();
+                n += index.binomial[i + 1][sq - adjust - 8 * remaining_pawns as usize];
+            }
+
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            start += len;
+            next += 1;
+        }
+
+        let value = data.decompress(d, idx);
+        Some(TableProbe::Value(match self.kind {
+            TableKind::Wdl => value as i32 - 2,
+            TableKind::Dtz => data.map_dtz(d, value, wdl),
+        }))
+    }
+}
+
+// Index of the first group of a pawnless position: three unique pieces
+// together, or just the two kings
+fn lead_group_index(squares: &[usize], unique: bool) -> u64 {
+    let index = &*INDEX;
+    if !unique {
+        return index.map_kk[index.map_a1d1d4[squares[0]]][squares[1]] as u64;
+    }
+
+    let (s0, s1, s2) = (squares[0], squares[1], squares[2]);
+    let adjust1 = (s1 > s0) as usize;
+    let adjust2 = (s2 > s0) as usize + (s2 > s1) as usize;
+    let rank = |sq: usize| sq / 8;
+
+    let idx = if off_diagonal(s0) != 0 {
+        (index.map_a1d1d4[s0] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+    } else if off_diagonal(s1) != 0 {
+        (6 * 63 + rank(s0) * 28 + index.map_b1h1h7[s1]) * 62 + s2 - adjust2
+    } else if off_diagonal(s2) != 0 {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + rank(s0) * 7 * 28
+            + (rank(s1) - adjust1) * 28
+            + index.map_b1h1h7[s2]
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + rank(s0) * 7 * 6
+            + (rank(s1) - adjust1) * 6
+            + (rank(s2) - adjust2)
+    };
+    idx as u64
+}
+
+impl TableData {
+    fn parse(file: Mmap, kind: TableKind, material: &Material) -> Option<Self> {
+        let magic = match kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+        if file.len() < 5 || file[..4] != magic {
+            return None;
+        }
+
+        let mut table = TableData {
+            file,
+            pairs: Default::default(),
+            map: 0,
+        };
+        let bytes = &table.file;
+
+        // First byte: bit 0 when both sides to move are stored, bit 1 when
+        // the table has pawns
+        let flags = bytes[4];
+        let split = !material.symmetric;
+        if (flags & 2 != 0) != material.has_pawns || (flags & 1 != 0) != split {
+            return None;
+        }
+
+        let sides = if kind == TableKind::Wdl && split {
+            2
+        } else {
+            1
+        };
+        let files = if material.has_pawns { 4 } else { 1 };
+        let both_pawns = material.has_pawns && material.pawns[1] > 0;
+
+        let mut pos = 5;
+        let mut pairs: [[PairsData; 4]; 2] = Default::default();
+        for f in 0..files {
+            let order = [
+                [
+                    (bytes[pos] & 0xf) as usize,
+                    if both_pawns {
+                        (bytes[pos + 1] & 0xf) as usize
+                    } else {
+                        0xf
+                    },
+                ],
+                [
+                    (bytes[pos] >> 4) as usize,
+                    if both_pawns {
+                        (bytes[pos + 1] >> 4) as usize
+                    } else {
+                        0xf
+                    },
+                ],
+            ];
+            pos += 1 + both_pawns as usize;
+
+            for k in 0..material.pieces {
+                for (i, side) in pairs.iter_mut().enumerate().take(sides) {
+                    let b = bytes[pos];
+                    side[f].pieces[k] = if i == 0 { b & 0xf } else { b >> 4 };
+                }
+                pos += 1;
+            }
+
+            for (i, side) in pairs.iter_mut().enumerate().take(sides) {
+                set_groups(&mut side[f], material, order[i], f);
+            }
+        }
+        pos += pos & 1;
+
+        for f in 0..files {
+            for side in pairs.iter_mut().take(sides) {
+                pos = set_sizes(&mut side[f], bytes, pos)?;
+            }
+        }
+
+        if kind == TableKind::Dtz {
+            table.map = pos;
+            for d in pairs[0].iter_mut().take(files) {
+                if d.flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                if d.flags & FLAG_WIDE != 0 {
+                    pos += pos & 1;
+                    for i in 0..4 {
+                        d.map_idx[i] = (pos - table.map) / 2 + 1;
+                        pos += 2 * read_u16(bytes, pos)? as usize + 2;
+                    }
+                } else {
+                    for i in 0..4 {
+                        d.map_idx[i] = pos - table.map + 1;
+                        pos += *bytes.get(pos)? as usize + 1;
+                    }
+                }
+            }
+            pos += pos & 1;
+        }
+
+        for f in 0..files {
+            for side in pairs.iter_mut().take(sides) {
+                side[f].sparse_index = pos;
+                pos += side[f].sparse_index_size * 6;
+            }
+        }
+        for f in 0..files {
+            for side in pairs.iter_mut().take(sides) {
+                side[f].block_lengths = pos;
+                pos += side[f].block_lengths_size * 2;
+            }
+        }
+        for f in 0..files {
+            for side in pairs.iter_mut().take(sides) {
+                pos = (pos + 0x3f) & !0x3f;
+                side[f].data = pos;
+                pos += side[f].num_blocks * side[f].block_size;
+            }
+        }
+        if pos > bytes.len() {
+            return None;
+        }
+
+        table.pairs = pairs;
+        Some(table)
+    }
+
+    fn decompress(&self, d: &PairsData, idx: u64) -> u16 {
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            return d.min_sym_len as u16;
+        }
+        let bytes = &self.file;
+
+        // The sparse index points at the block holding the value in the
+        // middle of every span, walk from there to the one holding `idx`
+        let k = (idx / d.span as u64) as usize;
+        let entry = d.sparse_index + 6 * k;
+        let mut block = u32::from_le_bytes(bytes[entry..entry + 4].try_into().unwrap()) as usize;
+        let mut offset = u16::from_le_bytes([bytes[entry + 4], bytes[entry + 5]]) as i64;
+        offset += (idx % d.span as u64) as i64 - (d.span / 2) as i64;
+
+        let block_length = |b: usize| {
+            u16::from_le_bytes([
+                bytes[d.block_lengths + 2 * b],
+                bytes[d.block_lengths + 2 * b + 1],
+            ]) as i64
+        };
+        while offset < 0 {
+            block -= 1;
+            offset += block_length(block) + 1;
+        }
+        while offset > block_length(block) {
+            offset -= block_length(block) + 1;
+            block += 1;
+        }
+
+        // Decode symbols until the one that covers `offset`
+        let mut ptr = d.data + block * d.block_size;
+        let read_u32 = |p: usize| u32::from_be_bytes(bytes[p..p + 4].try_into().unwrap());
+        let mut buf = u64::from_be_bytes(bytes[ptr..ptr + 8].try_into().unwrap());
+        ptr += 8;
+        let mut buf_size = 64;
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buf < d.base64[len] {
+                len += 1;
+            }
+            let shift = (64 - len - d.min_sym_len as usize) as u32;
+            sym = (buf - d.base64[len]).checked_shr(shift).unwrap_or(0) as usize;
+            let lowest = d.lowest_sym + 2 * len;
+            sym += u16::from_le_bytes([bytes[lowest], bytes[lowest + 1]]) as usize;
+
+            let values = d.symlen[sym] as i64 + 1;
+            if offset < values {
+                break;
+            }
+            offset -= values;
+
+            let len = len + d.min_sym_len as usize;
+            buf <<= len;
+            buf_size -= len;
+            if buf_size <= 32 {
+                buf_size += 32;
+                buf |= (read_u32(ptr) as u64) << (64 - buf_size);
+                ptr += 4;
+            }
+        }
+
+        // Symbols stand for a pair of symbols, follow the side that holds
+        // `offset` down to a single value
+        while d.symlen[sym] != 0 {
+            let (left, right) = pair(bytes, d.btree, sym);
+            let values = d.symlen[left] as i64 + 1;
+            if offset < values {
+                sym = left;
+            } else {
+                offset -= values;
+                sym = right;
+            }
+        }
+        pair(bytes, d.btree, sym).0 as u16
+    }
+
+    // Stored DTZ values are remapped by frequency and may count moves
+    // instead of plies, returns plies
+    fn map_dtz(&self, d: &PairsData, value: u16, wdl: Wdl) -> i32 {
+        let mut value = value as usize;
+        if d.flags & FLAG_MAPPED != 0 {
+            let map_idx = d.map_idx[match wdl {
+                Wdl::Loss => 1,
+                Wdl::BlessedLoss => 3,
+                Wdl::Draw | Wdl::Win => 0,
+                Wdl::CursedWin => 2,
+            }];
+            let bytes = &self.file;
+            value = if d.flags & FLAG_WIDE != 0 {
+                let p = self.map + 2 * (map_idx + value);
+                u16::from_le_bytes([bytes[p], bytes[p + 1]]) as usize
+            } else {
+                bytes[self.map + map_idx + value] as usize
+            };
+        }
+
+        let plies = match wdl {
+            Wdl::Win => d.flags & FLAG_WIN_PLIES != 0,
+            Wdl::Loss => d.flags & FLAG_LOSS_PLIES != 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => false,
+            Wdl::Draw => true,
+        };
+        let value = if plies { value } else { 2 * value };
+        value as i32 + 1
+    }
+}
+
+fn read_u16(bytes: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes([*bytes.get(pos)?, *bytes.get(pos + 1)?]))
+}
+
+// Left and right child of a symbol, 12 bits each
+fn pair(bytes: &[u8], btree: usize, sym: usize) -> (usize, usize) {
+    let p = btree + 3 * sym;
+    let (a, b, c) = (
+        bytes[p] as usize,
+        bytes[p + 1] as usize,
+        bytes[p + 2] as usize,
+    );
+    (((b & 0xf) << 8) | a, (c << 4) | (b >> 4))
+}
+
+// Pieces of the same kind and color are encoded together. Without pawns the
+// first group holds three unique pieces, or the two kings when there are
+// none. `order` says in which order the groups are multiplied together.
+fn set_groups(d: &mut PairsData, material: &Material, order: [usize; 2], file: usize) {
+    let index = &*INDEX;
+    let mut first_len: i32 = if material.has_pawns {
+        0
+    } else if material.has_unique_pieces {
+        3
+    } else {
+        2
+    };
+
+    let mut n = 0;
+    d.group_len[0] = 1;
+    for i in 1..material.pieces {
+        first_len -= 1;
+        if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+            d.group_len[n] += 1;
+        } else {
+            n += 1;
+            d.group_len[n] = 1;
+        }
+    }
+    n += 1;
+    d.group_len[n] = 0;
+
+    let both_pawns = material.has_pawns && material.pawns[1] > 0;
+    let mut next = if both_pawns { 2 } else { 1 };
+    let mut free_squares = 64 - d.group_len[0] - if both_pawns { d.group_len[1] } else { 0 };
+    let mut idx: u64 = 1;
+
+    let mut k = 0;
+    while next < n || k == order[0] || k == order[1] {
+        if k == order[0] {
+            d.group_idx[0] = idx;
+            idx *= if material.has_pawns {
+                index.lead_pawns_size[d.group_len[0]][file]
+            } else if material.has_unique_pieces {
+                31332
+            } else {
+                462
+            };
+        } else if k == order[1] {
+            d.group_idx[1] = idx;
+            idx *= index.binomial[d.group_len[1]][48 - d.group_len[0]];
+        } else {
+            d.group_idx[next] = idx;
+            idx *= index.binomial[d.group_len[next]][free_squares];
+            free_squares -= d.group_len[next];
+            next += 1;
+        }
+        k += 1;
+    }
+    d.group_idx[n] = idx;
+}
+
+// Reads the Huffman code and block layout of one table, returns the offset
+// right after it
+fn set_sizes(d: &mut PairsData, bytes: &[u8], mut pos: usize) -> Option<usize> {
+    d.flags = *bytes.get(pos)?;
+    pos += 1;
+
+    if d.flags & FLAG_SINGLE_VALUE != 0 {
+        // The one value is stored in place of the minimum symbol length
+        d.min_sym_len = *bytes.get(pos)?;
+        return Some(pos + 1);
+    }
+
+    let groups = d.group_len.iter().position(|&l| l == 0).unwrap();
+    let size = d.group_idx[groups];
+
+    let header = bytes.get(pos..pos + 9)?;
+    d.block_size = 1 << header[0];
+    d.span = 1 << header[1];
+    d.sparse_index_size = size.div_ceil(d.span as u64) as usize;
+    let padding = header[2] as usize;
+    d.num_blocks = u32::from_le_bytes(header[3..7].try_into().unwrap()) as usize;
+    // Padded so the sparse index never points past the end
+    d.block_lengths_size = d.num_blocks + padding;
+    let max_sym_len = header[7];
+    d.min_sym_len = header[8];
+    pos += 9;
+    if max_sym_len < d.min_sym_len {
+        return None;
+    }
+
+    // Longer codes have lower values, base64 goes from the shortest length
+    // to the longest and is left aligned to 64 bits
+    d.lowest_sym = pos;
+    let lengths = (max_sym_len - d.min_sym_len) as usize + 1;
+    let lowest = |i: usize| read_u16(bytes, d.lowest_sym + 2 * i).map(|s| s as u64);
+    d.base64 = vec![0; lengths];
+    for i in (0..lengths - 1).rev() {
+        d.base64[i] = (d.base64[i + 1] + lowest(i)?).checked_sub(lowest(i + 1)?)? / 2;
+    }
+    for (i, base) in d.base64.iter_mut().enumerate() {
+        *base = base
+            .checked_shl((64 - i - d.min_sym_len as usize) as u32)
+            .unwrap_or(0);
+    }
+    pos += 2 * lengths;
+
+    let symbols = read_u16(bytes, pos)? as usize;
+    pos += 2;
+    d.btree = pos;
+    bytes.get(pos..pos + 3 * symbols)?;
+
+    d.symlen = vec![0; symbols];
+    let mut visited = vec![false; symbols];
+    for sym in 0..symbols {
+        if !visited[sym] {
+            d.symlen[sym] = symlen(d, bytes, sym, &mut visited);
+        }
+    }
+
+    Some(pos + 3 * symbols + (symbols & 1))
+}
+
+fn symlen(d: &mut PairsData, bytes: &[u8], sym: usize, visited: &mut [bool]) -> u8 {
+    visited[sym] = true;
+    let (left, right) = pair(bytes, d.btree, sym);
+    if right == 0xfff {
+        return 0;
+    }
+
+    for s in [left, right] {
+        if !visited[s] {
+            d.symlen[s] = symlen(d, bytes, s, visited);
+        }
+    }
+    (d.symlen[left] as usize + d.symlen[right] as usize + 1) as u8
+}
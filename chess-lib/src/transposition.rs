@@ -1,23 +1,66 @@
-use crate::movegen::Move;
+use crate::{
+    movegen::Move,
+    score::{from_tt, to_tt},
+};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
+// Entries are two atomics so the table can be shared between search threads
+// without locking. The key is stored xor-ed with the data, so an entry torn
+// by concurrent writes fails the key check instead of returning bad data.
 struct Transposition {
-    hash: u64,
-    depth: i32,
-    score: i32,
-    kind: TranspositionKind,
-    _best_move: Move,
+    key: AtomicU64,
+    data: AtomicU64,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TranspositionKind {
     Exact,
     Alpha,
     Beta,
 }
 
+struct Entry {
+    depth: i32,
+    score: i32,
+    kind: TranspositionKind,
+    best_move: Move,
+}
+
+impl Entry {
+    // | score: 30 | kind: 2 | depth: 8 | move: 24 |
+    fn pack(&self) -> u64 {
+        let kind = match self.kind {
+            TranspositionKind::Exact => 0,
+            TranspositionKind::Alpha => 1,
+            TranspositionKind::Beta => 2,
+        };
+        ((self.score as u64 & 0x3fff_ffff) << 34)
+            | (kind << 32)
+            | ((self.depth.clamp(0, 255) as u64) << 24)
+            | (self.best_move.to_bits() as u64 & 0xff_ffff)
+    }
+
+    fn unpack(data: u64) -> Self {
+        // Sign extend the 30 bit score
+        let score = ((data as i64) >> 34) as i32;
+        let kind = match (data >> 32) & 0b11 {
+            0 => TranspositionKind::Exact,
+            1 => TranspositionKind::Alpha,
+            _ => TranspositionKind::Beta,
+        };
+        Self {
+            depth: ((data >> 24) & 0xff) as i32,
+            score,
+            kind,
+            best_move: Move::from_bits((data & 0xff_ffff) as u32),
+        }
+    }
+}
+
 pub struct TranspositionTable {
     table: Box<[Transposition]>,
 
-    pub stored_cnt: usize,
+    stored_cnt: AtomicUsize,
 }
 
 impl TranspositionTable {
@@ -25,67 +68,87 @@ impl TranspositionTable {
         self.table.len()
     }
 
+    pub fn stored_cnt(&self) -> usize {
+        self.stored_cnt.load(Ordering::Relaxed)
+    }
+
     pub fn new(entries: usize) -> Self {
         let table = (0..entries)
             .map(|_| Transposition {
-                hash: 0,
-                depth: 0,
-                score: 0,
-                kind: TranspositionKind::Exact,
-                _best_move: Move::empty(),
+                key: AtomicU64::new(0),
+                data: AtomicU64::new(0),
             })
             .collect();
 
         Self {
             table,
-            stored_cnt: 0,
+            stored_cnt: AtomicUsize::new(0),
         }
     }
 
-    pub fn probe(&self, hash: u64, depth: i32, alpha: i32, beta: i32) -> Option<i32> {
+    fn load(&self, hash: u64) -> Option<Entry> {
         let idx = hash as usize % self.table.len();
         let entry = &self.table[idx];
+        let data = entry.data.load(Ordering::Relaxed);
+        let key = entry.key.load(Ordering::Relaxed);
+
+        if key ^ data == hash && data != 0 {
+            Some(Entry::unpack(data))
+        } else {
+            None
+        }
+    }
+
+    pub fn probe(&self, hash: u64, depth: i32, ply: i32, alpha: i32, beta: i32) -> Option<i32> {
+        let mut entry = self.load(hash)?;
+        if entry.depth < depth {
+            return None;
+        }
+        entry.score = from_tt(entry.score, ply);
 
-        if entry.hash == hash && entry.depth >= depth {
-            match entry.kind {
-                TranspositionKind::Exact => Some(entry.score),
-                TranspositionKind::Alpha => {
-                    if entry.score <= alpha {
-                        Some(entry.score)
-                    } else {
-                        None
-                    }
+        match entry.kind {
+            TranspositionKind::Exact => Some(entry.score),
+            TranspositionKind::Alpha => {
+                if entry.score <= alpha {
+                    Some(entry.score)
+                } else {
+                    None
                 }
-                TranspositionKind::Beta => {
-                    if entry.score >= beta {
-                        Some(entry.score)
-                    } else {
-                        None
-                    }
+            }
+            TranspositionKind::Beta => {
+                if entry.score >= beta {
+                    Some(entry.score)
+                } else {
+                    None
                 }
             }
-        } else {
-            None
         }
     }
 
+    pub fn best_move(&self, hash: u64) -> Option<Move> {
+        self.load(hash).map(|e| e.best_move)
+    }
+
     pub fn store(
-        &mut self,
+        &self,
         hash: u64,
         depth: i32,
+        ply: i32,
         score: i32,
         kind: TranspositionKind,
         best_move: Move,
     ) {
-        self.stored_cnt += 1;
+        self.stored_cnt.fetch_add(1, Ordering::Relaxed);
         let idx = hash as usize % self.table.len();
 
-        self.table[idx] = Transposition {
-            hash,
+        let data = Entry {
             depth,
-            score,
+            score: to_tt(score, ply),
             kind,
-            _best_move: best_move,
-        };
+            best_move,
+        }
+        .pack();
+        self.table[idx].key.store(hash ^ data, Ordering::Relaxed);
+        self.table[idx].data.store(data, Ordering::Relaxed);
     }
 }
@@ -1,15 +1,22 @@
 use crate::{
+    analysis::PositionAnalysis,
+    bitboard::BitBoard,
     board::Board,
-    bot::Bot,
+    book::Book,
+    bot::{Bot, PvLine},
     console_log,
+    eval::pawns::PawnStructure,
     game::Game,
     movegen::{legal_moves, Move, SpecialMove},
     piece::Piece,
+    score::Score,
+    see::see,
     square::Square,
+    strength::{pick_move, Strength, MAX_ELO, MIN_ELO},
 };
-use rand::Rng;
+use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
-use std::fmt::Display;
+use std::{fmt::Display, sync::atomic::Ordering};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -36,6 +43,10 @@ impl WasmMove {
     pub fn from_json(json: String) -> Self {
         serde_json::from_str(&json).unwrap()
     }
+
+    pub fn uci(&self) -> String {
+        self.mv.to_string()
+    }
 }
 
 impl Display for WasmMove {
@@ -170,6 +181,10 @@ impl WasmGame {
         self.0.update_state();
     }
 
+    pub fn insufficient_material(&self, color: crate::piece::Color) -> bool {
+        crate::game::insufficient_material(&self.0.board, color)
+    }
+
     pub fn legal_moves(&self) -> Vec<WasmMove> {
         legal_moves(&self.0.board).iter().map(Into::into).collect()
     }
@@ -199,6 +214,16 @@ impl WasmGame {
     pub fn board_at(&self, ply: usize) -> WasmBoard {
         WasmBoard(self.0.board_history[ply].clone())
     }
+
+    pub fn analyze(
+        &self,
+        lines: usize,
+        depth: i32,
+        max_time: u64,
+        on_iteration: Option<js_sys::Function>,
+    ) -> Vec<AnalysisLine> {
+        analyze(self.board(), lines, depth, max_time, on_iteration)
+    }
 }
 
 #[wasm_bindgen]
@@ -239,6 +264,59 @@ impl WasmBoard {
     pub fn from_json(json: String) -> Self {
         Self(serde_json::from_str(&json).unwrap())
     }
+
+    pub fn see(&self, mv: WasmMove) -> i32 {
+        see(&self.0, &mv.into())
+    }
+
+    pub fn pawn_structure(&self) -> PawnStructureInfo {
+        let s = PawnStructure::new(&self.0);
+        let squares = |b: [u64; 2]| {
+            let b = b[0] | b[1];
+            (0..64).filter(|i| b & (1 << i) != 0).collect()
+        };
+        PawnStructureInfo {
+            passed: squares(s.passed),
+            isolated: squares(s.isolated),
+            doubled: squares(s.doubled),
+            backward: squares(s.backward),
+        }
+    }
+
+    pub fn analysis(&self) -> PositionAnalysisInfo {
+        let a = PositionAnalysis::new(&self.0);
+        let squares = |b: BitBoard| (0..64).filter(|&i| b.get(i as u64)).collect();
+        PositionAnalysisInfo {
+            white_attacks: a.attackers[0].to_vec(),
+            black_attacks: a.attackers[1].to_vec(),
+            white_controlled: squares(a.controlled[0]),
+            black_controlled: squares(a.controlled[1]),
+            hanging: squares(a.hanging),
+            pinned: squares(a.pinned),
+            checkers: squares(a.checkers),
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct PawnStructureInfo {
+    pub passed: Vec<u8>,
+    pub isolated: Vec<u8>,
+    pub doubled: Vec<u8>,
+    pub backward: Vec<u8>,
+}
+
+// Square lists are indices, A1 = 0. Attack counts have an entry for every
+// square.
+#[wasm_bindgen(getter_with_clone)]
+pub struct PositionAnalysisInfo {
+    pub white_attacks: Vec<u8>,
+    pub black_attacks: Vec<u8>,
+    pub white_controlled: Vec<u8>,
+    pub black_controlled: Vec<u8>,
+    pub hanging: Vec<u8>,
+    pub pinned: Vec<u8>,
+    pub checkers: Vec<u8>,
 }
 
 #[wasm_bindgen]
@@ -251,11 +329,71 @@ pub struct PieceWithIndex {
 #[wasm_bindgen]
 pub struct BotMove {
     pub nodes_searched: u64,
+    // Centipawns, or the mate distance in moves when `mate` is set
     pub score: i32,
+    pub mate: bool,
     pub depth: i32,
     pub best_move: WasmMove,
 }
 
+#[wasm_bindgen]
+impl BotMove {
+    // "+0.35", "M3" or "-M3", from the bot's point of view
+    pub fn score_text(&self) -> String {
+        self.score().to_string()
+    }
+}
+
+impl BotMove {
+    pub fn score(&self) -> Score {
+        join_score(self.score, self.mate)
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct AnalysisLine {
+    // Same encoding as `BotMove`
+    pub score: i32,
+    pub mate: bool,
+    pub depth: i32,
+    pub pv: Vec<WasmMove>,
+}
+
+#[wasm_bindgen]
+impl AnalysisLine {
+    pub fn score_text(&self) -> String {
+        join_score(self.score, self.mate).to_string()
+    }
+}
+
+impl From<PvLine> for AnalysisLine {
+    fn from(line: PvLine) -> Self {
+        let (score, mate) = split_score(line.score);
+        Self {
+            score,
+            mate,
+            depth: line.depth,
+            pv: line.pv.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+fn split_score(score: i32) -> (i32, bool) {
+    match Score::from_search(score) {
+        Score::Centipawns(cp) => (cp, false),
+        Score::Mate(n) => (n, true),
+        Score::MateGiven => unreachable!("Searches never start with the opponent mated"),
+    }
+}
+
+fn join_score(score: i32, mate: bool) -> Score {
+    if mate {
+        Score::Mate(score)
+    } else {
+        Score::Centipawns(score)
+    }
+}
+
 #[wasm_bindgen]
 #[derive(PartialEq, Debug)]
 pub enum Difficulty {
@@ -264,103 +402,98 @@ pub enum Difficulty {
     Hard,
 }
 
+impl Difficulty {
+    pub fn elo(&self) -> u32 {
+        match self {
+            Difficulty::Easy => 800,
+            Difficulty::Medium => 1400,
+            Difficulty::Hard => MAX_ELO,
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub fn bot_move(board: WasmBoard, difficulty: Difficulty) -> BotMove {
-    console_error_panic_hook::set_once();
+    bot_move_elo(board, difficulty.elo())
+}
 
-    let (depth, tt_size, max_time) = match difficulty {
-        Difficulty::Easy => (5, 10000000, 3000),
-        Difficulty::Medium => (7, 10000000, 3000),
-        Difficulty::Hard => (30, 10000000, 10000),
-    };
+// Plays at a strength between `MIN_ELO` and `MAX_ELO`, out of range values
+// are clamped
+#[wasm_bindgen]
+pub fn bot_move_elo(board: WasmBoard, elo: u32) -> BotMove {
+    console_error_panic_hook::set_once();
 
-    let mut bot = Bot::new(depth, tt_size, max_time);
-    let m = bot.make_move(board.0, difficulty == Difficulty::Hard);
+    let strength = Strength::new(elo);
+    let mut bot = Bot::with_limits(10000000, strength.limits());
+    let m = pick_move(&mut bot, board.0, strength, &mut rand::thread_rng())
+        .expect("Bot asked to move without legal moves");
 
+    let (score, mate) = split_score(bot.score);
     BotMove {
-        best_move: select_move(m, difficulty),
+        best_move: m.into(),
         nodes_searched: bot.nodes_searched,
-        score: bot.score,
+        score,
+        mate,
         depth: bot.reached_depth,
     }
 }
 
-fn select_move(moves: Box<[(Move, i32)]>, difficulty: Difficulty) -> WasmMove {
-    match difficulty {
-        Difficulty::Easy => {
-            if moves[0].1 >= 400_000 {
-                return moves[0].0.into();
-            }
+lazy_static! {
+    static ref BOOK: Book = Book::builtin();
+}
 
-            let score_brackets = [50, 100, 200, 500, 2000];
-            let probabilites = [0.35, 0.25, 0.2, 0.15, 0.05];
+// A move from the bundled opening book, if the position is in it and the
+// game is still within the book depth of the level. `ply` is the number of
+// moves played so far.
+#[wasm_bindgen]
+pub fn book_move(board: WasmBoard, ply: usize, elo: u32) -> Option<WasmMove> {
+    if ply >= Strength::new(elo).book_plies() {
+        return None;
+    }
+    BOOK.pick(&board.0, ply, &mut rand::thread_rng())
+        .map(Into::into)
+}
 
-            let moves_per_bracked: Vec<_> = score_brackets
-                .iter()
-                .map(|b| {
-                    moves
-                        .iter()
-                        .filter(|(_, s)| moves[0].1 - *s <= *b)
-                        .collect::<Vec<_>>()
-                })
-                .collect();
-
-            loop {
-                let mut r: f64 = rand::thread_rng().gen();
-                console_log!("Generated {r}");
-                let mut bracket = 0;
-                for i in 0..probabilites.len() {
-                    r -= probabilites[i];
-                    bracket = i;
-                    if r <= 0. {
-                        break;
-                    }
-                }
-                console_log!("Bracket {bracket} - {}", score_brackets[bracket]);
+#[wasm_bindgen]
+pub fn min_elo() -> u32 {
+    MIN_ELO
+}
 
-                if !moves_per_bracked[bracket].is_empty() {
-                    let idx = rand::thread_rng().gen_range(0..moves_per_bracked[bracket].len());
-                    return moves[idx].0.into();
-                }
-            }
-        }
-        Difficulty::Medium => {
-            if moves[0].1 >= 400_000 {
-                return moves[0].0.into();
-            }
+#[wasm_bindgen]
+pub fn max_elo() -> u32 {
+    MAX_ELO
+}
 
-            let score_brackets = [20, 50, 100, 200, 400];
-            let probabilites = [0.5, 0.3, 0.1, 0.08, 0.02];
+// The best `lines` moves with their principal variations. `on_iteration` is
+// called with the depth and lines after every completed iteration, returning
+// `false` from it stops the search.
+#[wasm_bindgen]
+pub fn analyze(
+    board: WasmBoard,
+    lines: usize,
+    depth: i32,
+    max_time: u64,
+    on_iteration: Option<js_sys::Function>,
+) -> Vec<AnalysisLine> {
+    console_error_panic_hook::set_once();
 
-            let moves_per_bracked: Vec<_> = score_brackets
+    let mut bot = Bot::new(depth, 10000000, max_time);
+    let stop = bot.stop_handle();
+    bot.analyze(board.0, lines, |info| {
+        if let Some(f) = &on_iteration {
+            let lines = info
+                .lines
                 .iter()
-                .map(|b| {
-                    moves
-                        .iter()
-                        .filter(|(_, s)| moves[0].1 - *s <= *b)
-                        .collect::<Vec<_>>()
-                })
-                .collect();
-
-            loop {
-                let mut r: f64 = rand::thread_rng().gen();
-                console_log!("Generated {r}");
-                let mut bracket = 0;
-                for i in 0..probabilites.len() {
-                    r -= probabilites[i];
-                    bracket = i;
-                    if r <= 0. {
-                        break;
-                    }
-                }
-                console_log!("Bracket {bracket} - {}", score_brackets[bracket]);
-
-                if !moves_per_bracked[bracket].is_empty() {
-                    let idx = rand::thread_rng().gen_range(0..moves_per_bracked[bracket].len());
-                    return moves[idx].0.into();
-                }
+                .cloned()
+                .map(|l| JsValue::from(AnalysisLine::from(l)))
+                .collect::<js_sys::Array>();
+            let res = f.call2(&JsValue::NULL, &JsValue::from(info.depth), &lines);
+            if res.ok().and_then(|v| v.as_bool()) == Some(false) {
+                stop.store(true, Ordering::Relaxed);
             }
         }
-        Difficulty::Hard => moves[0].0.into(),
-    }
+    })
+    .into_iter()
+    .map(Into::into)
+    .collect()
 }
@@ -2,8 +2,40 @@ use crate::{
     board::Board,
     piece::{Color, Piece},
 };
-use rand::Rng;
+use lazy_static::lazy_static;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
+lazy_static! {
+    // Fixed keys so the pawn key can be updated incrementally by `Board`
+    static ref PAWN_KEYS: [[u64; 64]; 2] = {
+        let mut rng = StdRng::seed_from_u64(0x5041574e);
+        let mut keys = [[0; 64]; 2];
+        for color in keys.iter_mut() {
+            for key in color.iter_mut() {
+                *key = rng.gen();
+            }
+        }
+        keys
+    };
+}
+
+pub fn pawn_key(color: Color, square: u64) -> u64 {
+    PAWN_KEYS[color as usize][square as usize]
+}
+
+pub fn pawn_hash(board: &Board) -> u64 {
+    let mut hash = 0;
+    for (color, pawns) in [(Color::White, board.w_pawn), (Color::Black, board.b_pawn)] {
+        let mut b = pawns.0;
+        while b != 0 {
+            hash ^= pawn_key(color, b.trailing_zeros() as u64);
+            b &= b - 1;
+        }
+    }
+    hash
+}
+
+#[derive(Clone)]
 pub struct Zobrist {
     pieces: [[u64; 64]; 12],
     castling: [u64; 16],
@@ -0,0 +1,2 @@
+DROP TABLE game_analysis;
+DROP TABLE analysis_jobs;
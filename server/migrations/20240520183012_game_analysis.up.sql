@@ -0,0 +1,25 @@
+CREATE TABLE analysis_jobs (
+  game_id INT PRIMARY KEY REFERENCES games(id),
+  -- pending, running, done or failed
+  status VARCHAR(16) NOT NULL DEFAULT 'pending',
+  white_accuracy DOUBLE,
+  black_accuracy DOUBLE,
+  error VARCHAR(256),
+
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  finished_at TIMESTAMP NULL
+);
+
+CREATE TABLE game_analysis (
+  game_id INT NOT NULL REFERENCES games(id),
+  ply INT NOT NULL,
+  played VARCHAR(8) NOT NULL,
+  best_move VARCHAR(8),
+  -- Evaluation after the move from white's view, one of the two is set
+  score INT,
+  mate INT,
+  class VARCHAR(16) NOT NULL,
+  cp_loss INT NOT NULL,
+
+  PRIMARY KEY (game_id, ply)
+);
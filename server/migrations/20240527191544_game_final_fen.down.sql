@@ -0,0 +1,2 @@
+ALTER TABLE games
+DROP COLUMN final_fen;
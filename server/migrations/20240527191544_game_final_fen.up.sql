@@ -0,0 +1,2 @@
+ALTER TABLE games
+ADD COLUMN final_fen VARCHAR(100) AFTER moves;
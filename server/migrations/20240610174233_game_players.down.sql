@@ -0,0 +1,25 @@
+-- Games between two users can't be stored with a single player
+DELETE FROM game_analysis WHERE game_id IN (SELECT id FROM games WHERE bot_color IS NULL);
+DELETE FROM analysis_jobs WHERE game_id IN (SELECT id FROM games WHERE bot_color IS NULL);
+DELETE FROM games WHERE bot_color IS NULL;
+
+ALTER TABLE games
+ADD COLUMN player VARCHAR(64) REFERENCES users(id) AFTER id;
+
+UPDATE games
+SET player = CAST(IF(bot_color = 'Black', white_player, black_player) AS CHAR),
+result = CASE
+  WHEN result = '1/2-1/2' THEN 'Draw'
+  WHEN (result = '1-0') = (bot_color = 'Black') THEN 'Win'
+  ELSE 'Loss'
+END;
+
+ALTER TABLE games
+DROP FOREIGN KEY games_white_player,
+DROP FOREIGN KEY games_black_player,
+DROP COLUMN white_player,
+DROP COLUMN black_player,
+DROP COLUMN bot_color,
+DROP COLUMN termination,
+DROP COLUMN rated,
+DROP COLUMN time_control;
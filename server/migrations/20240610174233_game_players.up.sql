@@ -0,0 +1,25 @@
+ALTER TABLE games
+ADD COLUMN white_player INT AFTER id,
+ADD COLUMN black_player INT AFTER white_player,
+-- Set for games against the bot, which has no user
+ADD COLUMN bot_color VARCHAR(5) AFTER black_player,
+ADD COLUMN termination VARCHAR(32) AFTER result,
+ADD COLUMN rated BOOLEAN NOT NULL DEFAULT FALSE AFTER difficulty,
+ADD COLUMN time_control VARCHAR(16) AFTER rated,
+ADD CONSTRAINT games_white_player FOREIGN KEY (white_player) REFERENCES users(id),
+ADD CONSTRAINT games_black_player FOREIGN KEY (black_player) REFERENCES users(id);
+
+-- Every game so far was against the bot and didn't record the player's
+-- color. The player is kept as white so results stay right for them.
+-- `player` held the session token of the player, or their id.
+UPDATE games
+LEFT JOIN users ON games.player = users.token OR games.player = CAST(users.id AS CHAR)
+SET games.white_player = users.id, games.bot_color = 'Black';
+
+-- Results are stored as 1-0, 0-1 or 1/2-1/2 instead of from the player's
+-- view, and * while the game is going on
+UPDATE games
+SET result = CASE result WHEN 'Win' THEN '1-0' WHEN 'Loss' THEN '0-1' ELSE '1/2-1/2' END;
+
+ALTER TABLE games
+DROP COLUMN player;
@@ -0,0 +1 @@
+DROP TABLE correspondence_games;
@@ -0,0 +1,19 @@
+CREATE TABLE correspondence_games (
+  id INT PRIMARY KEY AUTO_INCREMENT,
+  white_player INT NOT NULL REFERENCES users(id),
+  black_player INT NOT NULL REFERENCES users(id),
+  days_per_move INT NOT NULL,
+  rated BOOLEAN NOT NULL DEFAULT FALSE,
+  -- The moves so far, stored like games.moves
+  moves JSON NOT NULL,
+  ply INT NOT NULL DEFAULT 0,
+  -- The side to move loses once it's past
+  deadline TIMESTAMP NOT NULL,
+
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  finished_at TIMESTAMP NULL,
+  -- The finished game in `games`
+  game_id INT REFERENCES games(id),
+
+  INDEX (finished_at, deadline)
+);
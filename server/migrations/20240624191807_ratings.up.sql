@@ -0,0 +1,25 @@
+CREATE TABLE ratings (
+  user_id INT NOT NULL REFERENCES users(id),
+  -- bullet, blitz, rapid, classical or correspondence
+  category VARCHAR(16) NOT NULL,
+  rating DOUBLE NOT NULL,
+  deviation DOUBLE NOT NULL,
+  volatility DOUBLE NOT NULL,
+  games INT NOT NULL DEFAULT 0,
+
+  PRIMARY KEY (user_id, category),
+  INDEX (category, rating)
+);
+
+-- A user's rating after each of their rated games
+CREATE TABLE rating_history (
+  id INT PRIMARY KEY AUTO_INCREMENT,
+  user_id INT NOT NULL REFERENCES users(id),
+  category VARCHAR(16) NOT NULL,
+  game_id INT NOT NULL REFERENCES games(id),
+  rating DOUBLE NOT NULL,
+  deviation DOUBLE NOT NULL,
+  recorded_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  INDEX (user_id, category, recorded_at)
+);
@@ -0,0 +1,217 @@
+use anyhow::Context;
+use chess_lib::{
+    bot::{Bot, SearchLimits},
+    game::Game,
+    movegen::Move,
+    review::{review_game, GameReview},
+    score::Score,
+};
+use sqlx::{MySql, Pool};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::{Notify, Semaphore};
+
+// Games are analyzed in the background, one job per game kept in the
+// `analysis_jobs` table so queued and interrupted jobs are picked up again
+// after a restart. At most `ANALYSIS_WORKERS` games are searched at once,
+// each on a single thread.
+
+const TT_ENTRIES: usize = 1 << 18;
+const DEFAULT_NODES: u64 = 100_000;
+// Jobs are also looked for periodically, in case another server queued them
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+pub struct AnalysisQueue {
+    notify: Arc<Notify>,
+}
+
+impl AnalysisQueue {
+    pub fn start(pool: Pool<MySql>) -> Self {
+        let workers = std::env::var("ANALYSIS_WORKERS")
+            .ok()
+            .and_then(|w| w.parse().ok())
+            .unwrap_or_else(|| {
+                std::thread::available_parallelism().map_or(1, |n| (n.get() / 2).max(1))
+            });
+        let nodes = std::env::var("ANALYSIS_NODES")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(DEFAULT_NODES);
+
+        let notify = Arc::new(Notify::new());
+        tokio::spawn(run(pool, notify.clone(), workers, nodes));
+        Self { notify }
+    }
+
+    pub async fn enqueue(&self, pool: &Pool<MySql>, game_id: i32) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT IGNORE INTO analysis_jobs (game_id) VALUES (?)",
+            game_id
+        )
+        .execute(pool)
+        .await?;
+        self.notify.notify_one();
+        Ok(())
+    }
+}
+
+async fn run(pool: Pool<MySql>, notify: Arc<Notify>, workers: usize, nodes: u64) {
+    // Jobs that were running when the server stopped start over
+    if let Err(e) =
+        sqlx::query!("UPDATE analysis_jobs SET status = 'pending' WHERE status = 'running'")
+            .execute(&pool)
+            .await
+    {
+        tracing::error!("Failed to requeue analysis jobs: {}", e);
+    }
+
+    let permits = Arc::new(Semaphore::new(workers));
+    loop {
+        let permit = permits.clone().acquire_owned().await.unwrap();
+        match claim_job(&pool).await {
+            Ok(Some(game_id)) => {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    run_job(&pool, game_id, nodes).await;
+                    drop(permit);
+                });
+            }
+            Ok(None) => {
+                drop(permit);
+                tokio::select! {
+                    _ = notify.notified() => {}
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to fetch analysis jobs: {}", e);
+                drop(permit);
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+// Marks the oldest pending job as running
+async fn claim_job(pool: &Pool<MySql>) -> sqlx::Result<Option<i32>> {
+    loop {
+        let Some(game_id) = sqlx::query_scalar!(
+            "SELECT game_id FROM analysis_jobs WHERE status = 'pending' ORDER BY created_at LIMIT 1"
+        )
+        .fetch_optional(pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let claimed = sqlx::query!(
+            "UPDATE analysis_jobs SET status = 'running' WHERE game_id = ? AND status = 'pending'",
+            game_id
+        )
+        .execute(pool)
+        .await?;
+        if claimed.rows_affected() == 1 {
+            return Ok(Some(game_id));
+        }
+    }
+}
+
+async fn run_job(pool: &Pool<MySql>, game_id: i32, nodes: u64) {
+    tracing::info!("Analyzing game {}", game_id);
+    let result = match analyze(pool, game_id, nodes).await {
+        Ok(review) => {
+            // Every position gets the full node budget, so this is the
+            // depth the whole game was reviewed at
+            tracing::info!("Analyzed game {} at depth {}", game_id, review.depth);
+            store(pool, game_id, &review).await
+        }
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = result {
+        tracing::error!("Analysis of game {} failed: {:#}", game_id, e);
+        let error = format!("{:#}", e).chars().take(256).collect::<String>();
+        let _ = sqlx::query!(
+            "UPDATE analysis_jobs SET status = 'failed', error = ?, finished_at = NOW() WHERE game_id = ?",
+            error,
+            game_id
+        )
+        .execute(pool)
+        .await;
+    }
+}
+
+async fn analyze(pool: &Pool<MySql>, game_id: i32, nodes: u64) -> anyhow::Result<GameReview> {
+    let moves = sqlx::query_scalar!("SELECT moves FROM games WHERE id = ?", game_id)
+        .fetch_one(pool)
+        .await?;
+    let moves = parse_moves(moves)?;
+
+    // Searching blocks, keep it off the async runtime
+    tokio::task::spawn_blocking(move || {
+        let limits = SearchLimits {
+            nodes: Some(nodes),
+            ..Default::default()
+        };
+        let mut bot = Bot::with_limits(TT_ENTRIES, limits);
+        review_game(&Game::from_moves(moves), &mut bot, |_, _| {})
+    })
+    .await
+    .context("Analysis panicked")
+}
+
+async fn store(pool: &Pool<MySql>, game_id: i32, review: &GameReview) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!("DELETE FROM game_analysis WHERE game_id = ?", game_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for (ply, m) in review.moves.iter().enumerate() {
+        let (score, mate) = match m.eval {
+            Score::Centipawns(cp) => (Some(cp), None),
+            Score::Mate(n) => (None, Some(n)),
+            // Checkmate on the board is 0 either way, the player who made
+            // the move gave it
+            Score::MateGiven => (None, Some(0)),
+        };
+        sqlx::query!(
+            r#"
+            INSERT INTO game_analysis (game_id, ply, played, best_move, score, mate, class, cp_loss)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)"#,
+            game_id,
+            ply as i32 + 1,
+            m.mv.to_string(),
+            m.best.map(|b| b.to_string()),
+            score,
+            mate,
+            format!("{:?}", m.class),
+            m.cp_loss
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE analysis_jobs
+        SET status = 'done', white_accuracy = ?, black_accuracy = ?, error = NULL, finished_at = NOW()
+        WHERE game_id = ?"#,
+        review.white.accuracy,
+        review.black.accuracy,
+        game_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+// Clients submit the move list as a JSON encoded string
+pub fn parse_moves(moves: serde_json::Value) -> anyhow::Result<Vec<Move>> {
+    let moves: Vec<Move> = match moves {
+        serde_json::Value::String(s) => serde_json::from_str(&s)?,
+        moves => serde_json::from_value(moves)?,
+    };
+    Ok(moves)
+}
@@ -5,7 +5,8 @@ pub fn games_list(games: Vec<Game>, infinite_scroll_url: Option<&str>) -> String
     html! {
         <table class="games">
             <tr class="games-header">
-                <th style="padding: 5px 0px;">"Player"</th>
+                <th style="padding: 5px 0px;">"White"</th>
+                <th>"Black"</th>
                 <th>"Date"</th>
                 <th>"Result"</th>
                 <th>"Difficulty"</th>
@@ -34,7 +35,8 @@ pub fn game_html(game: Game) -> String {
 
     html! {
         <tr class="game" onclick={format!("location.href='/games/{}';", game.id)}>
-            <td>{player(&game)}</td>
+            <td>{player(&game, "White", &game.white)}</td>
+            <td>{player(&game, "Black", &game.black)}</td>
             <td>{date}</td>
             <td>{game.result}</td>
             <td>{difficulty}</td>
@@ -45,11 +47,14 @@ pub fn game_html(game: Game) -> String {
     }
 }
 
-fn player(game: &Game) -> String {
-    match &game.player {
+fn player(game: &Game, color: &str, username: &Option<String>) -> String {
+    match username {
         Some(player) => html! {
             <a class="player" href={format!("/users/{}", player)}>{player}</a>
         },
+        None if game.bot_color.as_deref() == Some(color) => html! {
+            "Bot"
+        },
         None => html! {
             "Guest"
         },
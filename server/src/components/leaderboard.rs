@@ -0,0 +1,41 @@
+use crate::{models::LeaderboardEntry, rating};
+use html_to_string_macro::html;
+
+// Rows of a leaderboard starting at rank `first_rank`, the last one loads
+// the next page when scrolled to
+pub fn leaderboard_rows(
+    entries: Vec<LeaderboardEntry>,
+    first_rank: i64,
+    next_page_url: Option<String>,
+) -> String {
+    let rows = entries
+        .into_iter()
+        .zip(first_rank..)
+        .map(|(entry, rank)| {
+            html! {
+                <tr>
+                    <td>{rank}</td>
+                    <td>
+                        <a class="player" href={format!("/users/{}", entry.username)}>{&entry.username}</a>
+                    </td>
+                    <td>{rating::display(entry.rating, entry.deviation)}</td>
+                    <td>{entry.games}</td>
+                </tr>
+            }
+        })
+        .collect::<String>();
+
+    let next_page = match next_page_url {
+        Some(url) => html! {
+            <tr hx-get={url} hx-trigger="revealed" hx-swap="outerHTML">
+                <td>"Loading"</td>
+            </tr>
+        },
+        None => String::new(),
+    };
+
+    html! {
+        {rows}
+        {next_page}
+    }
+}
@@ -0,0 +1,42 @@
+use crate::live::LiveGameSummary;
+use html_to_string_macro::html;
+
+// Refreshes itself every few seconds, so games that started or ended show up
+pub fn live_games_list(games: Vec<LiveGameSummary>) -> String {
+    let rows = if games.is_empty() {
+        html! {
+            <tr>
+                <td colspan="5" style="padding: 10px 0px;">"No games are being played right now"</td>
+            </tr>
+        }
+    } else {
+        games.into_iter().map(live_game_html).collect::<String>()
+    };
+
+    html! {
+        <table class="games" hx-get="/api/live_games" hx-trigger="every 5s" hx-swap="outerHTML">
+            <tr class="games-header">
+                <th style="padding: 5px 0px;">"White"</th>
+                <th>"Black"</th>
+                <th>"Clock"</th>
+                <th>"Moves"</th>
+                <th>"Watching"</th>
+            </tr>
+            {rows}
+        </table>
+    }
+}
+
+fn live_game_html(game: LiveGameSummary) -> String {
+    let rated = if game.rated { "Rated" } else { "Casual" };
+
+    html! {
+        <tr class="game" onclick={format!("location.href='/games/{}/live';", game.id)}>
+            <td>{game.white}</td>
+            <td>{game.black}</td>
+            <td>{game.time_control}" "{rated}</td>
+            <td>{game.ply.div_ceil(2)}</td>
+            <td>{game.spectators}</td>
+        </tr>
+    }
+}
@@ -1,5 +1,9 @@
 mod games_list;
+mod leaderboard;
+mod live_games_list;
 mod navbar;
 
 pub use games_list::*;
+pub use leaderboard::*;
+pub use live_games_list::*;
 pub use navbar::*;
@@ -1,27 +1,54 @@
-use crate::models::User;
+use crate::{
+    models::{User, UserRating},
+    rating,
+};
 use html_to_string_macro::html;
+use sqlx::{MySql, Pool};
+
+pub async fn navbar(pool: &Pool<MySql>, user: Option<User>) -> String {
+    let user = match user {
+        Some(user) => {
+            // The rating in the category the user plays the most
+            let rating = rating::user_ratings(pool, user.id)
+                .await
+                .ok()
+                .and_then(|ratings| ratings.into_iter().next());
+            logged_in(user, rating)
+        }
+        None => not_logged_in(),
+    };
 
-pub fn navbar(user: Option<User>) -> String {
     html!(
         <div class="navbar-wrapper">
             <div class="navbar">
-                <a href="/">"Home"</a>
+                <div class="links">
+                    <a href="/">"Home"</a>
+                    <a href="/lobby">"Play online"</a>
+                    <a href="/leaderboard">"Leaderboard"</a>
+                </div>
 
                 <div class="user">
-                    {match user {
-                        Some(user) => logged_in(user),
-                        None => not_logged_in(),
-                    }}
+                    {user}
                 </div>
             </div>
         </div>
     )
 }
 
-pub fn logged_in(user: User) -> String {
+pub fn logged_in(user: User, rating: Option<UserRating>) -> String {
+    let rating = match rating {
+        Some(r) => html!(
+            <span class="rating" title={format!("{} rating", r.category)}>
+                {rating::display(r.rating, r.deviation)}
+            </span>
+        ),
+        None => String::new(),
+    };
+
     html!(
         <a href="/logout" class="login">"Logout"</a>
         <a href={format!("/users/{}", &user.username)} class="profile">{&user.username}</a>
+        {rating}
     )
 }
 
@@ -0,0 +1,245 @@
+use crate::{
+    analysis::parse_moves,
+    live::GameEnd,
+    models::{CorrespondenceGame, CorrespondenceTurn},
+    router::AppState,
+    store::{self, NewGame},
+};
+use chess_lib::{game::Game, movegen::Move, piece::Color};
+use sqlx::{MySql, Pool};
+use std::time::Duration;
+
+// Correspondence games are played move by move over days. The moves so far
+// and the deadline of the side to move are kept in `correspondence_games`,
+// a task forfeits the games whose deadline passed. Finished games are
+// stored in `games` like any other.
+
+pub const DAYS_PER_MOVE: [u32; 4] = [1, 3, 7, 14];
+// How often deadlines are checked
+const TIMEOUT_INTERVAL: Duration = Duration::from_secs(60);
+
+impl CorrespondenceGame {
+    pub fn color_of(&self, user_id: i32) -> Option<Color> {
+        if self.white_player == user_id {
+            Some(Color::White)
+        } else if self.black_player == user_id {
+            Some(Color::Black)
+        } else {
+            None
+        }
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        if self.ply % 2 == 0 {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
+
+    pub fn game(&self) -> anyhow::Result<Game> {
+        Ok(Game::from_moves(parse_moves(self.moves.clone())?))
+    }
+}
+
+pub async fn create(
+    pool: &Pool<MySql>,
+    white: i32,
+    black: i32,
+    days_per_move: u32,
+    rated: bool,
+) -> sqlx::Result<i32> {
+    let moves = serde_json::Value::String("[]".to_string());
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO correspondence_games (white_player, black_player, days_per_move, rated, moves, deadline)
+        VALUES (?, ?, ?, ?, ?, NOW() + INTERVAL ? DAY)"#,
+        white,
+        black,
+        days_per_move,
+        rated,
+        moves,
+        days_per_move
+    )
+    .execute(pool)
+    .await?;
+    Ok(inserted.last_insert_id() as i32)
+}
+
+pub async fn load(pool: &Pool<MySql>, id: i32) -> sqlx::Result<Option<CorrespondenceGame>> {
+    sqlx::query_as!(
+        CorrespondenceGame,
+        r#"
+        SELECT c.id, c.white_player, c.black_player, white.username as white, black.username as black,
+            c.days_per_move, c.rated as "rated: bool", c.moves, c.ply, c.deadline, c.finished_at, c.game_id
+        FROM correspondence_games c
+        JOIN users white ON c.white_player = white.id
+        JOIN users black ON c.black_player = black.id
+        WHERE c.id = ?"#,
+        id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+// Games where it's the user's move, the most urgent first
+pub async fn your_turn(pool: &Pool<MySql>, user_id: i32) -> sqlx::Result<Vec<CorrespondenceTurn>> {
+    sqlx::query_as!(
+        CorrespondenceTurn,
+        r#"
+        SELECT c.id, IF(c.white_player = ?, black.username, white.username) as "opponent!", c.ply, c.deadline
+        FROM correspondence_games c
+        JOIN users white ON c.white_player = white.id
+        JOIN users black ON c.black_player = black.id
+        WHERE c.finished_at IS NULL
+            AND ((c.white_player = ? AND c.ply % 2 = 0) OR (c.black_player = ? AND c.ply % 2 = 1))
+        ORDER BY c.deadline"#,
+        user_id,
+        user_id,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+fn moves_json(moves: &[Move]) -> serde_json::Value {
+    serde_json::Value::String(serde_json::to_string(moves).unwrap())
+}
+
+// Saves the game after a move and restarts the clock. False if the game
+// changed since it was loaded.
+pub async fn save_move(
+    pool: &Pool<MySql>,
+    game: &CorrespondenceGame,
+    played: &Game,
+) -> sqlx::Result<bool> {
+    let updated = sqlx::query!(
+        r#"
+        UPDATE correspondence_games
+        SET moves = ?, ply = ply + 1, deadline = NOW() + INTERVAL days_per_move DAY
+        WHERE id = ? AND ply = ? AND finished_at IS NULL"#,
+        moves_json(&played.moves),
+        game.id,
+        game.ply
+    )
+    .execute(pool)
+    .await?;
+    Ok(updated.rows_affected() == 1)
+}
+
+// Ends the game with its final position `finished` and stores it in
+// `games`, all in one transaction. None if the game changed since it was
+// loaded.
+pub async fn finish(
+    state: &AppState,
+    game: &CorrespondenceGame,
+    finished: &Game,
+    end: GameEnd,
+) -> sqlx::Result<Option<i32>> {
+    let mut tx = state.pool.begin().await?;
+    let claimed = sqlx::query!(
+        r#"
+        UPDATE correspondence_games
+        SET moves = ?, ply = ?, finished_at = NOW()
+        WHERE id = ? AND ply = ? AND finished_at IS NULL"#,
+        moves_json(&finished.moves),
+        finished.moves.len() as i32,
+        game.id,
+        game.ply
+    )
+    .execute(&mut *tx)
+    .await?;
+    if claimed.rows_affected() != 1 {
+        return Ok(None);
+    }
+
+    let (winner, termination) = end.outcome(&finished.board);
+    let time_control = format!("{}d", game.days_per_move);
+    let new_game = NewGame {
+        white: Some(game.white_player),
+        black: Some(game.black_player),
+        bot_color: None,
+        difficulty: None,
+        rated: game.rated,
+        time_control: Some(&time_control),
+    };
+    let game_id = store::insert_finished(
+        &mut tx,
+        &new_game,
+        finished,
+        store::result(winner),
+        termination,
+    )
+    .await?;
+
+    sqlx::query!(
+        "UPDATE correspondence_games SET game_id = ? WHERE id = ?",
+        game_id,
+        game.id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    store::queue_analysis(state, game_id).await;
+    Ok(Some(game_id))
+}
+
+// Deletes a game without recording it. False if it changed since it was
+// loaded.
+pub async fn abort(pool: &Pool<MySql>, game: &CorrespondenceGame) -> sqlx::Result<bool> {
+    let deleted = sqlx::query!(
+        "DELETE FROM correspondence_games WHERE id = ? AND ply = ? AND finished_at IS NULL",
+        game.id,
+        game.ply
+    )
+    .execute(pool)
+    .await?;
+    Ok(deleted.rows_affected() == 1)
+}
+
+pub fn start_timeouts(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TIMEOUT_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = forfeit_expired(&state).await {
+                tracing::error!("Failed to end timed out correspondence games: {:#}", e);
+            }
+        }
+    });
+}
+
+async fn forfeit_expired(state: &AppState) -> anyhow::Result<()> {
+    let expired = sqlx::query_scalar!(
+        "SELECT id FROM correspondence_games WHERE finished_at IS NULL AND deadline < NOW()"
+    )
+    .fetch_all(&state.pool)
+    .await?;
+
+    for id in expired {
+        if let Err(e) = forfeit(state, id).await {
+            tracing::error!("Failed to end correspondence game {}: {:#}", id, e);
+        }
+    }
+    Ok(())
+}
+
+async fn forfeit(state: &AppState, id: i32) -> anyhow::Result<()> {
+    let Some(game) = load(&state.pool, id).await? else {
+        return Ok(());
+    };
+    // Games that never got going are aborted, a challenge left unanswered
+    // mustn't cost rating
+    if game.ply < 2 {
+        if abort(&state.pool, &game).await? {
+            tracing::info!("Correspondence game {} aborted after timing out", id);
+        }
+        return Ok(());
+    }
+    let end = GameEnd::Timeout(game.side_to_move());
+    if finish(state, &game, &game.game()?, end).await?.is_some() {
+        tracing::info!("Correspondence game {} timed out", id);
+    }
+    Ok(())
+}
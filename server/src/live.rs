@@ -0,0 +1,465 @@
+use crate::{
+    router::AppState,
+    store::{self, NewGame},
+};
+use chess_lib::{
+    board::Board,
+    game::{insufficient_material, Game},
+    movegen::{legal_moves, san::to_san},
+    piece::Color,
+    wasm::GameState,
+};
+use serde::Serialize;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::{broadcast, Notify};
+
+// Games between two users played over WebSockets. While a game is going on
+// its state lives here: moves are checked with chess-lib and every event is
+// broadcast to all connections of the game, the players' and any number of
+// spectators'. Players may drop and reconnect, each new connection gets the
+// whole game first. The game's row is created when it starts and completed
+// when it ends, or deleted if it ended before both players moved.
+
+// A player without any open connection for this long loses the game
+const ABANDON_AFTER: Duration = Duration::from_secs(60);
+// How often clocks and connections are checked
+const TICK: Duration = Duration::from_millis(250);
+const EVENT_BUFFER: usize = 64;
+
+pub struct Clock {
+    pub remaining: [Duration; 2],
+    pub increment: Duration,
+}
+
+impl Clock {
+    // `time` and `increment` in seconds, no clock for a `time` of 0
+    pub fn new(time: u64, increment: u64) -> Option<Self> {
+        (time > 0).then(|| Self {
+            remaining: [Duration::from_secs(time); 2],
+            increment: Duration::from_secs(increment),
+        })
+    }
+
+    // "minutes+increment", as in `TIME_CONTROLS`
+    pub fn parse(time_control: &str) -> Option<Self> {
+        let (minutes, increment) = time_control.split_once('+')?;
+        Self::new(minutes.parse::<u64>().ok()? * 60, increment.parse().ok()?)
+    }
+
+    // Charges a move that took `elapsed`, false if the flag fell
+    pub fn punch(&mut self, color: Color, elapsed: Duration) -> bool {
+        let remaining = &mut self.remaining[color as usize];
+        match remaining.checked_sub(elapsed) {
+            Some(left) => {
+                *remaining = left + self.increment;
+                true
+            }
+            None => {
+                *remaining = Duration::ZERO;
+                false
+            }
+        }
+    }
+}
+
+pub fn clock_ms(clock: &Option<Clock>, color: Color) -> Option<u64> {
+    clock
+        .as_ref()
+        .map(|c| c.remaining[color as usize].as_millis() as u64)
+}
+
+#[derive(Clone, Copy)]
+pub enum GameEnd {
+    State(GameState),
+    // The color that ran out of time, resigned or left
+    Timeout(Color),
+    Resignation(Color),
+    Abandoned(Color),
+}
+
+impl GameEnd {
+    // The winner and how the game ended, for the final position `board`
+    pub fn outcome(self, board: &Board) -> (Option<Color>, &'static str) {
+        match self {
+            GameEnd::State(GameState::Checkmate) => {
+                // The side to move is the one that got mated
+                (Some(board.side_to_move.opposite()), "Checkmate")
+            }
+            GameEnd::State(GameState::Stalemate) => (None, "Stalemate"),
+            GameEnd::State(GameState::DrawByRepetition) => (None, "Repetition"),
+            GameEnd::State(GameState::DrawByFiftyMoveRule) => (None, "Fifty move rule"),
+            GameEnd::State(GameState::DrawByInsufficientMaterial) => {
+                (None, "Insufficient material")
+            }
+            GameEnd::State(GameState::InProgress) => unreachable!("The game isn't over"),
+            // Running out of time only loses if the opponent could still mate
+            GameEnd::Timeout(side) if insufficient_material(board, side.opposite()) => {
+                (None, "Timeout vs insufficient material")
+            }
+            GameEnd::Timeout(side) => (Some(side.opposite()), "Timeout"),
+            GameEnd::Resignation(side) => (Some(side.opposite()), "Resignation"),
+            GameEnd::Abandoned(side) => (Some(side.opposite()), "Abandoned"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Player {
+    pub id: i32,
+    pub username: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GameEvent {
+    // The whole game, sent to every connection when it joins. `color` is
+    // the one the connection plays, none for spectators.
+    State {
+        white: String,
+        black: String,
+        color: Option<Color>,
+        moves: Vec<String>,
+        white_ms: Option<u64>,
+        black_ms: Option<u64>,
+        white_connected: bool,
+        black_connected: bool,
+        spectators: usize,
+    },
+    Move {
+        // Index of the move in the game, players get their own moves back
+        ply: usize,
+        #[serde(rename = "move")]
+        mv: String,
+        san: String,
+        white_ms: Option<u64>,
+        black_ms: Option<u64>,
+    },
+    Presence {
+        color: Color,
+        connected: bool,
+    },
+    Spectators {
+        count: usize,
+    },
+    GameOver {
+        result: &'static str,
+        reason: &'static str,
+        id: i32,
+    },
+    // The game ended before both players moved and isn't kept
+    Aborted,
+    // Only sent to the connection that caused it
+    Error {
+        message: String,
+    },
+}
+
+struct Session {
+    game: Game,
+    clock: Option<Clock>,
+    turn_start: Instant,
+    // Open connections of white and black, and since when a player had none
+    connections: [usize; 2],
+    gone_since: [Option<Instant>; 2],
+    spectators: usize,
+    end: Option<GameEnd>,
+}
+
+pub struct LiveGame {
+    pub id: i32,
+    pub white: Player,
+    pub black: Player,
+    pub rated: bool,
+    pub time_control: String,
+    session: Mutex<Session>,
+    events: broadcast::Sender<GameEvent>,
+    // Wakes the game's task up when it ended
+    ended: Notify,
+}
+
+impl LiveGame {
+    pub fn color_of(&self, user_id: i32) -> Option<Color> {
+        if self.white.id == user_id {
+            Some(Color::White)
+        } else if self.black.id == user_id {
+            Some(Color::Black)
+        } else {
+            None
+        }
+    }
+
+    // Registers a connection of `color`, or of a spectator, and returns the
+    // game so far with the events that follow it
+    pub fn join(&self, color: Option<Color>) -> (GameEvent, broadcast::Receiver<GameEvent>) {
+        let mut session = self.session.lock().unwrap();
+        match color {
+            Some(color) => {
+                session.connections[color as usize] += 1;
+                if session.gone_since[color as usize].take().is_some() {
+                    let _ = self.events.send(GameEvent::Presence {
+                        color,
+                        connected: true,
+                    });
+                }
+            }
+            None => {
+                session.spectators += 1;
+                let count = session.spectators;
+                let _ = self.events.send(GameEvent::Spectators { count });
+            }
+        }
+        (self.state(&session, color), self.events.subscribe())
+    }
+
+    pub fn leave(&self, color: Option<Color>) {
+        let mut session = self.session.lock().unwrap();
+        let Some(color) = color else {
+            session.spectators -= 1;
+            let count = session.spectators;
+            let _ = self.events.send(GameEvent::Spectators { count });
+            return;
+        };
+        session.connections[color as usize] -= 1;
+        if session.connections[color as usize] == 0 {
+            session.gone_since[color as usize] = Some(Instant::now());
+            let _ = self.events.send(GameEvent::Presence {
+                color,
+                connected: false,
+            });
+        }
+    }
+
+    pub fn summary(&self) -> LiveGameSummary {
+        let session = self.session.lock().unwrap();
+        LiveGameSummary {
+            id: self.id,
+            white: self.white.username.clone(),
+            black: self.black.username.clone(),
+            rated: self.rated,
+            time_control: self.time_control.clone(),
+            ply: session.game.moves.len(),
+            spectators: session.spectators,
+        }
+    }
+
+    // The game so far, for connections that missed some of it
+    pub fn snapshot(&self, color: Option<Color>) -> GameEvent {
+        self.state(&self.session.lock().unwrap(), color)
+    }
+
+    fn state(&self, session: &Session, color: Option<Color>) -> GameEvent {
+        // The side to move's clock has been running since its turn started
+        let clock = |side: Color| {
+            session.clock.as_ref().map(|c| {
+                let mut left = c.remaining[side as usize];
+                if side == session.game.board.side_to_move && session.end.is_none() {
+                    left = left.saturating_sub(session.turn_start.elapsed());
+                }
+                left.as_millis() as u64
+            })
+        };
+        GameEvent::State {
+            white: self.white.username.clone(),
+            black: self.black.username.clone(),
+            color,
+            moves: session.game.moves.iter().map(|m| m.to_string()).collect(),
+            white_ms: clock(Color::White),
+            black_ms: clock(Color::Black),
+            white_connected: session.connections[0] > 0,
+            black_connected: session.connections[1] > 0,
+            spectators: session.spectators,
+        }
+    }
+
+    // Plays `mv`, in UCI notation, for `color`
+    pub fn play(&self, color: Color, mv: &str) -> Result<(), String> {
+        let mut session = self.session.lock().unwrap();
+        if session.end.is_some() {
+            return Err("The game is over".to_string());
+        }
+        if session.game.board.side_to_move != color {
+            return Err("It's not your turn".to_string());
+        }
+        let legal = legal_moves(&session.game.board);
+        let Some(m) = legal.iter().find(|m| m.to_string() == mv) else {
+            return Err(format!("{} is not a legal move", mv));
+        };
+
+        let elapsed = session.turn_start.elapsed();
+        if let Some(clock) = &mut session.clock {
+            if !clock.punch(color, elapsed) {
+                self.end(&mut session, GameEnd::Timeout(color));
+                return Ok(());
+            }
+        }
+        let san = to_san(&session.game.board, m);
+        session.game.make_move(*m);
+        session.turn_start = Instant::now();
+
+        let _ = self.events.send(GameEvent::Move {
+            ply: session.game.moves.len() - 1,
+            mv: m.to_string(),
+            san,
+            white_ms: clock_ms(&session.clock, Color::White),
+            black_ms: clock_ms(&session.clock, Color::Black),
+        });
+        if session.game.game_state != GameState::InProgress {
+            let end = GameEnd::State(session.game.game_state);
+            self.end(&mut session, end);
+        }
+        Ok(())
+    }
+
+    pub fn resign(&self, color: Color) {
+        let mut session = self.session.lock().unwrap();
+        self.end(&mut session, GameEnd::Resignation(color));
+    }
+
+    fn end(&self, session: &mut Session, end: GameEnd) {
+        if session.end.is_none() {
+            session.end = Some(end);
+            self.ended.notify_one();
+        }
+    }
+
+    // Ends the game when the side to move's flag fell or a player has been
+    // gone for too long
+    fn check(&self) -> Option<GameEnd> {
+        let mut session = self.session.lock().unwrap();
+        if session.end.is_none() {
+            let side = session.game.board.side_to_move;
+            let elapsed = session.turn_start.elapsed();
+            if let Some(clock) = &session.clock {
+                if elapsed >= clock.remaining[side as usize] {
+                    self.end(&mut session, GameEnd::Timeout(side));
+                }
+            }
+            for color in [side, side.opposite()] {
+                if session.gone_since[color as usize].is_some_and(|t| t.elapsed() >= ABANDON_AFTER)
+                {
+                    self.end(&mut session, GameEnd::Abandoned(color));
+                }
+            }
+        }
+        session.end
+    }
+}
+
+// What the list of ongoing games shows of each
+#[derive(Serialize)]
+pub struct LiveGameSummary {
+    pub id: i32,
+    pub white: String,
+    pub black: String,
+    pub rated: bool,
+    pub time_control: String,
+    pub ply: usize,
+    pub spectators: usize,
+}
+
+#[derive(Clone, Default)]
+pub struct LiveGames {
+    games: Arc<Mutex<HashMap<i32, Arc<LiveGame>>>>,
+}
+
+impl LiveGames {
+    pub fn get(&self, id: i32) -> Option<Arc<LiveGame>> {
+        self.games.lock().unwrap().get(&id).cloned()
+    }
+
+    // The games going on, the newest first
+    pub fn list(&self) -> Vec<LiveGameSummary> {
+        let games: Vec<_> = self.games.lock().unwrap().values().cloned().collect();
+        let mut summaries: Vec<_> = games.iter().map(|g| g.summary()).collect();
+        summaries.sort_by_key(|s| Reverse(s.id));
+        summaries
+    }
+
+    // Creates the game's row and starts its clocks. Both players have
+    // `ABANDON_AFTER` to connect.
+    pub async fn start(
+        &self,
+        state: &AppState,
+        white: Player,
+        black: Player,
+        rated: bool,
+        time_control: &str,
+    ) -> sqlx::Result<Arc<LiveGame>> {
+        let id = store::create_game(
+            &state.pool,
+            &NewGame {
+                white: Some(white.id),
+                black: Some(black.id),
+                bot_color: None,
+                difficulty: None,
+                rated,
+                time_control: Some(time_control),
+            },
+        )
+        .await?;
+
+        let now = Instant::now();
+        let game = Arc::new(LiveGame {
+            id,
+            white,
+            black,
+            rated,
+            time_control: time_control.to_string(),
+            session: Mutex::new(Session {
+                game: Game::default(),
+                clock: Clock::parse(time_control),
+                turn_start: now,
+                connections: [0; 2],
+                gone_since: [Some(now); 2],
+                spectators: 0,
+                end: None,
+            }),
+            events: broadcast::channel(EVENT_BUFFER).0,
+            ended: Notify::new(),
+        });
+
+        self.games.lock().unwrap().insert(id, game.clone());
+        tokio::spawn(run(state.clone(), game.clone()));
+        Ok(game)
+    }
+}
+
+// Waits for the game to end, then saves it
+async fn run(state: AppState, game: Arc<LiveGame>) {
+    let end = loop {
+        if let Some(end) = game.check() {
+            break end;
+        }
+        tokio::select! {
+            _ = game.ended.notified() => {}
+            _ = tokio::time::sleep(TICK) => {}
+        }
+    };
+
+    let finished = Game::from_moves(game.session.lock().unwrap().game.moves.clone());
+    let event = if finished.moves.len() < 2 {
+        if let Err(e) = store::delete_game(&state.pool, game.id).await {
+            tracing::error!("Failed to delete aborted live game {}: {}", game.id, e);
+        }
+        GameEvent::Aborted
+    } else {
+        let (winner, reason) = end.outcome(&finished.board);
+        let result = store::result(winner);
+        if let Err(e) = store::finish_game(&state, game.id, &finished, result, reason).await {
+            tracing::error!("Failed to save live game {}: {}", game.id, e);
+        }
+        GameEvent::GameOver {
+            result,
+            reason,
+            id: game.id,
+        }
+    };
+
+    state.live.games.lock().unwrap().remove(&game.id);
+    let _ = game.events.send(event);
+}
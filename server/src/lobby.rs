@@ -0,0 +1,146 @@
+use crate::live::Player;
+use chess_lib::piece::Color;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc};
+
+// Open challenges and the matchmaking queue. Both belong to a lobby
+// connection and are withdrawn when it closes, so nobody gets paired with
+// a player that already left. Pairing players only takes them out of the
+// lobby, starting their game is up to the caller.
+
+#[derive(Clone, Serialize)]
+pub struct Challenge {
+    pub id: u64,
+    pub username: String,
+    pub time_control: String,
+    // The creator's color, none for a random one
+    pub color: Option<Color>,
+    pub rated: bool,
+}
+
+// A player waiting in the lobby, `notify` gets the id of their game once
+// they're paired
+pub struct Waiting {
+    pub conn: u64,
+    pub player: Player,
+    pub notify: mpsc::UnboundedSender<i32>,
+}
+
+#[derive(Default)]
+struct LobbyState {
+    next_id: u64,
+    challenges: Vec<(Challenge, Waiting)>,
+    // Players looking for any opponent with the same time control and
+    // rated flag
+    queue: Vec<(String, bool, Waiting)>,
+}
+
+#[derive(Clone)]
+pub struct Lobby {
+    state: Arc<Mutex<LobbyState>>,
+    // Sent whenever the open challenges changed
+    changed: broadcast::Sender<()>,
+}
+
+impl Default for Lobby {
+    fn default() -> Self {
+        Self {
+            state: Default::default(),
+            changed: broadcast::channel(16).0,
+        }
+    }
+}
+
+impl Lobby {
+    // A new id for a lobby connection
+    pub fn connect(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        state.next_id
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<()> {
+        self.changed.subscribe()
+    }
+
+    pub fn challenges(&self) -> Vec<Challenge> {
+        let state = self.state.lock().unwrap();
+        state.challenges.iter().map(|(c, _)| c.clone()).collect()
+    }
+
+    // Opens a challenge, replacing the ones already open on the connection
+    pub fn create(
+        &self,
+        waiting: Waiting,
+        time_control: String,
+        color: Option<Color>,
+        rated: bool,
+    ) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.challenges.retain(|(_, w)| w.conn != waiting.conn);
+        state.next_id += 1;
+        let id = state.next_id;
+        let challenge = Challenge {
+            id,
+            username: waiting.player.username.clone(),
+            time_control,
+            color,
+            rated,
+        };
+        state.challenges.push((challenge, waiting));
+        drop(state);
+        let _ = self.changed.send(());
+        id
+    }
+
+    // Takes the challenge `id` out of the lobby for `player` to accept
+    pub fn accept(&self, id: u64, player: &Player) -> Result<(Challenge, Waiting), &'static str> {
+        let mut state = self.state.lock().unwrap();
+        let Some(i) = state.challenges.iter().position(|(c, _)| c.id == id) else {
+            return Err("The challenge is gone");
+        };
+        if state.challenges[i].1.player.id == player.id {
+            return Err("You can't accept your own challenge");
+        }
+        let accepted = state.challenges.remove(i);
+        drop(state);
+        let _ = self.changed.send(());
+        Ok(accepted)
+    }
+
+    // Pairs `waiting` with someone already queued for the same kind of
+    // game, or queues them
+    pub fn queue(
+        &self,
+        waiting: Waiting,
+        time_control: String,
+        rated: bool,
+    ) -> Option<(Waiting, Waiting)> {
+        let mut state = self.state.lock().unwrap();
+        state.queue.retain(|(_, _, w)| w.conn != waiting.conn);
+        let opponent = state.queue.iter().position(|(tc, r, w)| {
+            *tc == time_control && *r == rated && w.player.id != waiting.player.id
+        });
+        match opponent {
+            Some(i) => Some((state.queue.remove(i).2, waiting)),
+            None => {
+                state.queue.push((time_control, rated, waiting));
+                None
+            }
+        }
+    }
+
+    // Withdraws everything the connection has open
+    pub fn leave(&self, conn: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.queue.retain(|(_, _, w)| w.conn != conn);
+        let open = state.challenges.len();
+        state.challenges.retain(|(_, w)| w.conn != conn);
+        let changed = state.challenges.len() != open;
+        drop(state);
+        if changed {
+            let _ = self.changed.send(());
+        }
+    }
+}
@@ -1,10 +1,20 @@
+use analysis::AnalysisQueue;
+use live::LiveGames;
+use lobby::Lobby;
 use router::AppState;
 use sqlx::mysql::MySqlPoolOptions;
 
+mod analysis;
 mod auth;
 mod components;
+mod correspondence;
+mod live;
+mod lobby;
 mod models;
+mod rating;
 mod router;
+mod stats;
+mod store;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -16,8 +26,20 @@ async fn main() -> anyhow::Result<()> {
         .connect(&std::env::var("DATABASE_URL")?)
         .await?;
     sqlx::migrate!().run(&db).await?;
+    let deleted = store::delete_unfinished(&db).await?;
+    if deleted > 0 {
+        tracing::info!("Deleted {} live games interrupted by a restart", deleted);
+    }
 
-    let app = router::app(AppState { pool: db });
+    let analysis = AnalysisQueue::start(db.clone());
+    let state = AppState {
+        pool: db,
+        analysis,
+        live: LiveGames::default(),
+        lobby: Lobby::default(),
+    };
+    correspondence::start_timeouts(state.clone());
+    let app = router::app(state);
 
     let listener = tokio::net::TcpListener::bind("[::]:8080").await?;
     axum::serve(listener, app).await?;
@@ -10,7 +10,89 @@ pub struct User {
 pub struct Game {
     pub id: i32,
     pub played_at: time::OffsetDateTime,
-    pub player: Option<String>,
+    // Usernames, none for guests and the bot
+    pub white: Option<String>,
+    pub black: Option<String>,
+    pub bot_color: Option<String>,
     pub result: String,
     pub difficulty: Option<String>,
 }
+
+#[derive(Debug)]
+pub struct AnalysisJob {
+    pub status: String,
+    pub white_accuracy: Option<f64>,
+    pub black_accuracy: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct AnalysisPly {
+    pub ply: i32,
+    pub played: String,
+    pub best_move: Option<String>,
+    pub score: Option<i32>,
+    pub mate: Option<i32>,
+    pub class: String,
+    pub cp_loss: i32,
+}
+
+#[derive(Debug)]
+pub struct CorrespondenceGame {
+    pub id: i32,
+    pub white_player: i32,
+    pub black_player: i32,
+    pub white: String,
+    pub black: String,
+    pub days_per_move: i32,
+    pub rated: bool,
+    pub moves: serde_json::Value,
+    pub ply: i32,
+    pub deadline: time::OffsetDateTime,
+    pub finished_at: Option<time::OffsetDateTime>,
+    pub game_id: Option<i32>,
+}
+
+// A correspondence game waiting for a move of the user
+#[derive(Debug)]
+pub struct CorrespondenceTurn {
+    pub id: i32,
+    pub opponent: String,
+    pub ply: i32,
+    pub deadline: time::OffsetDateTime,
+}
+
+#[derive(Debug)]
+pub struct UserRating {
+    pub category: String,
+    pub rating: f64,
+    pub deviation: f64,
+    pub games: i32,
+}
+
+#[derive(Debug)]
+pub struct LeaderboardEntry {
+    pub username: String,
+    pub rating: f64,
+    pub deviation: f64,
+    pub games: i32,
+}
+
+#[derive(Debug)]
+pub struct RatingPoint {
+    pub category: String,
+    pub rating: f64,
+    pub recorded_at: time::OffsetDateTime,
+}
+
+// A finished game of a user, for their statistics
+#[derive(Debug)]
+pub struct StatsGame {
+    pub id: i32,
+    pub played_at: time::OffsetDateTime,
+    pub result: String,
+    pub termination: Option<String>,
+    pub difficulty: Option<String>,
+    pub moves: serde_json::Value,
+    // The user's
+    pub color: String,
+}
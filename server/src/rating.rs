@@ -0,0 +1,369 @@
+use crate::models::{LeaderboardEntry, RatingPoint, UserRating};
+use sqlx::{MySql, Pool, Transaction};
+use std::f64::consts::PI;
+
+// Glicko-2 ratings, one per user and category of time control. Every rated
+// game is a rating period of its own: both players are updated as soon as
+// it ends, each from the other's rating before the game. The bot is rated
+// at the level it plays, so games against it can be rated too, but its
+// rating never changes.
+
+pub const CATEGORIES: [&str; 5] = ["bullet", "blitz", "rapid", "classical", "correspondence"];
+
+const DEFAULT_RATING: f64 = 1500.0;
+const DEFAULT_DEVIATION: f64 = 350.0;
+const DEFAULT_VOLATILITY: f64 = 0.06;
+// Keeps ratings of players with many games from freezing
+const MIN_DEVIATION: f64 = 45.0;
+const BOT_DEVIATION: f64 = 60.0;
+// Ratings less certain than this are shown as provisional and left off the
+// leaderboards
+pub const PROVISIONAL_DEVIATION: f64 = 110.0;
+// How much the volatility may change
+const TAU: f64 = 0.5;
+// Between the Glicko and Glicko-2 scales
+const SCALE: f64 = 173.7178;
+const EPSILON: f64 = 0.000001;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self {
+            rating: DEFAULT_RATING,
+            deviation: DEFAULT_DEVIATION,
+            volatility: DEFAULT_VOLATILITY,
+        }
+    }
+}
+
+fn g(phi: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * phi * phi / (PI * PI)).sqrt()
+}
+
+impl Rating {
+    // The rating after scoring `score` against `opponent`: 1 for a win,
+    // 0.5 for a draw and 0 for a loss
+    pub fn update(self, opponent: Rating, score: f64) -> Rating {
+        self.update_period(&[(opponent, score)])
+    }
+
+    // The rating after a rating period with `games`, each an opponent and
+    // the score against them
+    fn update_period(self, games: &[(Rating, f64)]) -> Rating {
+        let mu = (self.rating - DEFAULT_RATING) / SCALE;
+        let phi = self.deviation / SCALE;
+
+        // Sums over the games of the estimated variance's inverse and of
+        // the rating improvement
+        let mut v_inv = 0.0;
+        let mut improvement = 0.0;
+        for (opponent, score) in games {
+            let mu_j = (opponent.rating - DEFAULT_RATING) / SCALE;
+            let g_j = g(opponent.deviation / SCALE);
+            let expected = 1.0 / (1.0 + (-g_j * (mu - mu_j)).exp());
+            v_inv += g_j * g_j * expected * (1.0 - expected);
+            improvement += g_j * (score - expected);
+        }
+        let v = 1.0 / v_inv;
+        let delta = v * improvement;
+
+        // New volatility, found with the Illinois algorithm
+        let a = (self.volatility * self.volatility).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            let d = phi * phi + v + ex;
+            ex * (delta * delta - phi * phi - v - ex) / (2.0 * d * d) - (x - a) / (TAU * TAU)
+        };
+        let mut big_a = a;
+        let mut big_b = if delta * delta > phi * phi + v {
+            (delta * delta - phi * phi - v).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * TAU) < 0.0 {
+                k += 1.0;
+            }
+            a - k * TAU
+        };
+        let mut f_a = f(big_a);
+        let mut f_b = f(big_b);
+        while (big_b - big_a).abs() > EPSILON {
+            let c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+            let f_c = f(c);
+            if f_c * f_b <= 0.0 {
+                big_a = big_b;
+                f_a = f_b;
+            } else {
+                f_a /= 2.0;
+            }
+            big_b = c;
+            f_b = f_c;
+        }
+        let volatility = (big_a / 2.0).exp();
+
+        let phi_star = (phi * phi + volatility * volatility).sqrt();
+        let new_phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+        let new_mu = mu + new_phi * new_phi * improvement;
+
+        Rating {
+            rating: new_mu * SCALE + DEFAULT_RATING,
+            deviation: (new_phi * SCALE).clamp(MIN_DEVIATION, DEFAULT_DEVIATION),
+            volatility,
+        }
+    }
+}
+
+// The category of a time control, by the game's estimated length:
+// minutes + 40 moves of increment. Games without a clock are classical.
+pub fn category(time_control: Option<&str>) -> &'static str {
+    let Some(time_control) = time_control else {
+        return "classical";
+    };
+    if time_control.ends_with('d') {
+        return "correspondence";
+    }
+    let estimate = time_control
+        .split_once('+')
+        .and_then(|(minutes, increment)| {
+            Some(minutes.parse::<u64>().ok()? * 60 + increment.parse::<u64>().ok()? * 40)
+        })
+        .unwrap_or(u64::MAX);
+    match estimate {
+        s if s < 180 => "bullet",
+        s if s < 480 => "blitz",
+        s if s < 1500 => "rapid",
+        _ => "classical",
+    }
+}
+
+// The bot at a difficulty stored with a game, "1200 Elo"
+fn bot_rating(difficulty: &str) -> Option<Rating> {
+    let elo = difficulty.strip_suffix(" Elo")?.parse::<f64>().ok()?;
+    Some(Rating {
+        rating: elo,
+        deviation: BOT_DEVIATION,
+        volatility: DEFAULT_VOLATILITY,
+    })
+}
+
+// "1523", or "1500?" while the rating is provisional
+pub fn display(rating: f64, deviation: f64) -> String {
+    if deviation > PROVISIONAL_DEVIATION {
+        format!("{:.0}?", rating)
+    } else {
+        format!("{:.0}", rating)
+    }
+}
+
+// Updates the ratings of the players of the finished game `game_id`, if it
+// was rated. Part of the transaction that finishes the game.
+pub async fn rate_game(tx: &mut Transaction<'_, MySql>, game_id: i32) -> sqlx::Result<()> {
+    let game = sqlx::query!(
+        r#"
+        SELECT white_player, black_player, bot_color, difficulty, rated as "rated: bool", time_control, result
+        FROM games
+        WHERE id = ?"#,
+        game_id
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    if !game.rated {
+        return Ok(());
+    }
+    let white_score = match game.result.as_str() {
+        "1-0" => 1.0,
+        "0-1" => 0.0,
+        "1/2-1/2" => 0.5,
+        _ => return Ok(()),
+    };
+    let category = category(game.time_control.as_deref());
+
+    // The user ids, none for the bot, with the ratings before the game
+    let mut sides = Vec::with_capacity(2);
+    for (player, color) in [(game.white_player, "White"), (game.black_player, "Black")] {
+        let side = match player {
+            Some(id) => (Some(id), load(tx, id, category).await?),
+            None if game.bot_color.as_deref() == Some(color) => {
+                match game.difficulty.as_deref().and_then(bot_rating) {
+                    Some(rating) => (None, rating),
+                    None => return Ok(()),
+                }
+            }
+            // Guests can't play rated games
+            None => return Ok(()),
+        };
+        sides.push(side);
+    }
+
+    let scores = [white_score, 1.0 - white_score];
+    for (i, (player, rating)) in sides.iter().enumerate() {
+        if let Some(user_id) = player {
+            let new = rating.update(sides[1 - i].1, scores[i]);
+            save(tx, *user_id, category, game_id, new).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn load(
+    tx: &mut Transaction<'_, MySql>,
+    user_id: i32,
+    category: &str,
+) -> sqlx::Result<Rating> {
+    let rating = sqlx::query_as!(
+        Rating,
+        r#"
+        SELECT rating, deviation, volatility
+        FROM ratings
+        WHERE user_id = ? AND category = ?
+        FOR UPDATE"#,
+        user_id,
+        category
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    Ok(rating.unwrap_or_default())
+}
+
+async fn save(
+    tx: &mut Transaction<'_, MySql>,
+    user_id: i32,
+    category: &str,
+    game_id: i32,
+    rating: Rating,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO ratings (user_id, category, rating, deviation, volatility, games)
+        VALUES (?, ?, ?, ?, ?, 1)
+        ON DUPLICATE KEY UPDATE
+            rating = VALUES(rating),
+            deviation = VALUES(deviation),
+            volatility = VALUES(volatility),
+            games = games + 1"#,
+        user_id,
+        category,
+        rating.rating,
+        rating.deviation,
+        rating.volatility
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO rating_history (user_id, category, game_id, rating, deviation)
+        VALUES (?, ?, ?, ?, ?)"#,
+        user_id,
+        category,
+        game_id,
+        rating.rating,
+        rating.deviation
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+// The user's ratings in the categories they played, the most played first
+pub async fn user_ratings(pool: &Pool<MySql>, user_id: i32) -> sqlx::Result<Vec<UserRating>> {
+    sqlx::query_as!(
+        UserRating,
+        r#"
+        SELECT category, rating, deviation, games
+        FROM ratings
+        WHERE user_id = ?
+        ORDER BY games DESC, category"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// The best established ratings in `category`, `limit` of them from `offset`
+pub async fn leaderboard(
+    pool: &Pool<MySql>,
+    category: &str,
+    limit: i64,
+    offset: i64,
+) -> sqlx::Result<Vec<LeaderboardEntry>> {
+    sqlx::query_as!(
+        LeaderboardEntry,
+        r#"
+        SELECT users.username, ratings.rating, ratings.deviation, ratings.games
+        FROM ratings
+        JOIN users ON ratings.user_id = users.id
+        WHERE ratings.category = ? AND ratings.deviation <= ?
+        ORDER BY ratings.rating DESC, users.username
+        LIMIT ?
+        OFFSET ?"#,
+        category,
+        PROVISIONAL_DEVIATION,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// Every rating the user had, oldest first
+pub async fn history(pool: &Pool<MySql>, user_id: i32) -> sqlx::Result<Vec<RatingPoint>> {
+    sqlx::query_as!(
+        RatingPoint,
+        r#"
+        SELECT category, rating, recorded_at
+        FROM rating_history
+        WHERE user_id = ?
+        ORDER BY recorded_at, id"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(rating: f64, deviation: f64) -> Rating {
+        Rating {
+            rating,
+            deviation,
+            volatility: DEFAULT_VOLATILITY,
+        }
+    }
+
+    // The worked example of Glickman's "Example of the Glicko-2 system"
+    #[test]
+    fn glickman_example() {
+        let games = [
+            (rating(1400.0, 30.0), 1.0),
+            (rating(1550.0, 100.0), 0.0),
+            (rating(1700.0, 300.0), 0.0),
+        ];
+        let new = rating(1500.0, 200.0).update_period(&games);
+        assert!((new.rating - 1464.06).abs() < 0.01, "{:?}", new);
+        assert!((new.deviation - 151.52).abs() < 0.01, "{:?}", new);
+        assert!((new.volatility - 0.05999).abs() < 0.00001, "{:?}", new);
+    }
+
+    #[test]
+    fn winner_gains_and_loser_loses() {
+        let (a, b) = (Rating::default(), rating(1600.0, 80.0));
+        let (new_a, new_b) = (a.update(b, 1.0), b.update(a, 0.0));
+        assert!(new_a.rating > a.rating);
+        assert!(new_b.rating < b.rating);
+        // The new player moves much further
+        assert!(new_a.rating - a.rating > b.rating - new_b.rating);
+        assert!(new_a.deviation < a.deviation);
+
+        // A draw between equals changes neither rating
+        let drawn = a.update(a, 0.5);
+        assert!((drawn.rating - a.rating).abs() < 1e-9);
+    }
+}
@@ -1,8 +1,11 @@
 use super::*;
 use crate::{
-    auth::get_user_token,
-    components::game_html,
-    models::{Game, User},
+    analysis::parse_moves,
+    auth::get_user,
+    components::{game_html, live_games_list},
+    live::GameEnd,
+    models::{AnalysisJob, AnalysisPly, Game, User},
+    store::{self, NewGame},
 };
 use axum::{
     extract::{Path, Query, State},
@@ -10,13 +13,79 @@ use axum::{
     Json,
 };
 use axum_extra::headers::Cookie;
+use chess_lib::{game::Game as ChessGame, piece::Color, wasm::GameState};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/submit_game", post(submit_game))
         .route("/all_games", get(all_games))
+        .route("/live_games", get(live_games))
+        .route("/leaderboard", get(leaderboard::page))
         .route("/user_games", get(user_games))
         .route("/game_moves/:id", get(game_moves))
+        .route("/games/:id/analysis", get(game_analysis))
+        .route("/correspondence/:id", get(correspondence::game_state))
+        .route("/correspondence/:id/move", post(correspondence::play))
+        .route("/correspondence/:id/resign", post(correspondence::resign))
+        .route("/correspondence/:id/abort", post(correspondence::abort))
+}
+
+async fn game_analysis(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let result = sqlx::query_scalar!("SELECT result FROM games WHERE id = ?", id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    // Games are analyzed once they are over
+    if result == store::ONGOING {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let job = sqlx::query_as!(
+        AnalysisJob,
+        "SELECT status, white_accuracy, black_accuracy FROM analysis_jobs WHERE game_id = ?",
+        id
+    )
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let Some(job) = job else {
+        // Games from before the analysis worker get queued when first asked for
+        state
+            .analysis
+            .enqueue(&state.pool, id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        return Ok(Json(serde_json::json!({ "status": "pending" })));
+    };
+
+    if job.status != "done" {
+        return Ok(Json(serde_json::json!({ "status": job.status })));
+    }
+
+    let plies = sqlx::query_as!(
+        AnalysisPly,
+        r#"
+        SELECT ply, played, best_move, score, mate, class, cp_loss
+        FROM game_analysis
+        WHERE game_id = ?
+        ORDER BY ply"#,
+        id
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(serde_json::json!({
+        "status": job.status,
+        "white_accuracy": job.white_accuracy,
+        "black_accuracy": job.black_accuracy,
+        "plies": plies,
+    })))
 }
 
 async fn game_moves(
@@ -49,28 +118,25 @@ async fn user_games(
     if page < 1 {
         return Err(StatusCode::BAD_REQUEST);
     }
-    let games = sqlx::query!(
+    let games = sqlx::query_as!(
+        Game,
         r#"
-        SELECT games.id, games.played_at, games.result, games.difficulty FROM games 
-        WHERE player = ? 
+        SELECT games.id, games.played_at, games.result, games.difficulty, games.bot_color,
+            white.username as "white?", black.username as "black?"
+        FROM games
+        LEFT JOIN users white ON games.white_player = white.id
+        LEFT JOIN users black ON games.black_player = black.id
+        WHERE (games.white_player = ? OR games.black_player = ?) AND games.result != '*'
         ORDER BY games.played_at DESC
         LIMIT 10
         OFFSET ?"#,
         user.id,
+        user.id,
         (page - 1) * 10
     )
     .fetch_all(&state.pool)
     .await
-    .unwrap()
-    .into_iter()
-    .map(|row| crate::models::Game {
-        id: row.id,
-        played_at: row.played_at,
-        player: Some(user.username.clone()),
-        result: row.result,
-        difficulty: row.difficulty,
-    })
-    .collect::<Vec<_>>();
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     let next_page = if games.len() < 10 {
         "".to_string()
@@ -96,16 +162,19 @@ struct AllGamesQuery {
 async fn all_games(
     State(state): State<AppState>,
     Query(AllGamesQuery { page }): Query<AllGamesQuery>,
-) -> impl IntoResponse {
+) -> Result<Html<String>, StatusCode> {
     if page < 1 {
-        return StatusCode::BAD_REQUEST.into_response();
+        return Err(StatusCode::BAD_REQUEST);
     }
     let games = sqlx::query_as!(
         Game,
         r#"
-        SELECT games.id, games.played_at, games.result, games.difficulty, users.username as "player?"
+        SELECT games.id, games.played_at, games.result, games.difficulty, games.bot_color,
+            white.username as "white?", black.username as "black?"
         FROM games
-        LEFT JOIN users ON games.player = users.id
+        LEFT JOIN users white ON games.white_player = white.id
+        LEFT JOIN users black ON games.black_player = black.id
+        WHERE games.result != '*'
         ORDER BY games.played_at DESC
         LIMIT 10
         OFFSET ?"#,
@@ -113,7 +182,7 @@ async fn all_games(
     )
     .fetch_all(&state.pool)
     .await
-    .unwrap()
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     .into_iter()
     .map(|g| g.into())
     .collect::<Vec<Game>>();
@@ -128,57 +197,100 @@ async fn all_games(
         }
     };
 
-    Html(html!(
+    Ok(Html(html!(
         { games.into_iter().map(game_html).collect::<String>() }
         { next_page }
-    ))
-    .into_response()
+    )))
+}
+
+async fn live_games(State(state): State<AppState>) -> impl IntoResponse {
+    Html(live_games_list(state.live.list()))
 }
 
 async fn submit_game(
     State(state): State<AppState>,
     cookies: TypedHeader<Cookie>,
     Json(data): Json<GameDataJson>,
-) {
-    let user = get_user_token(cookies);
-    tracing::error!("Submitting game: {:?}", data);
-    let difficulty = match data.difficulty {
-        0 => "Easy",
-        1 => "Medium",
-        2 => "Hard",
-        _ => "-",
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let user = get_user(&state.pool, cookies).await.map(|u| u.id);
+    let game = validate_game(&data)?;
+    let difficulty = match (data.elo, data.difficulty) {
+        (Some(elo), _) => format!("{} Elo", elo),
+        (None, Some(0)) => "Easy".to_string(),
+        (None, Some(1)) => "Medium".to_string(),
+        (None, Some(2)) => "Hard".to_string(),
+        _ => "-".to_string(),
     };
 
-    match user {
-        Some(id) => {
-            sqlx::query!(
-                "INSERT INTO games (player, moves, result, difficulty) VALUES (?, ?, ?, ?)",
-                id,
-                data.moves,
-                data.result,
-                difficulty
-            )
-            .execute(&state.pool)
-            .await
-            .unwrap();
+    let (white, black) = match data.color {
+        Color::White => (user, None),
+        Color::Black => (None, user),
+    };
+    let new_game = NewGame {
+        white,
+        black,
+        bot_color: Some(data.color.opposite()),
+        difficulty: Some(&difficulty),
+        rated: false,
+        time_control: None,
+    };
+    let (winner, termination) = GameEnd::State(game.game_state).outcome(&game.board);
+    let game_id = store::save_game(&state, &new_game, &game, store::result(winner), termination)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(
+        serde_json::json!({ "id": game_id, "fen": game.fen() }),
+    ))
+}
+
+// Replays the submitted moves and checks the game really ended with the
+// claimed result, from the player's view
+fn validate_game(data: &GameDataJson) -> Result<ChessGame, (StatusCode, String)> {
+    let moves = parse_moves(data.moves.clone())
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid moves: {}", e)))?;
+    let game = ChessGame::from_legal_moves(&moves).map_err(|i| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Move {} ({}) is illegal", i + 1, moves[i]),
+        )
+    })?;
+
+    let result = match game.game_state {
+        GameState::InProgress => {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "The game isn't over".to_string(),
+            ))
         }
-        None => {
-            sqlx::query!(
-                "INSERT INTO games (moves, result, difficulty) VALUES (?, ?, ?)",
-                data.moves,
-                data.result,
-                difficulty
-            )
-            .execute(&state.pool)
-            .await
-            .unwrap();
+        GameState::Checkmate => {
+            // The side to move is the one that got mated
+            if game.board.side_to_move == data.color {
+                "Loss"
+            } else {
+                "Win"
+            }
         }
+        _ => "Draw",
+    };
+
+    if data.result != result {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("The game ended in a {}, not a {}", result, data.result),
+        ));
     }
+    Ok(game)
 }
 
 #[derive(serde::Deserialize, Debug)]
 pub struct GameDataJson {
     moves: serde_json::Value,
     result: String,
-    difficulty: i32,
+    // The player's color, the bot has the other one. Needed for every
+    // game, draws are stored by color too.
+    color: Color,
+    // Sent by clients from before bot levels
+    difficulty: Option<i32>,
+    elo: Option<u32>,
 }
@@ -0,0 +1,294 @@
+use super::{
+    new_game::TIME_CONTROLS,
+    ws::{receive, send},
+    *,
+};
+use crate::{
+    auth::get_user,
+    live::{clock_ms, Clock, GameEnd},
+    store::{self, NewGame},
+};
+use axum::extract::{
+    ws::{WebSocket, WebSocketUpgrade},
+    State,
+};
+use chess_lib::{
+    book::Book,
+    bot::Bot,
+    game::Game,
+    movegen::{legal_moves, san::to_san},
+    piece::Color,
+    strength::{pick_move, Strength, MAX_ELO, MIN_ELO},
+    wasm::GameState,
+};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+// Bot games played on the server: the client only sends its moves, the
+// server checks them, answers with the bot's moves, keeps the clocks and
+// saves the game once it's over.
+//
+// The first message starts the game, `time` and `increment` are in seconds
+// and have to be one of `TIME_CONTROLS`, or both 0 to play without clocks.
+// Only logged in players can play rated games:
+//   {"type": "start", "elo": 1200, "color": "White", "time": 300, "increment": 3, "rated": true}
+// After that the client sends moves in UCI notation, or resigns:
+//   {"type": "move", "move": "e2e4"}
+//   {"type": "resign"}
+
+const TT_ENTRIES: usize = 1 << 20;
+// Share of the remaining time the bot spends on a move
+const TIME_DIVISOR: u32 = 30;
+const MIN_MOVE_TIME: Duration = Duration::from_millis(50);
+
+lazy_static! {
+    static ref BOOK: Book = Book::builtin();
+    // Bot searches running at once, more games wait for a free thread
+    static ref SEARCHES: Semaphore = Semaphore::new(
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    );
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Start {
+        elo: u32,
+        color: Color,
+        time: u64,
+        increment: u64,
+        #[serde(default)]
+        rated: bool,
+    },
+    Move {
+        #[serde(rename = "move")]
+        mv: String,
+    },
+    Resign,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    // A move of the bot
+    Move {
+        #[serde(rename = "move")]
+        mv: String,
+        san: String,
+        white_ms: Option<u64>,
+        black_ms: Option<u64>,
+    },
+    // Sent after each accepted move of the player
+    Clock {
+        white_ms: Option<u64>,
+        black_ms: Option<u64>,
+    },
+    Error {
+        message: String,
+    },
+    GameOver {
+        // From the player's view
+        result: &'static str,
+        reason: String,
+        id: Option<i32>,
+    },
+}
+
+fn bot_move_time(clock: &Clock, color: Color) -> Duration {
+    (clock.remaining[color as usize] / TIME_DIVISOR + clock.increment / 2).max(MIN_MOVE_TIME)
+}
+
+pub async fn ws(
+    State(state): State<AppState>,
+    cookies: TypedHeader<headers::Cookie>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let user = get_user(&state.pool, cookies).await.map(|u| u.id);
+    ws.on_upgrade(move |socket| async move {
+        if let Err(e) = session(state, user, socket).await {
+            tracing::error!("Bot game failed: {:#}", e);
+        }
+    })
+}
+
+async fn session(state: AppState, user: Option<i32>, mut socket: WebSocket) -> anyhow::Result<()> {
+    let Some(ClientMessage::Start {
+        elo,
+        color,
+        time,
+        increment,
+        rated,
+    }) = receive(&mut socket).await?
+    else {
+        let message = "Expected a start message".to_string();
+        return send(&mut socket, &ServerMessage::Error { message }).await;
+    };
+
+    // Ratings assume the bot plays at its level, which it can't on odd clocks
+    let time_control = format!("{}+{}", time / 60, increment);
+    let untimed = time == 0 && increment == 0;
+    if !untimed && (time % 60 != 0 || !TIME_CONTROLS.contains(&time_control.as_str())) {
+        let message = "Unsupported time control".to_string();
+        return send(&mut socket, &ServerMessage::Error { message }).await;
+    }
+    if !(MIN_ELO..=MAX_ELO).contains(&elo) {
+        let message = "Unsupported bot level".to_string();
+        return send(&mut socket, &ServerMessage::Error { message }).await;
+    }
+
+    let strength = Strength::new(elo);
+    let bot_color = color.opposite();
+    let mut game = Game::default();
+    let mut bot = Some(Bot::with_limits(TT_ENTRIES, strength.limits()));
+    let mut clock = Clock::new(time, increment);
+    let mut turn_start = Instant::now();
+
+    let end = loop {
+        if game.game_state != GameState::InProgress {
+            break GameEnd::State(game.game_state);
+        }
+
+        if game.board.side_to_move == bot_color {
+            let mut b = bot.take().unwrap();
+            b.limits = strength.limits();
+            if let Some(clock) = &clock {
+                let limit = bot_move_time(clock, bot_color).as_millis() as u64;
+                b.limits.movetime = Some(b.limits.movetime.unwrap_or(limit).min(limit));
+            }
+
+            let board = game.board.clone();
+            let ply = game.moves.len();
+            let permit = SEARCHES.acquire().await?;
+            let (b, mv) = tokio::task::spawn_blocking(move || {
+                let mut rng = rand::thread_rng();
+                let mv = if ply < strength.book_plies() {
+                    BOOK.pick(&board, ply, &mut rng)
+                } else {
+                    None
+                };
+                let mv = mv.or_else(|| pick_move(&mut b, board, strength, &mut rng));
+                (b, mv)
+            })
+            .await?;
+            drop(permit);
+            bot = Some(b);
+
+            let mv = mv.expect("Bot asked to move without legal moves");
+            if let Some(clock) = &mut clock {
+                if !clock.punch(bot_color, turn_start.elapsed()) {
+                    break GameEnd::Timeout(bot_color);
+                }
+            }
+            let san = to_san(&game.board, &mv);
+            game.make_move(mv);
+            turn_start = Instant::now();
+
+            let (white_ms, black_ms) = (
+                clock_ms(&clock, Color::White),
+                clock_ms(&clock, Color::Black),
+            );
+            let sent = send(
+                &mut socket,
+                &ServerMessage::Move {
+                    mv: mv.to_string(),
+                    san,
+                    white_ms,
+                    black_ms,
+                },
+            )
+            .await;
+            // The player left while the bot was thinking
+            if sent.is_err() {
+                if game.moves.len() < 2 {
+                    return Ok(());
+                }
+                break match game.game_state {
+                    GameState::InProgress => GameEnd::Resignation(color),
+                    state => GameEnd::State(state),
+                };
+            }
+            continue;
+        }
+
+        // Broken connections count as leaving, so losing games can't be
+        // escaped by cutting them
+        let msg = match &clock {
+            Some(clock) => {
+                let left = clock.remaining[color as usize].saturating_sub(turn_start.elapsed());
+                match tokio::time::timeout(left, receive(&mut socket)).await {
+                    Ok(msg) => msg.unwrap_or(None),
+                    Err(_) => break GameEnd::Timeout(color),
+                }
+            }
+            None => receive(&mut socket).await.unwrap_or(None),
+        };
+
+        match msg {
+            // Games left before the player moved aren't kept
+            None if game.moves.len() < 2 => return Ok(()),
+            None | Some(ClientMessage::Resign) => break GameEnd::Resignation(color),
+            Some(ClientMessage::Start { .. }) => {
+                let message = "The game has already started".to_string();
+                let _ = send(&mut socket, &ServerMessage::Error { message }).await;
+            }
+            Some(ClientMessage::Move { mv }) => {
+                let legal = legal_moves(&game.board);
+                let Some(m) = legal.iter().find(|m| m.to_string() == mv) else {
+                    let message = format!("{} is not a legal move", mv);
+                    let _ = send(&mut socket, &ServerMessage::Error { message }).await;
+                    continue;
+                };
+
+                if let Some(clock) = &mut clock {
+                    if !clock.punch(color, turn_start.elapsed()) {
+                        break GameEnd::Timeout(color);
+                    }
+                }
+                game.make_move(*m);
+                turn_start = Instant::now();
+
+                let (white_ms, black_ms) = (
+                    clock_ms(&clock, Color::White),
+                    clock_ms(&clock, Color::Black),
+                );
+                let _ = send(&mut socket, &ServerMessage::Clock { white_ms, black_ms }).await;
+            }
+        }
+    };
+
+    let (winner, termination) = end.outcome(&game.board);
+    let result = match winner {
+        Some(winner) if winner == color => "Win",
+        Some(_) => "Loss",
+        None => "Draw",
+    };
+
+    let difficulty = format!("{} Elo", strength.elo());
+    let (white, black) = match color {
+        Color::White => (user, None),
+        Color::Black => (None, user),
+    };
+    let new_game = NewGame {
+        white,
+        black,
+        bot_color: Some(bot_color),
+        difficulty: Some(&difficulty),
+        rated: rated && user.is_some(),
+        time_control: (!untimed).then_some(time_control.as_str()),
+    };
+    let id = match store::save_game(&state, &new_game, &game, store::result(winner), termination)
+        .await
+    {
+        Ok(id) => Some(id),
+        Err(e) => {
+            tracing::error!("Failed to save bot game: {}", e);
+            None
+        }
+    };
+    // The player may be gone already
+    let reason = termination.to_string();
+    let _ = send(&mut socket, &ServerMessage::GameOver { result, reason, id }).await;
+    Ok(())
+}
@@ -0,0 +1,258 @@
+use super::*;
+use crate::{
+    auth::get_user,
+    components::navbar,
+    correspondence::{self, DAYS_PER_MOVE},
+    live::GameEnd,
+    models::{CorrespondenceGame, User},
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Response,
+    Form, Json,
+};
+use chess_lib::{movegen::legal_moves, piece::Color, wasm::GameState};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+
+#[derive(Deserialize)]
+pub struct NewGameForm {
+    opponent: String,
+    days: u32,
+    // The challenger's color: white, black or random
+    color: String,
+    rated: Option<String>,
+}
+
+pub async fn post(
+    State(state): State<AppState>,
+    cookies: TypedHeader<headers::Cookie>,
+    Form(data): Form<NewGameForm>,
+) -> Result<Redirect, (StatusCode, &'static str)> {
+    let user = get_user(&state.pool, cookies)
+        .await
+        .ok_or((StatusCode::UNAUTHORIZED, "Log in to play"))?;
+    let opponent = sqlx::query_as!(
+        User,
+        "SELECT * FROM users WHERE username = ?",
+        data.opponent
+    )
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|_| (StatusCode::NOT_FOUND, "User not found"))?;
+
+    if opponent.id == user.id {
+        return Err((StatusCode::BAD_REQUEST, "You can't play yourself"));
+    }
+    if !DAYS_PER_MOVE.contains(&data.days) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid days per move"));
+    }
+    let white = match data.color.as_str() {
+        "white" => true,
+        "black" => false,
+        "random" => rand::thread_rng().gen(),
+        _ => return Err((StatusCode::BAD_REQUEST, "Invalid color")),
+    };
+    let (white, black) = if white {
+        (user.id, opponent.id)
+    } else {
+        (opponent.id, user.id)
+    };
+
+    let id = correspondence::create(&state.pool, white, black, data.days, data.rated.is_some())
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to create the game",
+            )
+        })?;
+    Ok(Redirect::to(&format!("/correspondence/{}", id)))
+}
+
+pub async fn get(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    cookies: TypedHeader<headers::Cookie>,
+) -> Result<Response, StatusCode> {
+    let game = correspondence::load(&state.pool, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if let Some(game_id) = game.game_id {
+        return Ok(Redirect::to(&format!("/games/{}", game_id)).into_response());
+    }
+
+    Ok(Html(render_index(html! (
+        {navbar(&state.pool, get_user(&state.pool, cookies).await).await}
+        <game-el correspondence_id={id}></game-el>
+    )))
+    .into_response())
+}
+
+// The game as sent to clients, `color` is the one the user plays
+#[derive(Serialize)]
+pub struct CorrespondenceJson {
+    white: String,
+    black: String,
+    color: Option<Color>,
+    moves: Vec<String>,
+    days_per_move: i32,
+    deadline: String,
+    // The game in `games` once it's over
+    game_id: Option<i32>,
+}
+
+type ApiError = (StatusCode, String);
+
+fn server_error(e: impl std::fmt::Display) -> ApiError {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+async fn load(state: &AppState, id: i32) -> Result<CorrespondenceGame, ApiError> {
+    correspondence::load(&state.pool, id)
+        .await
+        .map_err(server_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Game not found".to_string()))
+}
+
+fn to_json(game: &CorrespondenceGame, user: Option<&User>) -> Result<CorrespondenceJson, ApiError> {
+    Ok(CorrespondenceJson {
+        white: game.white.clone(),
+        black: game.black.clone(),
+        color: user.and_then(|u| game.color_of(u.id)),
+        moves: game
+            .game()
+            .map_err(server_error)?
+            .moves
+            .iter()
+            .map(|m| m.to_string())
+            .collect(),
+        days_per_move: game.days_per_move,
+        deadline: game.deadline.format(&Rfc3339).map_err(server_error)?,
+        game_id: game.game_id,
+    })
+}
+
+// The game and the color of the user, who has to play in it and can still
+// act on it
+async fn load_own(
+    state: &AppState,
+    id: i32,
+    cookies: TypedHeader<headers::Cookie>,
+) -> Result<(CorrespondenceGame, User, Color), ApiError> {
+    let user = get_user(&state.pool, cookies)
+        .await
+        .ok_or((StatusCode::UNAUTHORIZED, "Log in to play".to_string()))?;
+    let game = load(state, id).await?;
+    let color = game.color_of(user.id).ok_or((
+        StatusCode::FORBIDDEN,
+        "You don't play in this game".to_string(),
+    ))?;
+    if game.finished_at.is_some() {
+        return Err((StatusCode::CONFLICT, "The game is over".to_string()));
+    }
+    Ok((game, user, color))
+}
+
+fn changed() -> ApiError {
+    (
+        StatusCode::CONFLICT,
+        "The game changed in the meantime, reload it".to_string(),
+    )
+}
+
+pub async fn game_state(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    cookies: TypedHeader<headers::Cookie>,
+) -> Result<Json<CorrespondenceJson>, ApiError> {
+    let user = get_user(&state.pool, cookies).await;
+    let game = load(&state, id).await?;
+    Ok(Json(to_json(&game, user.as_ref())?))
+}
+
+#[derive(Deserialize)]
+pub struct MoveJson {
+    #[serde(rename = "move")]
+    mv: String,
+}
+
+pub async fn play(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    cookies: TypedHeader<headers::Cookie>,
+    Json(MoveJson { mv }): Json<MoveJson>,
+) -> Result<Json<CorrespondenceJson>, ApiError> {
+    let (game, user, color) = load_own(&state, id, cookies).await?;
+    if game.side_to_move() != color {
+        return Err((StatusCode::CONFLICT, "It's not your turn".to_string()));
+    }
+
+    let mut played = game.game().map_err(server_error)?;
+    let legal = legal_moves(&played.board);
+    let Some(m) = legal.iter().find(|m| m.to_string() == mv) else {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("{} is not a legal move", mv),
+        ));
+    };
+    played.make_move(*m);
+
+    let saved = if played.game_state == GameState::InProgress {
+        correspondence::save_move(&state.pool, &game, &played).await
+    } else {
+        let end = GameEnd::State(played.game_state);
+        correspondence::finish(&state, &game, &played, end)
+            .await
+            .map(|id| id.is_some())
+    };
+    if !saved.map_err(server_error)? {
+        return Err(changed());
+    }
+
+    let game = load(&state, id).await?;
+    Ok(Json(to_json(&game, Some(&user))?))
+}
+
+pub async fn resign(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    cookies: TypedHeader<headers::Cookie>,
+) -> Result<Json<CorrespondenceJson>, ApiError> {
+    let (game, user, color) = load_own(&state, id, cookies).await?;
+    let current = game.game().map_err(server_error)?;
+    correspondence::finish(&state, &game, &current, GameEnd::Resignation(color))
+        .await
+        .map_err(server_error)?
+        .ok_or_else(changed)?;
+
+    let game = load(&state, id).await?;
+    Ok(Json(to_json(&game, Some(&user))?))
+}
+
+// Either player can call the game off before both of them moved, nothing
+// is recorded then
+pub async fn abort(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    cookies: TypedHeader<headers::Cookie>,
+) -> Result<StatusCode, ApiError> {
+    let (game, _, _) = load_own(&state, id, cookies).await?;
+    if game.ply >= 2 {
+        return Err((
+            StatusCode::CONFLICT,
+            "Games can only be aborted before both players moved".to_string(),
+        ));
+    }
+
+    if !correspondence::abort(&state.pool, &game)
+        .await
+        .map_err(server_error)?
+    {
+        return Err(changed());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
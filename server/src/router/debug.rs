@@ -9,7 +9,7 @@ pub async fn get(
     let user = get_user(&state.pool, TypedHeader(cookies)).await;
 
     Html(render_index(html! (
-        {navbar(user)}
+        {navbar(&state.pool, user).await}
         <debug-el></debug-el>
     )))
 }
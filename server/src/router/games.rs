@@ -13,9 +13,12 @@ pub async fn get(
     let games = sqlx::query_as!(
         Game,
         r#"
-        SELECT games.id, games.played_at, games.result, games.difficulty, users.username as "player?"
+        SELECT games.id, games.played_at, games.result, games.difficulty, games.bot_color,
+            white.username as "white?", black.username as "black?"
         FROM games
-        LEFT JOIN users ON games.player = users.id
+        LEFT JOIN users white ON games.white_player = white.id
+        LEFT JOIN users black ON games.black_player = black.id
+        WHERE games.result != '*'
         ORDER BY games.played_at DESC
         LIMIT 10
         "#,
@@ -28,7 +31,7 @@ pub async fn get(
     .collect();
 
     Html(render_index(html! (
-        {navbar(get_user(&state.pool, cookies).await)}
+        {navbar(&state.pool, get_user(&state.pool, cookies).await).await}
         <div class="content">
             <h1>"All games played"</h1>
             {games_list(games, Some("/api/all_games?page=2"))}
@@ -41,8 +44,29 @@ pub async fn get_game(
     Path(id): Path<i32>,
     cookies: TypedHeader<headers::Cookie>,
 ) -> impl IntoResponse {
+    // Games still being played are followed live
+    if state.live.get(id).is_some() {
+        return Redirect::to(&format!("/games/{}/live", id)).into_response();
+    }
     Html(render_index(html! (
-        {navbar(get_user(&state.pool, cookies).await)}
+        {navbar(&state.pool, get_user(&state.pool, cookies).await).await}
         <game-el game_id={id}></game-el>
     )))
+    .into_response()
+}
+
+// A game being played, for its players and anyone watching
+pub async fn get_live(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    cookies: TypedHeader<headers::Cookie>,
+) -> impl IntoResponse {
+    if state.live.get(id).is_none() {
+        return Redirect::to(&format!("/games/{}", id)).into_response();
+    }
+    Html(render_index(html! (
+        {navbar(&state.pool, get_user(&state.pool, cookies).await).await}
+        <game-el live_game_id={id}></game-el>
+    )))
+    .into_response()
 }
@@ -1,10 +1,14 @@
-use super::*;
+use super::{
+    new_game::{DEFAULT_BOT_ELO, TIME_CONTROLS},
+    *,
+};
 use crate::{
     auth::get_user,
-    components::{games_list, navbar},
+    components::{games_list, live_games_list, navbar},
     models::Game,
 };
 use axum::extract::State;
+use chess_lib::strength::{MAX_ELO, MIN_ELO};
 
 pub async fn get(
     State(state): State<AppState>,
@@ -16,12 +20,15 @@ pub async fn get(
     } else {
         "Play as guest"
     };
+    let logged_in = user.is_some();
 
     Html(render_index(html! (
-        {navbar(user)}
+        {navbar(&state.pool, user).await}
         <div class="content">
-            {new_game(button_text)}
+            {new_game(button_text, logged_in)}
             <hr style="width: 80%; margin: 40px 0px;" />
+            <h2>"Playing now"</h2>
+            {live_games_list(state.live.list())}
             <h2>"Last 10 games"</h2>
             {last_games(&state).await}
             <a href="/games" style="margin-top: 20px;" class="login">"All games"</a>
@@ -29,7 +36,19 @@ pub async fn get(
     )))
 }
 
-fn new_game(button_text: &str) -> String {
+fn new_game(button_text: &str, logged_in: bool) -> String {
+    // Games in the browser aren't rated, they could be left unfinished
+    let rated = if logged_in {
+        html!(
+            <label style="margin: 0">
+                <input type="checkbox" name="rated" />
+                " Rated"
+            </label>
+        )
+    } else {
+        String::new()
+    };
+
     html!(
         <form action="/new-game" class="gameopts">
             <button class="newgame" type="submit">{button_text}</button>
@@ -42,17 +61,40 @@ fn new_game(button_text: &str) -> String {
                 </div>
             </div>
             <div class="divopt">
-                <p style="margin: 0">"Difficulty:"</p>
-                <select name="difficulty" class="difficulty">
-                    <option value="0">"Easy"</option>
-                    <option value="1">"Medium"</option>
-                    <option value="2">"Hard"</option>
+                <p style="margin: 0">"Bot level:"</p>
+                <select name="elo" class="difficulty">
+                    {level_options()}
                 </select>
             </div>
+            <div class="divopt">
+                <label style="margin: 0">
+                    <input type="checkbox" name="server" />
+                    " Play on the server, clock:"
+                </label>
+                <select name="time_control" class="difficulty">
+                    <option value="">"None"</option>
+                    {TIME_CONTROLS.iter().map(|tc| html!(<option value=tc>{tc}</option>)).collect::<String>()}
+                </select>
+                {rated}
+            </div>
         </form>
     )
 }
 
+fn level_options() -> String {
+    (MIN_ELO..=MAX_ELO)
+        .step_by(200)
+        .map(|elo| {
+            let selected = if elo == DEFAULT_BOT_ELO {
+                "selected"
+            } else {
+                ""
+            };
+            html!(<option value=elo {selected}>{elo}" Elo"</option>)
+        })
+        .collect()
+}
+
 fn color_select(color: &str, checked: &str) -> String {
     html!(
         <div class="tooltip">
@@ -69,9 +111,12 @@ async fn last_games(state: &AppState) -> String {
     let games = sqlx::query_as!(
         Game,
         r#"
-        SELECT games.id, games.played_at, games.result, games.difficulty, users.username as "player?"
+        SELECT games.id, games.played_at, games.result, games.difficulty, games.bot_color,
+            white.username as "white?", black.username as "black?"
         FROM games
-        LEFT JOIN users ON games.player = users.id
+        LEFT JOIN users white ON games.white_player = white.id
+        LEFT JOIN users black ON games.black_player = black.id
+        WHERE games.result != '*'
         ORDER BY games.played_at DESC
         LIMIT 10
         "#
@@ -0,0 +1,92 @@
+use super::*;
+use crate::{
+    auth::get_user,
+    components::{leaderboard_rows, navbar},
+    rating::{self, CATEGORIES},
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+
+const PAGE_SIZE: i64 = 20;
+
+pub async fn index() -> Redirect {
+    Redirect::to("/leaderboard/blitz")
+}
+
+pub async fn get(
+    State(state): State<AppState>,
+    Path(category): Path<String>,
+    cookies: TypedHeader<headers::Cookie>,
+) -> Result<Html<String>, StatusCode> {
+    if !CATEGORIES.contains(&category.as_str()) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let mut rows = rows(&state, &category, 1).await?;
+    if rows.is_empty() {
+        rows = html!(
+            <tr>
+                <td colspan="4">"Nobody has an established rating yet"</td>
+            </tr>
+        );
+    }
+
+    let tabs = CATEGORIES
+        .iter()
+        .map(|c| {
+            let class = if *c == category {
+                "tab selected"
+            } else {
+                "tab"
+            };
+            html!(<a class={class} href={format!("/leaderboard/{}", c)}>{c}</a>)
+        })
+        .collect::<String>();
+
+    Ok(Html(render_index(html! (
+        {navbar(&state.pool, get_user(&state.pool, cookies).await).await}
+        <div class="content">
+            <h1>"Leaderboard"</h1>
+            <div class="tabs">{tabs}</div>
+            <table class="games">
+                <tr class="games-header">
+                    <th style="padding: 5px 0px;">"Rank"</th>
+                    <th>"Player"</th>
+                    <th>"Rating"</th>
+                    <th>"Games"</th>
+                </tr>
+                {rows}
+            </table>
+        </div>
+    ))))
+}
+
+#[derive(Deserialize)]
+pub struct PageQuery {
+    category: String,
+    page: i64,
+}
+
+// The rows of the next page, for infinite scrolling
+pub async fn page(
+    State(state): State<AppState>,
+    Query(PageQuery { category, page }): Query<PageQuery>,
+) -> Result<Html<String>, StatusCode> {
+    if page < 1 || !CATEGORIES.contains(&category.as_str()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(Html(rows(&state, &category, page).await?))
+}
+
+async fn rows(state: &AppState, category: &str, page: i64) -> Result<String, StatusCode> {
+    let offset = (page - 1) * PAGE_SIZE;
+    let entries = rating::leaderboard(&state.pool, category, PAGE_SIZE, offset)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let next_page_url = (entries.len() as i64 == PAGE_SIZE)
+        .then(|| format!("/api/leaderboard?category={}&page={}", category, page + 1));
+    Ok(leaderboard_rows(entries, offset + 1, next_page_url))
+}
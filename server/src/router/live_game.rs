@@ -0,0 +1,105 @@
+use super::{
+    ws::{receive, send},
+    *,
+};
+use crate::{
+    auth::get_user,
+    live::{GameEvent, LiveGame},
+};
+use axum::{
+    extract::{
+        ws::{WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    http::StatusCode,
+};
+use chess_lib::piece::Color;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+// The channel of a live game. Both players and anyone watching connect to
+// it, first get the game so far and then every event. Players send their
+// moves in UCI notation, or resign:
+//   {"type": "move", "move": "e2e4"}
+//   {"type": "resign"}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Move {
+        #[serde(rename = "move")]
+        mv: String,
+    },
+    Resign,
+}
+
+pub async fn ws(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    cookies: TypedHeader<headers::Cookie>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let Some(game) = state.live.get(id) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let color = get_user(&state.pool, cookies)
+        .await
+        .and_then(|u| game.color_of(u.id));
+
+    ws.on_upgrade(move |socket| async move {
+        let (game_so_far, events) = game.join(color);
+        let result = session(&game, color, socket, game_so_far, events).await;
+        game.leave(color);
+        if let Err(e) = result {
+            tracing::error!("Live game {} connection failed: {:#}", game.id, e);
+        }
+    })
+    .into_response()
+}
+
+async fn session(
+    game: &Arc<LiveGame>,
+    color: Option<Color>,
+    mut socket: WebSocket,
+    game_so_far: GameEvent,
+    mut events: Receiver<GameEvent>,
+) -> anyhow::Result<()> {
+    send(&mut socket, &game_so_far).await?;
+
+    loop {
+        tokio::select! {
+            msg = receive::<ClientMessage>(&mut socket) => {
+                let Some(msg) = msg? else {
+                    return Ok(());
+                };
+                let Some(color) = color else {
+                    let message = "Spectators can't play".to_string();
+                    send(&mut socket, &GameEvent::Error { message }).await?;
+                    continue;
+                };
+                let played = match msg {
+                    ClientMessage::Move { mv } => game.play(color, &mv),
+                    ClientMessage::Resign => {
+                        game.resign(color);
+                        Ok(())
+                    }
+                };
+                if let Err(message) = played {
+                    send(&mut socket, &GameEvent::Error { message }).await?;
+                }
+            }
+            event = events.recv() => match event {
+                Ok(event) => {
+                    send(&mut socket, &event).await?;
+                    if matches!(event, GameEvent::GameOver { .. } | GameEvent::Aborted) {
+                        return Ok(());
+                    }
+                }
+                // Too far behind, start over from the whole game
+                Err(RecvError::Lagged(_)) => send(&mut socket, &game.snapshot(color)).await?,
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
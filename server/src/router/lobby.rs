@@ -0,0 +1,221 @@
+use super::{
+    new_game::TIME_CONTROLS,
+    ws::{receive, send},
+    *,
+};
+use crate::{
+    auth::get_user,
+    components::navbar,
+    live::Player,
+    lobby::{Challenge, Waiting},
+};
+use axum::extract::{
+    ws::{WebSocket, WebSocketUpgrade},
+    State,
+};
+use chess_lib::piece::Color;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+
+// The lobby page keeps a WebSocket open to see the open challenges as they
+// change. Logged in users can also open a challenge, accept one or look
+// for an opponent, and are sent the id of their game once paired:
+//   {"type": "create", "time_control": "5+3", "color": "White", "rated": true}
+//   {"type": "accept", "id": 4}
+//   {"type": "queue", "time_control": "5+3", "rated": false}
+//   {"type": "cancel"}
+// A missing color in a challenge picks a random one.
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Create {
+        time_control: String,
+        color: Option<Color>,
+        rated: bool,
+    },
+    Accept {
+        id: u64,
+    },
+    Queue {
+        time_control: String,
+        rated: bool,
+    },
+    Cancel,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    Challenges { challenges: Vec<Challenge> },
+    Matched { game_id: i32 },
+    Error { message: String },
+}
+
+pub async fn get(
+    State(state): State<AppState>,
+    cookies: TypedHeader<headers::Cookie>,
+) -> impl IntoResponse {
+    let user = get_user(&state.pool, cookies).await;
+    let username = user
+        .as_ref()
+        .map(|u| u.username.clone())
+        .unwrap_or_default();
+
+    Html(render_index(html! (
+        {navbar(&state.pool, user).await}
+        <div class="content">
+            <h1>"Play online"</h1>
+            <lobby-el username={username} time_controls={TIME_CONTROLS.join(",")}></lobby-el>
+        </div>
+    )))
+}
+
+pub async fn ws(
+    State(state): State<AppState>,
+    cookies: TypedHeader<headers::Cookie>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let player = get_user(&state.pool, cookies).await.map(|u| Player {
+        id: u.id,
+        username: u.username,
+    });
+    ws.on_upgrade(move |socket| async move {
+        let conn = state.lobby.connect();
+        let result = session(&state, conn, player, socket).await;
+        state.lobby.leave(conn);
+        if let Err(e) = result {
+            tracing::error!("Lobby connection failed: {:#}", e);
+        }
+    })
+}
+
+async fn session(
+    state: &AppState,
+    conn: u64,
+    player: Option<Player>,
+    mut socket: WebSocket,
+) -> anyhow::Result<()> {
+    let mut changes = state.lobby.subscribe();
+    let (notify, mut matched) = mpsc::unbounded_channel();
+    let challenges = state.lobby.challenges();
+    send(&mut socket, &ServerMessage::Challenges { challenges }).await?;
+
+    loop {
+        tokio::select! {
+            msg = receive::<ClientMessage>(&mut socket) => {
+                let Some(msg) = msg? else {
+                    return Ok(());
+                };
+                let Some(player) = &player else {
+                    let message = "Log in to play online".to_string();
+                    send(&mut socket, &ServerMessage::Error { message }).await?;
+                    continue;
+                };
+                let waiting = Waiting {
+                    conn,
+                    player: player.clone(),
+                    notify: notify.clone(),
+                };
+                if let Err(message) = handle(state, waiting, msg).await {
+                    send(&mut socket, &ServerMessage::Error { message }).await?;
+                }
+            }
+            changed = changes.recv() => match changed {
+                Ok(()) | Err(RecvError::Lagged(_)) => {
+                    let challenges = state.lobby.challenges();
+                    send(&mut socket, &ServerMessage::Challenges { challenges }).await?;
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            Some(game_id) = matched.recv() => {
+                send(&mut socket, &ServerMessage::Matched { game_id }).await?;
+            }
+        }
+    }
+}
+
+async fn handle(state: &AppState, waiting: Waiting, msg: ClientMessage) -> Result<(), String> {
+    match msg {
+        ClientMessage::Create {
+            time_control,
+            color,
+            rated,
+        } => {
+            check_time_control(&time_control)?;
+            state.lobby.create(waiting, time_control, color, rated);
+        }
+        ClientMessage::Accept { id } => {
+            let (challenge, creator) = state.lobby.accept(id, &waiting.player)?;
+            let color = challenge.color.unwrap_or_else(random_color);
+            let (white, black) = match color {
+                Color::White => (creator, waiting),
+                Color::Black => (waiting, creator),
+            };
+            start(
+                state,
+                white,
+                black,
+                challenge.rated,
+                &challenge.time_control,
+            )
+            .await?;
+        }
+        ClientMessage::Queue {
+            time_control,
+            rated,
+        } => {
+            check_time_control(&time_control)?;
+            if let Some((a, b)) = state.lobby.queue(waiting, time_control.clone(), rated) {
+                let (white, black) = match random_color() {
+                    Color::White => (a, b),
+                    Color::Black => (b, a),
+                };
+                start(state, white, black, rated, &time_control).await?;
+            }
+        }
+        ClientMessage::Cancel => state.lobby.leave(waiting.conn),
+    }
+    Ok(())
+}
+
+fn check_time_control(time_control: &str) -> Result<(), String> {
+    if TIME_CONTROLS.contains(&time_control) {
+        Ok(())
+    } else {
+        Err(format!("Unknown time control {}", time_control))
+    }
+}
+
+fn random_color() -> Color {
+    if rand::thread_rng().gen() {
+        Color::White
+    } else {
+        Color::Black
+    }
+}
+
+async fn start(
+    state: &AppState,
+    white: Waiting,
+    black: Waiting,
+    rated: bool,
+    time_control: &str,
+) -> Result<(), String> {
+    // Whatever else the players have open in the lobby is withdrawn, they
+    // can't be matched twice
+    state.lobby.leave(white.conn);
+    state.lobby.leave(black.conn);
+    let game = state
+        .live
+        .start(state, white.player, black.player, rated, time_control)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to start a live game: {}", e);
+            "The game couldn't be started".to_string()
+        })?;
+    let _ = white.notify.send(game.id);
+    let _ = black.notify.send(game.id);
+    Ok(())
+}
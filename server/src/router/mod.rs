@@ -1,3 +1,4 @@
+use crate::{analysis::AnalysisQueue, live::LiveGames, lobby::Lobby};
 use axum::{
     http::header::SET_COOKIE,
     response::{Html, IntoResponse, Redirect},
@@ -10,23 +11,35 @@ use sqlx::{MySql, Pool};
 use tower_http::{services::ServeDir, trace::TraceLayer};
 
 mod api;
+mod bot_game;
+mod correspondence;
 mod debug;
 mod games;
 mod index;
+mod leaderboard;
+mod live_game;
+mod lobby;
 mod login;
 mod new_game;
 mod register;
 mod user;
+mod ws;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: Pool<MySql>,
+    pub analysis: AnalysisQueue,
+    pub live: LiveGames,
+    pub lobby: Lobby,
 }
 
 pub fn app(state: AppState) -> Router {
     Router::new()
         .route("/", get(index::get))
         .route("/new-game", get(new_game::get))
+        .route("/bot-game/ws", get(bot_game::ws))
+        .route("/lobby", get(lobby::get))
+        .route("/lobby/ws", get(lobby::ws))
         .route("/debug", get(debug::get))
         .route("/register", get(register::get))
         .route("/register", post(register::post))
@@ -34,8 +47,16 @@ pub fn app(state: AppState) -> Router {
         .route("/login", post(login::post))
         .route("/logout", get(logout))
         .route("/users/:username", get(user::get))
+        .route("/users/:username/rating_history", get(user::rating_history))
+        .route("/users/:username/stats", get(user::stats_json))
+        .route("/leaderboard", get(leaderboard::index))
+        .route("/leaderboard/:category", get(leaderboard::get))
         .route("/games", get(games::get))
         .route("/games/:id", get(games::get_game))
+        .route("/games/:id/live", get(games::get_live))
+        .route("/games/:id/ws", get(live_game::ws))
+        .route("/correspondence", post(correspondence::post))
+        .route("/correspondence/:id", get(correspondence::get))
         .nest("/api", api::router())
         .with_state(state)
         .nest_service(
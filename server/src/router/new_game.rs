@@ -4,13 +4,37 @@ use axum::{
     extract::{Query, State},
     http::StatusCode,
 };
+use chess_lib::{
+    strength::{MAX_ELO, MIN_ELO},
+    wasm::Difficulty,
+};
 use rand::Rng;
 use serde::Deserialize;
 
+pub const DEFAULT_BOT_ELO: u32 = 1200;
+// Minutes and increment in seconds, for games played on the server
+pub const TIME_CONTROLS: [&str; 4] = ["3+2", "5+3", "10+5", "15+10"];
+
 #[derive(Deserialize)]
 pub struct Options {
     color: String,
-    difficulty: u8,
+    // Old links pick one of three presets instead of a level
+    difficulty: Option<u8>,
+    elo: Option<u32>,
+    // Set to play against the bot on the server instead of in the browser
+    server: Option<String>,
+    time_control: Option<String>,
+    // Only for games on the server
+    rated: Option<String>,
+}
+
+fn preset(difficulty: u8) -> Option<Difficulty> {
+    match difficulty {
+        0 => Some(Difficulty::Easy),
+        1 => Some(Difficulty::Medium),
+        2 => Some(Difficulty::Hard),
+        _ => None,
+    }
 }
 
 pub async fn get(
@@ -18,6 +42,10 @@ pub async fn get(
     Query(Options {
         mut color,
         difficulty,
+        elo,
+        server,
+        time_control,
+        rated,
     }): Query<Options>,
     TypedHeader(cookies): TypedHeader<headers::Cookie>,
 ) -> impl IntoResponse {
@@ -33,13 +61,35 @@ pub async fn get(
     } else if color != "white" && color != "black" {
         return StatusCode::BAD_REQUEST.into_response();
     }
-    if difficulty > 2 {
+    let elo = match (elo, difficulty) {
+        (Some(elo), _) => elo,
+        (None, Some(difficulty)) => match preset(difficulty) {
+            Some(difficulty) => difficulty.elo(),
+            None => return StatusCode::BAD_REQUEST.into_response(),
+        },
+        (None, None) => DEFAULT_BOT_ELO,
+    };
+    if !(MIN_ELO..=MAX_ELO).contains(&elo) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    let time_control = time_control.unwrap_or_default();
+    if !time_control.is_empty() && !TIME_CONTROLS.contains(&time_control.as_str()) {
         return StatusCode::BAD_REQUEST.into_response();
     }
 
+    let game = if server.is_some() {
+        let rated = if rated.is_some() && user.is_some() {
+            "rated"
+        } else {
+            ""
+        };
+        html!(<game-el player_color=color elo={elo} server_hosted="true" time_control={time_control} {rated}></game-el>)
+    } else {
+        html!(<game-el player_color=color elo={elo}></game-el>)
+    };
     Html(render_index(html! (
-        {navbar(user)}
-        <game-el player_color=color difficulty={difficulty}></game-el>
+        {navbar(&state.pool, user).await}
+        {game}
     )))
     .into_response()
 }
@@ -2,90 +2,312 @@ use super::*;
 use crate::{
     auth::get_user,
     components::{games_list, navbar},
-    models::User,
+    correspondence::{self, DAYS_PER_MOVE},
+    models::{CorrespondenceTurn, Game, User, UserRating},
+    rating,
+    stats::{self, Count, NamedRecord, OpeningStats, UserStats},
 };
 use axum::{
     extract::{Path, State},
     http::StatusCode,
+    Json,
 };
-use std::collections::HashMap;
+use serde::Serialize;
 
 pub async fn get(
     cookies: TypedHeader<headers::Cookie>,
     State(state): State<AppState>,
     Path(username): Path<String>,
 ) -> Result<Html<String>, (StatusCode, &'static str)> {
-    let user = sqlx::query_as!(User, "SELECT * FROM users WHERE username = ?", username)
+    let user: User = sqlx::query_as!(User, "SELECT * FROM users WHERE username = ?", username)
         .fetch_one(&state.pool)
         .await
         .map_err(|_| (StatusCode::NOT_FOUND, "User not found"))?
         .into();
 
-    let stats = get_stats(&user, &state).await;
-    let games = sqlx::query!(
+    let stats = stats::user_stats(&state.pool, user.id).await.unwrap();
+    let ratings = rating::user_ratings(&state.pool, user.id).await.unwrap();
+    let games = sqlx::query_as!(
+        Game,
         r#"
-        SELECT games.id, games.played_at, games.result, games.difficulty FROM games 
-        WHERE player = ? 
+        SELECT games.id, games.played_at, games.result, games.difficulty, games.bot_color,
+            white.username as "white?", black.username as "black?"
+        FROM games
+        LEFT JOIN users white ON games.white_player = white.id
+        LEFT JOIN users black ON games.black_player = black.id
+        WHERE (games.white_player = ? OR games.black_player = ?) AND games.result != '*'
         ORDER BY games.played_at DESC
         LIMIT 10"#,
+        user.id,
         user.id
     )
     .fetch_all(&state.pool)
     .await
-    .unwrap()
-    .into_iter()
-    .map(|row| crate::models::Game {
-        id: row.id,
-        played_at: row.played_at,
-        player: Some(user.username.clone()),
-        result: row.result,
-        difficulty: row.difficulty,
-    })
-    .collect::<Vec<_>>();
+    .unwrap();
+
+    // Players see the correspondence games waiting for them on their own
+    // page, and can challenge others from theirs
+    let viewer = get_user(&state.pool, cookies).await;
+    let correspondence = match &viewer {
+        Some(viewer) if viewer.id == user.id => your_turn_html(
+            correspondence::your_turn(&state.pool, user.id)
+                .await
+                .unwrap(),
+        ),
+        Some(_) => challenge_form(&user.username),
+        None => String::new(),
+    };
 
     Ok(Html(render_index(html! (
-        {navbar(get_user(&state.pool, cookies).await)}
+        {navbar(&state.pool, viewer).await}
         <div class="content">
             <h1>{&user.username}</h1>
+            {ratings_html(&user.username, ratings)}
             {stats_html(stats)}
+            {correspondence}
             <h2>"Games"</h2>
             {games_list(games, Some(&format!("/api/user_games?page=2&id={}", user.id)))}
         </div>
     ))))
 }
 
-fn stats_html(stats: [i64; 3]) -> String {
+fn your_turn_html(games: Vec<CorrespondenceTurn>) -> String {
+    if games.is_empty() {
+        return html!(
+            <h2>"Your turn"</h2>
+            <p>"No correspondence games are waiting for your move"</p>
+        );
+    }
+
+    let date_format =
+        time::format_description::parse("[month repr:short] [day], [hour]:[minute] UTC").unwrap();
+    html!(
+        <h2>"Your turn"</h2>
+        <table class="games">
+            <tr class="games-header">
+                <th style="padding: 5px 0px;">"Opponent"</th>
+                <th>"Move"</th>
+                <th>"Deadline"</th>
+            </tr>
+            {games.into_iter().map(|game| html! {
+                <tr class="game" onclick={format!("location.href='/correspondence/{}';", game.id)}>
+                    <td>{game.opponent}</td>
+                    <td>{game.ply / 2 + 1}</td>
+                    <td>{game.deadline.format(&date_format).unwrap()}</td>
+                </tr>
+            }).collect::<String>()}
+        </table>
+    )
+}
+
+fn challenge_form(username: &str) -> String {
+    html!(
+        <form action="/correspondence" method="post" class="gameopts">
+            <input type="hidden" name="opponent" value=username />
+            <button class="newgame" type="submit">"Correspondence challenge"</button>
+            <div class="divopt">
+                <p style="margin: 0">"Days per move:"</p>
+                <select name="days" class="difficulty">
+                    {DAYS_PER_MOVE.iter().map(|days| html!(<option value=days>{days}</option>)).collect::<String>()}
+                </select>
+            </div>
+            <div class="divopt">
+                <p style="margin: 0">"Your color:"</p>
+                <select name="color" class="difficulty">
+                    <option value="random">"Random"</option>
+                    <option value="white">"White"</option>
+                    <option value="black">"Black"</option>
+                </select>
+            </div>
+            <label>
+                <input type="checkbox" name="rated" />
+                " Rated"
+            </label>
+        </form>
+    )
+}
+
+fn ratings_html(username: &str, ratings: Vec<UserRating>) -> String {
+    if ratings.is_empty() {
+        return String::new();
+    }
+
+    html!(
+        <h2>"Ratings"</h2>
+        <table class="games">
+            <tr class="games-header">
+                <th style="padding: 5px 0px;">"Category"</th>
+                <th>"Rating"</th>
+                <th>"Games"</th>
+            </tr>
+            {ratings.into_iter().map(|r| html! {
+                <tr>
+                    <td>{r.category}</td>
+                    <td>{rating::display(r.rating, r.deviation)}</td>
+                    <td>{r.games}</td>
+                </tr>
+            }).collect::<String>()}
+        </table>
+        <rating-chart username={username}></rating-chart>
+    )
+}
+
+fn stats_html(stats: UserStats) -> String {
+    let by_color = [
+        NamedRecord {
+            name: "White".to_string(),
+            record: stats.white,
+        },
+        NamedRecord {
+            name: "Black".to_string(),
+            record: stats.black,
+        },
+    ];
+    let average_length = stats
+        .average_length
+        .map(|moves| format!("{:.1} moves", moves))
+        .unwrap_or_else(|| "-".to_string());
+
     html! (
         <div class="stats">
             <h2>"Stats"</h2>
             <p>
-                "Wins: " {stats[0]}
+                "Wins: " {stats.total.wins}
+            </p>
+            <p>
+                "Draws: " {stats.total.draws}
+            </p>
+            <p>
+                "Losses: " {stats.total.losses}
             </p>
             <p>
-                "Draws: " {stats[1]}
+                "Average game length: " {average_length}
             </p>
             <p>
-                "Losses: " {stats[2]}
+                "Longest win streak: " {stats.longest_win_streak}
             </p>
         </div>
+        {record_table("Color", by_color.into_iter())}
+        {record_table("Opponent", stats.by_difficulty.into_iter())}
+        {openings_table(stats.openings)}
+        {count_table("Termination", stats.terminations)}
+        {count_table("Month", stats.monthly)}
     )
 }
 
-async fn get_stats(user: &User, state: &AppState) -> [i64; 3] {
-    let stats = sqlx::query!(
-        r#"SELECT result, count(result) as count FROM games WHERE player = ? GROUP BY result ORDER BY result DESC"#,
-        user.id
+fn record_table(title: &str, records: impl Iterator<Item = NamedRecord>) -> String {
+    html!(
+        <table class="games" style="margin-bottom: 20px;">
+            <tr class="games-header">
+                <th style="padding: 5px 0px;">{title}</th>
+                <th>"Wins"</th>
+                <th>"Draws"</th>
+                <th>"Losses"</th>
+                <th>"Win rate"</th>
+            </tr>
+            {records.map(|NamedRecord { name, record: r }| html! {
+                <tr>
+                    <td>{name}</td>
+                    <td>{r.wins}</td>
+                    <td>{r.draws}</td>
+                    <td>{r.losses}</td>
+                    <td>{r.win_rate()}</td>
+                </tr>
+            }).collect::<String>()}
+        </table>
     )
-    .fetch_all(&state.pool)
-    .await
-    .unwrap()
-    .into_iter()
-    .map(|row| (row.result, row.count))
-    .collect::<HashMap<_, _>>();
-
-    [
-        stats.get("Win").copied().unwrap_or(0),
-        stats.get("Draw").copied().unwrap_or(0),
-        stats.get("Loss").copied().unwrap_or(0),
-    ]
+}
+
+fn openings_table(openings: Vec<OpeningStats>) -> String {
+    if openings.is_empty() {
+        return String::new();
+    }
+
+    html!(
+        <table class="games" style="margin-bottom: 20px;">
+            <tr class="games-header">
+                <th style="padding: 5px 0px;">"ECO"</th>
+                <th>"Opening"</th>
+                <th>"Games"</th>
+                <th>"Win rate"</th>
+            </tr>
+            {openings.into_iter().map(|o| html! {
+                <tr>
+                    <td>{o.eco}</td>
+                    <td>{o.name}</td>
+                    <td>{o.record.games()}</td>
+                    <td>{o.record.win_rate()}</td>
+                </tr>
+            }).collect::<String>()}
+        </table>
+    )
+}
+
+fn count_table(title: &str, counts: Vec<Count>) -> String {
+    if counts.is_empty() {
+        return String::new();
+    }
+
+    html!(
+        <table class="games" style="margin-bottom: 20px;">
+            <tr class="games-header">
+                <th style="padding: 5px 0px;">{title}</th>
+                <th>"Games"</th>
+            </tr>
+            {counts.into_iter().map(|c| html! {
+                <tr>
+                    <td>{c.name}</td>
+                    <td>{c.count}</td>
+                </tr>
+            }).collect::<String>()}
+        </table>
+    )
+}
+
+// The user's statistics, as shown on their page
+pub async fn stats_json(
+    State(state): State<AppState>,
+    Path(username): Path<String>,
+) -> Result<Json<UserStats>, StatusCode> {
+    let user = sqlx::query_as!(User, "SELECT * FROM users WHERE username = ?", username)
+        .fetch_one(&state.pool)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let stats = stats::user_stats(&state.pool, user.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(stats))
+}
+
+#[derive(Serialize)]
+pub struct RatingHistoryJson {
+    category: String,
+    // Seconds since the epoch
+    time: i64,
+    rating: f64,
+}
+
+// The user's ratings over time, for charting
+pub async fn rating_history(
+    State(state): State<AppState>,
+    Path(username): Path<String>,
+) -> Result<Json<Vec<RatingHistoryJson>>, StatusCode> {
+    let user = sqlx::query_as!(User, "SELECT * FROM users WHERE username = ?", username)
+        .fetch_one(&state.pool)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let history = rating::history(&state.pool, user.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(
+        history
+            .into_iter()
+            .map(|point| RatingHistoryJson {
+                category: point.category,
+                time: point.recorded_at.unix_timestamp(),
+                rating: point.rating.round(),
+            })
+            .collect(),
+    ))
 }
@@ -0,0 +1,22 @@
+use axum::extract::ws::{Message, WebSocket};
+use serde::{de::DeserializeOwned, Serialize};
+
+// JSON messages over the WebSockets of bot games, the lobby and live games
+
+pub async fn send(socket: &mut WebSocket, msg: &impl Serialize) -> anyhow::Result<()> {
+    let text = serde_json::to_string(msg)?;
+    socket.send(Message::Text(text)).await?;
+    Ok(())
+}
+
+// The next message of the client, `None` once it disconnected
+pub async fn receive<T: DeserializeOwned>(socket: &mut WebSocket) -> anyhow::Result<Option<T>> {
+    while let Some(msg) = socket.recv().await {
+        match msg? {
+            Message::Text(text) => return Ok(Some(serde_json::from_str(&text)?)),
+            Message::Close(_) => return Ok(None),
+            _ => {}
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,204 @@
+use crate::{analysis::parse_moves, models::StatsGame};
+use chess_lib::eco;
+use serde::Serialize;
+use sqlx::{MySql, Pool};
+use std::collections::HashMap;
+
+// Statistics of a user, computed from their finished games
+
+const TOP_OPENINGS: usize = 10;
+
+// Games won, drawn and lost
+#[derive(Default, Clone, Copy, Serialize)]
+pub struct Record {
+    pub wins: i64,
+    pub draws: i64,
+    pub losses: i64,
+}
+
+impl Record {
+    // `result` as stored, from white's view, of a game the user played as
+    // `color`
+    fn add(&mut self, result: &str, color: &str) {
+        match (result, color) {
+            ("1-0", "White") | ("0-1", "Black") => self.wins += 1,
+            ("0-1", "White") | ("1-0", "Black") => self.losses += 1,
+            ("1/2-1/2", _) => self.draws += 1,
+            _ => {}
+        }
+    }
+
+    pub fn games(&self) -> i64 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn win_rate(&self) -> String {
+        if self.games() == 0 {
+            return "-".to_string();
+        }
+        format!("{:.0}%", self.wins as f64 * 100.0 / self.games() as f64)
+    }
+}
+
+#[derive(Serialize)]
+pub struct NamedRecord {
+    pub name: String,
+    #[serde(flatten)]
+    pub record: Record,
+}
+
+#[derive(Serialize)]
+pub struct OpeningStats {
+    pub eco: &'static str,
+    pub name: &'static str,
+    #[serde(flatten)]
+    pub record: Record,
+}
+
+#[derive(Serialize)]
+pub struct Count {
+    pub name: String,
+    pub count: i64,
+}
+
+#[derive(Serialize)]
+pub struct UserStats {
+    pub total: Record,
+    pub white: Record,
+    pub black: Record,
+    // By the bot level played against, from the weakest, then the rest
+    pub by_difficulty: Vec<NamedRecord>,
+    // In full moves
+    pub average_length: Option<f64>,
+    // The most played first
+    pub openings: Vec<OpeningStats>,
+    pub longest_win_streak: i64,
+    // How games ended, the most common first
+    pub terminations: Vec<Count>,
+    // Games per month, "2024-06", oldest first
+    pub monthly: Vec<Count>,
+}
+
+pub async fn user_stats(pool: &Pool<MySql>, user_id: i32) -> anyhow::Result<UserStats> {
+    let games = sqlx::query_as!(
+        StatsGame,
+        r#"
+        SELECT id, played_at, result, termination, difficulty, moves,
+            CASE WHEN white_player = ? THEN 'White' ELSE 'Black' END as "color!"
+        FROM games
+        WHERE (white_player = ? OR black_player = ?) AND result != '*'
+        ORDER BY played_at, id"#,
+        user_id,
+        user_id,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Replaying the openings blocks, keep it off the async runtime
+    tokio::task::spawn_blocking(move || compute(games)).await?
+}
+
+fn compute(games: Vec<StatsGame>) -> anyhow::Result<UserStats> {
+    let month_format = time::format_description::parse("[year]-[month]")?;
+
+    let mut total = Record::default();
+    let mut colors = [Record::default(); 2];
+    let mut by_difficulty: HashMap<String, Record> = HashMap::new();
+    let mut openings: HashMap<(&'static str, &'static str), Record> = HashMap::new();
+    let mut terminations: HashMap<String, i64> = HashMap::new();
+    let mut monthly: Vec<Count> = Vec::new();
+    let mut plies = 0;
+    let mut counted = 0;
+    let (mut streak, mut longest_win_streak) = (0, 0);
+
+    for game in &games {
+        // A bad row shouldn't take the whole page down, leave it out
+        let moves = match parse_moves(game.moves.clone()) {
+            Ok(moves) => moves,
+            Err(e) => {
+                tracing::warn!("Game {} left out of the statistics: {:#}", game.id, e);
+                continue;
+            }
+        };
+
+        let (result, color) = (game.result.as_str(), game.color.as_str());
+        let mut record = Record::default();
+        record.add(result, color);
+        let add = |r: &mut Record| {
+            r.wins += record.wins;
+            r.draws += record.draws;
+            r.losses += record.losses;
+        };
+
+        add(&mut total);
+        add(&mut colors[(color == "Black") as usize]);
+        let opponent = game
+            .difficulty
+            .clone()
+            .unwrap_or_else(|| "Players".to_string());
+        add(by_difficulty.entry(opponent).or_default());
+
+        plies += moves.len();
+        if let Some(opening) = eco::classify(&moves) {
+            add(openings.entry((opening.eco, opening.name)).or_default());
+        }
+
+        let termination = game
+            .termination
+            .clone()
+            .unwrap_or_else(|| "Unknown".to_string());
+        *terminations.entry(termination).or_default() += 1;
+
+        let month = game.played_at.format(&month_format)?;
+        match monthly.last_mut() {
+            Some(last) if last.name == month => last.count += 1,
+            _ => monthly.push(Count {
+                name: month,
+                count: 1,
+            }),
+        }
+
+        counted += 1;
+        streak = if record.wins > 0 { streak + 1 } else { 0 };
+        longest_win_streak = longest_win_streak.max(streak);
+    }
+
+    let mut by_difficulty: Vec<_> = by_difficulty
+        .into_iter()
+        .map(|(name, record)| NamedRecord { name, record })
+        .collect();
+    by_difficulty.sort_by_key(|r| {
+        let elo = r
+            .name
+            .strip_suffix(" Elo")
+            .and_then(|elo| elo.parse::<u32>().ok());
+        (elo.unwrap_or(u32::MAX), r.name.clone())
+    });
+
+    let mut openings: Vec<_> = openings
+        .into_iter()
+        .map(|((eco, name), record)| OpeningStats { eco, name, record })
+        .collect();
+    openings.sort_by_key(|o| (-o.record.games(), o.eco, o.name));
+    openings.truncate(TOP_OPENINGS);
+
+    let mut terminations: Vec<_> = terminations
+        .into_iter()
+        .map(|(name, count)| Count { name, count })
+        .collect();
+    terminations.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(UserStats {
+        total,
+        white: colors[0],
+        black: colors[1],
+        by_difficulty,
+        average_length: (counted > 0)
+            .then(|| (plies as f64 / counted as f64 / 2.0 * 10.0).round() / 10.0),
+        openings,
+        longest_win_streak,
+        terminations,
+        monthly,
+    })
+}
@@ -0,0 +1,143 @@
+use crate::{rating, router::AppState};
+use chess_lib::{game::Game, piece::Color};
+use sqlx::{MySql, MySqlConnection, Pool, Transaction};
+
+// Writing played games to the `games` table. Results are stored from
+// white's view like in PGN: 1-0, 0-1, 1/2-1/2, or * while the game is going
+// on.
+
+pub const ONGOING: &str = "*";
+
+pub fn result(winner: Option<Color>) -> &'static str {
+    match winner {
+        Some(Color::White) => "1-0",
+        Some(Color::Black) => "0-1",
+        None => "1/2-1/2",
+    }
+}
+
+// Who plays a game, players are user ids and `None` for guests and the bot
+pub struct NewGame<'a> {
+    pub white: Option<i32>,
+    pub black: Option<i32>,
+    pub bot_color: Option<Color>,
+    pub difficulty: Option<&'a str>,
+    pub rated: bool,
+    pub time_control: Option<&'a str>,
+}
+
+// Adds a game that's about to start, completed by `finish_game`
+pub async fn create_game(pool: &Pool<MySql>, game: &NewGame<'_>) -> sqlx::Result<i32> {
+    insert_game(&mut *pool.acquire().await?, game).await
+}
+
+async fn insert_game(conn: &mut MySqlConnection, game: &NewGame<'_>) -> sqlx::Result<i32> {
+    // Stored the way clients send them, a JSON encoded string
+    let moves = serde_json::Value::String("[]".to_string());
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO games (white_player, black_player, bot_color, moves, result, difficulty, rated, time_control)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)"#,
+        game.white,
+        game.black,
+        game.bot_color.map(|c| format!("{:?}", c)),
+        moves,
+        ONGOING,
+        game.difficulty,
+        game.rated,
+        game.time_control
+    )
+    .execute(conn)
+    .await?;
+    Ok(inserted.last_insert_id() as i32)
+}
+
+// Stores the moves and result of a finished game and rates it
+async fn write_result(
+    tx: &mut Transaction<'_, MySql>,
+    id: i32,
+    game: &Game,
+    result: &str,
+    termination: &str,
+) -> sqlx::Result<()> {
+    let moves = serde_json::Value::String(serde_json::to_string(&game.moves).unwrap());
+    sqlx::query!(
+        "UPDATE games SET moves = ?, final_fen = ?, result = ?, termination = ? WHERE id = ?",
+        moves,
+        game.fen(),
+        result,
+        termination,
+        id
+    )
+    .execute(&mut **tx)
+    .await?;
+    rating::rate_game(tx, id).await
+}
+
+// Stores the moves and result of a finished game, rates it and queues its
+// analysis
+pub async fn finish_game(
+    state: &AppState,
+    id: i32,
+    game: &Game,
+    result: &str,
+    termination: &str,
+) -> sqlx::Result<()> {
+    let mut tx = state.pool.begin().await?;
+    write_result(&mut tx, id, game, result, termination).await?;
+    tx.commit().await?;
+    queue_analysis(state, id).await;
+    Ok(())
+}
+
+// Stores a game that's already over as part of `tx`, the caller queues
+// its analysis once committed
+pub async fn insert_finished(
+    tx: &mut Transaction<'_, MySql>,
+    new_game: &NewGame<'_>,
+    game: &Game,
+    result: &str,
+    termination: &str,
+) -> sqlx::Result<i32> {
+    let id = insert_game(tx, new_game).await?;
+    write_result(tx, id, game, result, termination).await?;
+    Ok(id)
+}
+
+// Stores a game that's already over
+pub async fn save_game(
+    state: &AppState,
+    new_game: &NewGame<'_>,
+    game: &Game,
+    result: &str,
+    termination: &str,
+) -> sqlx::Result<i32> {
+    let mut tx = state.pool.begin().await?;
+    let id = insert_finished(&mut tx, new_game, game, result, termination).await?;
+    tx.commit().await?;
+    queue_analysis(state, id).await;
+    Ok(id)
+}
+
+pub async fn queue_analysis(state: &AppState, id: i32) {
+    if let Err(e) = state.analysis.enqueue(&state.pool, id).await {
+        tracing::error!("Failed to queue analysis of game {}: {}", id, e);
+    }
+}
+
+// Removes a game that was aborted before it was finished
+pub async fn delete_game(pool: &Pool<MySql>, id: i32) -> sqlx::Result<()> {
+    sqlx::query!("DELETE FROM games WHERE id = ? AND result = ?", id, ONGOING)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Live games are only kept in memory, the ones a restart interrupted can't
+// be finished
+pub async fn delete_unfinished(pool: &Pool<MySql>) -> sqlx::Result<u64> {
+    let deleted = sqlx::query!("DELETE FROM games WHERE result = ?", ONGOING)
+        .execute(pool)
+        .await?;
+    Ok(deleted.rows_affected())
+}
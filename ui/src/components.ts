@@ -1,5 +1,7 @@
 import "htmx.org";
 
 import "./components/board";
+import "./components/chart";
 import "./components/game";
+import "./components/lobby";
 import "./components/moves";
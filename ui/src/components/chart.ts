@@ -0,0 +1,126 @@
+import { LitElement, css, html, svg } from "lit";
+import { customElement, property, state } from "lit/decorators.js";
+
+type RatingPoint = {
+  category: string;
+  // Seconds since the epoch
+  time: number;
+  rating: number;
+};
+
+const WIDTH = 600;
+const HEIGHT = 250;
+const PADDING = 40;
+const COLORS: { [category: string]: string } = {
+  bullet: "#f97316",
+  blitz: "#0ea5e9",
+  rapid: "#22c55e",
+  classical: "#a855f7",
+  correspondence: "#eab308",
+};
+
+// A user's ratings over time, one line per category
+@customElement("rating-chart")
+export class RatingChart extends LitElement {
+  @property()
+  username: string = "";
+
+  @state()
+  series: Map<string, RatingPoint[]> = new Map();
+
+  connectedCallback() {
+    super.connectedCallback();
+    fetch(`/users/${this.username}/rating_history`).then((res) =>
+      res.json().then((points: RatingPoint[]) => {
+        const series = new Map<string, RatingPoint[]>();
+        for (const p of points) {
+          if (!series.has(p.category)) series.set(p.category, []);
+          series.get(p.category)!.push(p);
+        }
+        this.series = series;
+      }),
+    );
+  }
+
+  render() {
+    const points = [...this.series.values()].flat();
+    if (points.length < 2) return html``;
+
+    const times = points.map((p) => p.time);
+    const ratings = points.map((p) => p.rating);
+    const [t0, t1] = [Math.min(...times), Math.max(...times)];
+    // Some room above and below, rounded to hundreds for the labels
+    const r0 = Math.floor((Math.min(...ratings) - 50) / 100) * 100;
+    const r1 = Math.ceil((Math.max(...ratings) + 50) / 100) * 100;
+    const x = (t: number) =>
+      PADDING + ((t - t0) / Math.max(t1 - t0, 1)) * (WIDTH - 2 * PADDING);
+    const y = (r: number) =>
+      HEIGHT - PADDING - ((r - r0) / (r1 - r0)) * (HEIGHT - 2 * PADDING);
+
+    const grid = [];
+    for (let r = r0; r <= r1; r += 100) {
+      grid.push(svg`
+        <line x1=${PADDING} x2=${WIDTH - PADDING} y1=${y(r)} y2=${y(r)} class="grid" />
+        <text x=${PADDING - 5} y=${y(r) + 4} text-anchor="end">${r}</text>
+      `);
+    }
+    const lines = [...this.series].map(
+      ([category, points]) => svg`
+        <polyline
+          points=${points.map((p) => `${x(p.time)},${y(p.rating)}`).join(" ")}
+          stroke=${COLORS[category] ?? "white"}
+        />
+      `,
+    );
+
+    return html`
+      <svg viewBox="0 0 ${WIDTH} ${HEIGHT}" width=${WIDTH} height=${HEIGHT}>
+        ${grid} ${lines}
+      </svg>
+      <div class="legend">
+        ${[...this.series.keys()].map(
+          (category) =>
+            html`<span style="color: ${COLORS[category] ?? "white"}"
+              >${category}</span
+            >`,
+        )}
+      </div>
+    `;
+  }
+
+  static styles = css`
+    :host {
+      display: flex;
+      flex-direction: column;
+      align-items: center;
+      margin: 20px 0px;
+    }
+
+    polyline {
+      fill: none;
+      stroke-width: 2;
+    }
+
+    .grid {
+      stroke: #525252;
+      stroke-width: 1;
+    }
+
+    text {
+      fill: #a3a3a3;
+      font-size: 12px;
+    }
+
+    .legend {
+      display: flex;
+      gap: 15px;
+      font-size: 14px;
+    }
+  `;
+}
+
+declare global {
+  interface HTMLElementTagNameMap {
+    "rating-chart": RatingChart;
+  }
+}
@@ -5,7 +5,6 @@ import {
   WasmGame,
   WasmMove,
   opposite_color,
-  Difficulty,
 } from "chess-lib";
 import { LitElement, css, html } from "lit";
 import { customElement, property, state } from "lit/decorators.js";
@@ -15,20 +14,14 @@ const bot_worker = new ComlinkWorker<typeof import("../worker")>(
   new URL("../worker.ts", import.meta.url),
 );
 
-function difficulty(d: number) {
-  if (d == 0) {
-    return Difficulty.Easy;
-  } else if (d == 1) {
-    return Difficulty.Medium;
-  } else {
-    return Difficulty.Hard;
-  }
-}
-
-async function run_worker(board: WasmBoard, diff: number): Promise<WasmMove> {
+async function run_worker(
+  board: WasmBoard,
+  elo: number,
+  ply: number,
+): Promise<[WasmMove, string]> {
   const board_json = board.to_json();
-  const m = await bot_worker.bot_turn(board_json, difficulty(diff));
-  return WasmMove.from_json(m);
+  const m = await bot_worker.bot_turn(board_json, elo, ply);
+  return [WasmMove.from_json(m.move), m.score];
 }
 
 @customElement("game-el")
@@ -43,12 +36,56 @@ export class GameEl extends LitElement {
   @property()
   player_color: string = "white";
   @property({ type: Number })
-  difficulty: number = 0;
+  elo: number = 1200;
+
+  // Play against the bot on the server, which also keeps the clocks,
+  // instead of running it in a worker
+  @property({ type: Boolean })
+  server_hosted: boolean = false;
+  // "minutes+increment", empty for no clocks
+  @property()
+  time_control: string = "";
+  // Server hosted games against the bot can count for the player's rating
+  @property({ type: Boolean })
+  rated: boolean = false;
+  socket?: WebSocket;
+
+  // A game against another player going on on the server, which sends
+  // the whole game on connecting
+  @property()
+  live_game_id: string = "";
+  // A correspondence game, moves are sent over HTTP
+  @property()
+  correspondence_id: string = "";
+  // When the player to move loses a correspondence game
+  @state()
+  deadline: string = "";
+  // Watching someone else's game
+  spectating: boolean = false;
+  @state()
+  players: [string, string] | null = null;
+  @state()
+  opponent_gone: boolean = false;
+  // How many others are watching a live game
+  @state()
+  spectators: number = 0;
+  // The server said the game is over
+  finished: boolean = false;
+
+  // Milliseconds left for white and black when they were last updated
+  @state()
+  clocks: [number, number] | null = null;
+  clocks_updated: number = 0;
+  clock_timer?: number;
 
   drawn_board: WasmBoard = new WasmBoard();
   drawn_histoy: boolean = false;
   drawn_ply: number = 0;
 
+  // Evaluation of the bot's last move, from the bot's point of view
+  @state()
+  bot_score: string = "";
+
   constructor() {
     super();
     this.drawn_board = this.game.board();
@@ -62,7 +99,11 @@ export class GameEl extends LitElement {
 
   bot_color: Color = Color.Black;
   player_moves() {
-    if (this.game.side_to_move() == this.bot_color || this.drawn_histoy) {
+    if (
+      this.game.side_to_move() == this.bot_color ||
+      this.drawn_histoy ||
+      this.spectating
+    ) {
       return [];
     } else {
       return this.game.legal_moves();
@@ -71,8 +112,13 @@ export class GameEl extends LitElement {
 
   bot_turn() {
     if (this.game.game_state() != GameState.InProgress) return;
+    // The server moves on its own after receiving the player's move, or
+    // the other player does
+    if (this.played_on_server()) return;
 
-    run_worker(this.game.board(), this.difficulty).then((m) => {
+    const ply = this.game.move_history().length;
+    run_worker(this.game.board(), this.elo, ply).then(([m, score]) => {
+      this.bot_score = score;
       this.game.make_move(m);
       this.drawn_board = this.game.board();
       this.drawn_histoy = false;
@@ -106,7 +152,219 @@ export class GameEl extends LitElement {
       }
       this.game_over_div.value!.style.display = "block";
 
-      this.send_game_to_server();
+      if (!this.played_on_server()) {
+        this.send_game_to_server();
+      }
+    }
+  }
+
+  connect_server() {
+    const protocol = location.protocol == "https:" ? "wss:" : "ws:";
+    this.socket = new WebSocket(`${protocol}//${location.host}/bot-game/ws`);
+    const [minutes, increment] =
+      this.time_control === ""
+        ? [0, 0]
+        : this.time_control.split("+").map(Number);
+
+    this.socket.onopen = () => {
+      this.socket!.send(
+        JSON.stringify({
+          type: "start",
+          elo: this.elo,
+          color: Color[opposite_color(this.bot_color)],
+          time: minutes * 60,
+          increment: increment,
+          rated: this.rated,
+        }),
+      );
+    };
+    this.socket.onmessage = (e) => {
+      this.handle_server_message(JSON.parse(e.data));
+    };
+  }
+
+  played_on_server() {
+    return (
+      this.server_hosted ||
+      this.live_game_id !== "" ||
+      this.correspondence_id !== ""
+    );
+  }
+
+  connect_live() {
+    const protocol = location.protocol == "https:" ? "wss:" : "ws:";
+    this.socket = new WebSocket(
+      `${protocol}//${location.host}/games/${this.live_game_id}/ws`,
+    );
+    this.socket.onmessage = (e) => {
+      this.handle_server_message(JSON.parse(e.data));
+    };
+    // Reconnect to pick the game up where it is
+    this.socket.onclose = () => {
+      if (!this.finished) {
+        setTimeout(() => this.connect_live(), 1000);
+      }
+    };
+  }
+
+  // Replays the moves of a game kept on the server, after joining a live
+  // game or loading a correspondence game
+  load_server_game(msg: any) {
+    this.game = new WasmGame();
+    for (const uci of msg.moves) {
+      const m = this.game.legal_moves().find((m) => m.uci() == uci);
+      if (m === undefined) {
+        console.error("Server sent an illegal move:", uci);
+        return;
+      }
+      this.game.make_move(m);
+    }
+    this.drawn_board = this.game.board();
+    this.drawn_histoy = false;
+    this.drawn_ply = this.game.move_history().length;
+    this.players = [msg.white, msg.black];
+
+    this.spectating = msg.color === null;
+    if (!this.spectating) {
+      this.player_color = msg.color == "White" ? "white" : "black";
+      this.bot_color = msg.color == "White" ? Color.Black : Color.White;
+      // Only live games tell who is connected
+      if (msg.white_connected !== undefined) {
+        this.opponent_gone =
+          msg.color == "White" ? !msg.black_connected : !msg.white_connected;
+      }
+    }
+    this.spectators = msg.spectators ?? 0;
+  }
+
+  load_correspondence() {
+    fetch(`/api/correspondence/${this.correspondence_id}`).then((res) =>
+      res.json().then((data) => this.apply_correspondence(data)),
+    );
+  }
+
+  apply_correspondence(data: any) {
+    this.load_server_game(data);
+    this.deadline = data.deadline;
+    this.finished = data.game_id !== null;
+    this.requestUpdate();
+  }
+
+  // Sends an action on the correspondence game and shows the game after it
+  post_correspondence(action: string, body?: object) {
+    fetch(`/api/correspondence/${this.correspondence_id}/${action}`, {
+      method: "POST",
+      headers: {
+        "Content-Type": "application/json",
+      },
+      body: JSON.stringify(body ?? {}),
+    }).then((res) => {
+      if (!res.ok) {
+        res.text().then((err) => console.error("Server:", err));
+        this.load_correspondence();
+        return;
+      }
+      res.json().then((data) => {
+        this.apply_correspondence(data);
+        // Games ended without mate or draw on the board are shown finished
+        if (
+          data.game_id !== null &&
+          this.game.game_state() == GameState.InProgress
+        ) {
+          location.href = `/games/${data.game_id}`;
+        }
+      });
+    });
+  }
+
+  handle_server_message(msg: any) {
+    if (msg.white_ms != null && msg.black_ms != null) {
+      this.clocks = [msg.white_ms, msg.black_ms];
+      this.clocks_updated = Date.now();
+      if (this.clock_timer === undefined) {
+        this.clock_timer = setInterval(() => this.requestUpdate(), 200);
+      }
+    }
+
+    if (msg.type == "state") {
+      this.load_server_game(msg);
+    } else if (msg.type == "move") {
+      // Players get their own moves back
+      if (msg.ply !== undefined && msg.ply < this.game.move_history().length) {
+        this.requestUpdate();
+        return;
+      }
+      const m = this.game.legal_moves().find((m) => m.uci() == msg.move);
+      if (m === undefined) {
+        console.error("Server sent an illegal move:", msg.move);
+        return;
+      }
+      this.game.make_move(m);
+      this.drawn_board = this.game.board();
+      this.drawn_histoy = false;
+      this.drawn_ply = this.game.move_history().length;
+      this.handle_game_state_change();
+    } else if (msg.type == "presence") {
+      if (
+        !this.spectating &&
+        Color[msg.color as keyof typeof Color] == this.bot_color
+      ) {
+        this.opponent_gone = !msg.connected;
+      }
+    } else if (msg.type == "spectators") {
+      this.spectators = msg.count;
+    } else if (msg.type == "error") {
+      console.error("Server:", msg.message);
+    } else if (msg.type == "game_over") {
+      this.finished = true;
+      clearInterval(this.clock_timer);
+      this.clock_timer = undefined;
+      this.game_over_text.value!.innerText = `Game Over! ${msg.result} (${msg.reason})`;
+      this.game_over_div.value!.style.display = "block";
+    } else if (msg.type == "aborted") {
+      this.finished = true;
+      clearInterval(this.clock_timer);
+      this.clock_timer = undefined;
+      this.game_over_text.value!.innerText = "Game aborted";
+      this.game_over_div.value!.style.display = "block";
+    }
+    this.requestUpdate();
+  }
+
+  // The side to move's clock runs down between server updates
+  clock_text(color: Color) {
+    if (this.clocks === null) return "";
+    let ms = this.clocks[color];
+    if (
+      this.clock_timer !== undefined &&
+      this.game.side_to_move() == color &&
+      this.game.game_state() == GameState.InProgress
+    ) {
+      ms -= Date.now() - this.clocks_updated;
+    }
+    const seconds = Math.max(0, Math.ceil(ms / 1000));
+    const pad = (n: number) => n.toString().padStart(2, "0");
+    return `${Color[color]} ${Math.floor(seconds / 60)}:${pad(seconds % 60)}`;
+  }
+
+  // Correspondence games can be called off before both players moved
+  abort() {
+    fetch(`/api/correspondence/${this.correspondence_id}/abort`, {
+      method: "POST",
+    }).then((res) => {
+      if (res.ok) {
+        location.href = "/";
+      } else {
+        res.text().then((err) => console.error("Server:", err));
+      }
+    });
+  }
+
+  resign() {
+    if (this.correspondence_id !== "") {
+      this.post_correspondence("resign");
+    } else {
+      this.socket?.send(JSON.stringify({ type: "resign" }));
     }
   }
 
@@ -128,17 +386,39 @@ export class GameEl extends LitElement {
       body: JSON.stringify({
         result: result,
         moves: this.game.moves_server(),
-        difficulty: this.difficulty,
+        elo: this.elo,
+        color: Color[opposite_color(this.bot_color)],
       }),
+    }).then((res) => {
+      if (!res.ok) {
+        res.text().then((err) => console.error("Game was rejected:", err));
+      }
     });
   }
 
   reset() {
+    if (this.live_game_id !== "") {
+      location.href = "/lobby";
+      return;
+    }
+    if (this.correspondence_id !== "") {
+      location.href = "/";
+      return;
+    }
     this.game = new WasmGame();
     this.game_over_div.value!.style.display = "none";
     this.drawn_board = this.game.board();
     this.drawn_histoy = false;
     this.drawn_ply = 0;
+    this.bot_score = "";
+    if (this.server_hosted) {
+      this.socket?.close();
+      clearInterval(this.clock_timer);
+      this.clock_timer = undefined;
+      this.clocks = null;
+      this.finished = false;
+      this.connect_server();
+    }
     this.requestUpdate();
   }
 
@@ -146,7 +426,13 @@ export class GameEl extends LitElement {
     if (this.ran_setup) return;
     this.ran_setup = true;
 
-    if (this.bot_color == Color.White) {
+    if (this.live_game_id !== "") {
+      this.connect_live();
+    } else if (this.correspondence_id !== "") {
+      this.load_correspondence();
+    } else if (this.server_hosted) {
+      this.connect_server();
+    } else if (this.bot_color == Color.White) {
       setTimeout(() => {
         this.bot_turn();
       }, 2000);
@@ -182,6 +468,13 @@ export class GameEl extends LitElement {
               this.handle_game_state_change();
               this.requestUpdate();
 
+              const history = this.game.move_history();
+              const uci = history[history.length - 1].uci();
+              if (this.correspondence_id !== "") {
+                this.post_correspondence("move", { move: uci });
+              } else if (this.played_on_server()) {
+                this.socket?.send(JSON.stringify({ type: "move", move: uci }));
+              }
               this.bot_turn();
             }}
             .flip=${this.player_color == "white"}
@@ -201,6 +494,38 @@ export class GameEl extends LitElement {
             .drawn_ply=${this.drawn_ply}
           ></moves-el>
         </div>
+        <p class="bot-score">
+          ${this.bot_score === "" ? "" : `Bot evaluation: ${this.bot_score}`}
+          ${this.players === null
+            ? ""
+            : `${this.players[0]} vs ${this.players[1]}`}
+          ${this.opponent_gone ? " · Opponent disconnected" : ""}
+          ${this.spectators > 0 ? ` · ${this.spectators} watching` : ""}
+          ${this.deadline !== "" && !this.finished
+            ? ` · Move by ${new Date(this.deadline).toLocaleString()}`
+            : ""}
+        </p>
+        ${this.played_on_server() &&
+        !this.spectating &&
+        !this.finished &&
+        this.game.game_state() == GameState.InProgress
+          ? html`<button class="game-over-button" @click=${this.resign}>
+              Resign
+            </button>`
+          : ""}
+        ${this.correspondence_id !== "" &&
+        !this.spectating &&
+        !this.finished &&
+        this.game.move_history().length < 2
+          ? html`<button class="game-over-button" @click=${this.abort}>
+              Abort
+            </button>`
+          : ""}
+        <p class="bot-score">
+          ${this.clocks === null
+            ? ""
+            : `${this.clock_text(Color.White)} · ${this.clock_text(Color.Black)}`}
+        </p>
       </div>
       <div
         class="game-over-bg"
@@ -247,6 +572,11 @@ export class GameEl extends LitElement {
       height: 100vh;
     }
 
+    .bot-score {
+      font-family: monospace;
+      min-height: 1.2em;
+    }
+
     .game-over-bg {
       display: none;
       position: absolute;
@@ -0,0 +1,241 @@
+import { LitElement, css, html } from "lit";
+import { customElement, property, state } from "lit/decorators.js";
+
+type Challenge = {
+  id: number;
+  username: string;
+  time_control: string;
+  color: string | null;
+  rated: boolean;
+};
+
+// Open challenges of other players, kept up to date by the server, and the
+// options to open one or to look for any opponent
+@customElement("lobby-el")
+export class LobbyEl extends LitElement {
+  // Empty when not logged in
+  @property()
+  username: string = "";
+  // Comma separated, "minutes+increment"
+  @property()
+  time_controls: string = "";
+
+  @state()
+  challenges: Challenge[] = [];
+  // What the player is waiting for, if anything
+  @state()
+  waiting: "" | "challenge" | "queue" = "";
+  @state()
+  error: string = "";
+
+  socket?: WebSocket;
+  time_control: string = "";
+  color: string = "";
+  rated: boolean = false;
+
+  connectedCallback() {
+    super.connectedCallback();
+    this.time_control = this.time_controls.split(",")[0];
+
+    const protocol = location.protocol == "https:" ? "wss:" : "ws:";
+    this.socket = new WebSocket(`${protocol}//${location.host}/lobby/ws`);
+    this.socket.onmessage = (e) => {
+      const msg = JSON.parse(e.data);
+      if (msg.type == "challenges") {
+        this.challenges = msg.challenges;
+      } else if (msg.type == "matched") {
+        location.href = `/games/${msg.game_id}/live`;
+      } else if (msg.type == "error") {
+        this.error = msg.message;
+        this.waiting = "";
+      }
+    };
+    this.socket.onclose = () => {
+      this.error = "Lost the connection to the lobby";
+    };
+  }
+
+  disconnectedCallback() {
+    super.disconnectedCallback();
+    this.socket?.close();
+  }
+
+  send(msg: object) {
+    this.error = "";
+    this.socket?.send(JSON.stringify(msg));
+  }
+
+  create() {
+    this.send({
+      type: "create",
+      time_control: this.time_control,
+      color: this.color === "" ? null : this.color,
+      rated: this.rated,
+    });
+    this.waiting = "challenge";
+  }
+
+  queue() {
+    this.send({
+      type: "queue",
+      time_control: this.time_control,
+      rated: this.rated,
+    });
+    this.waiting = "queue";
+  }
+
+  cancel() {
+    this.send({ type: "cancel" });
+    this.waiting = "";
+  }
+
+  render_options() {
+    if (this.username === "") {
+      return html`<p>
+        <a href="/login">Log in</a> to play against other players
+      </p>`;
+    }
+    if (this.waiting !== "") {
+      return html`<div class="options">
+        <p>
+          ${this.waiting == "challenge"
+            ? "Waiting for someone to accept your challenge"
+            : "Looking for an opponent"}
+        </p>
+        <button @click=${this.cancel}>Cancel</button>
+      </div>`;
+    }
+
+    return html`<div class="options">
+      <select
+        @change=${(e: Event) =>
+          (this.time_control = (e.target as HTMLSelectElement).value)}
+      >
+        ${this.time_controls
+          .split(",")
+          .map((tc) => html`<option value=${tc}>${tc}</option>`)}
+      </select>
+      <select
+        @change=${(e: Event) =>
+          (this.color = (e.target as HTMLSelectElement).value)}
+      >
+        <option value="">Random</option>
+        <option value="White">White</option>
+        <option value="Black">Black</option>
+      </select>
+      <label>
+        <input
+          type="checkbox"
+          @change=${(e: Event) =>
+            (this.rated = (e.target as HTMLInputElement).checked)}
+        />
+        Rated
+      </label>
+      <button @click=${this.create}>Create challenge</button>
+      <button @click=${this.queue}>Quick pairing</button>
+    </div>`;
+  }
+
+  render_challenge(c: Challenge) {
+    const own = c.username == this.username;
+    return html`<tr>
+      <td>${c.username}</td>
+      <td>${c.time_control}</td>
+      <td>${c.color ?? "Random"}</td>
+      <td>${c.rated ? "Rated" : "Casual"}</td>
+      <td>
+        ${own || this.username === ""
+          ? ""
+          : html`<button
+              @click=${() => this.send({ type: "accept", id: c.id })}
+            >
+              Accept
+            </button>`}
+      </td>
+    </tr>`;
+  }
+
+  render() {
+    return html`
+      ${this.render_options()}
+      <p class="error">${this.error}</p>
+      <h2>Open challenges</h2>
+      ${this.challenges.length == 0
+        ? html`<p>No open challenges</p>`
+        : html`<table>
+            <tr class="header">
+              <th>Player</th>
+              <th>Clock</th>
+              <th>Their color</th>
+              <th></th>
+              <th></th>
+            </tr>
+            ${this.challenges.map((c) => this.render_challenge(c))}
+          </table>`}
+    `;
+  }
+
+  static styles = css`
+    :host {
+      display: flex;
+      flex-direction: column;
+      align-items: center;
+    }
+
+    a {
+      color: #0ea5e9;
+    }
+
+    .options {
+      display: flex;
+      align-items: center;
+      gap: 10px;
+    }
+
+    select {
+      background-color: #404040;
+      color: white;
+      border: 1px solid #525252;
+      border-radius: 5px;
+      padding: 5px;
+    }
+
+    button {
+      padding: 8px 12px;
+      border-radius: 5px;
+      background-color: #0ea5e9;
+      color: white;
+      border: none;
+      cursor: pointer;
+    }
+
+    .error {
+      color: red;
+      font-size: 12px;
+      min-height: 1.2em;
+    }
+
+    table {
+      text-align: center;
+      background-color: #404040;
+      border-spacing: 0px;
+    }
+
+    .header {
+      font-size: 14px;
+      background-color: #171717;
+    }
+
+    td,
+    th {
+      border-bottom: 1px solid #525252;
+      padding: 10px 10px;
+    }
+  `;
+}
+
+declare global {
+  interface HTMLElementTagNameMap {
+    "lobby-el": LobbyEl;
+  }
+}
@@ -1,7 +1,21 @@
-import { Difficulty, WasmBoard, bot_move } from "chess-lib";
+import { WasmBoard, book_move, bot_move_elo } from "chess-lib";
+
+export interface BotTurn {
+  move: string;
+  score: string;
+}
+
+export function bot_turn(
+  board_json: string,
+  elo: number,
+  ply: number,
+): BotTurn {
+  let book = book_move(WasmBoard.from_json(board_json), ply, elo);
+  if (book) {
+    return { move: book.to_json(), score: "Book" };
+  }
 
-export function bot_turn(board_json: string, difficulty: Difficulty): string {
   let b = WasmBoard.from_json(board_json);
-  let m = bot_move(b, difficulty);
-  return m.best_move.to_json();
+  let m = bot_move_elo(b, elo);
+  return { move: m.best_move.to_json(), score: m.score_text() };
 }